@@ -0,0 +1,447 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    AbiFormatError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::AbiFormatError { reason } =>
+                write!(f, "The abi could not be read. Reason: {}", reason),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the field of the input records holding the abi, a json abi document or that document encoded
+    // in a string, e.g. the `abi` of a contract record or of a hardhat artifact
+    #[serde(default = "field_default")]
+    pub field: String,
+}
+
+fn field_default() -> String {
+    "abi".to_string()
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters { field: field_default() }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // records without an abi are passed through as they are, and those whose abi can't be read with
+    // the reason in `decodeError`
+    let abi = match input.get(&params.field).map(normalize_abi) {
+        Option::Some(Ok(abi)) => abi,
+        Option::Some(Err(e)) => {
+            input.insert("decodeError".to_string(), Value::String(e.to_string()));
+            let result_json = serde_json::to_vec(&input)?;
+            lens_sdk::free_transport_buffer(ptr)?;
+            return Ok(Some(result_json));
+        }
+        Option::None => {
+            let result_json = serde_json::to_vec(&input)?;
+            lens_sdk::free_transport_buffer(ptr)?;
+            return Ok(Some(result_json));
+        }
+    };
+
+    // the signatures are emitted along with the contract they were declared by
+    let address = input.get("address").cloned().unwrap_or(Value::Null);
+    let empty: Vec<Value> = Vec::new();
+    let mut documents = abi
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| signature_document(i, item, &address))
+        .map(|document| serde_json::to_vec(&document))
+        .collect::<Result<VecDeque<Vec<u8>>, serde_json::Error>>()?;
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    // an abi declaring only a constructor, fallback or receive function has no signature to emit
+    let first = documents.pop_front();
+    PENDING.write()?.extend(documents);
+    match first {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
+
+// the signature document of an abi event, function or error, other items have no signature
+fn signature_document(index: usize, item: &Value, address: &Value) -> Option<Value> {
+    let kind = item["type"].as_str().unwrap_or("function");
+    if !["event", "function", "error"].contains(&kind) {
+        return Option::None;
+    }
+
+    let sig = signature(item);
+    let hash = keccak(&sig);
+    let mut document = serde_json::json!({
+        "address": address,
+        "abiIndex": index,
+        "kind": kind,
+        "name": item["name"],
+        "signature": sig,
+        "inputs": parameter_list(&item["inputs"], kind == "event"),
+    });
+    match kind {
+        "event" => {
+            // anonymous events have no topic0, their logs can only be matched on their contract
+            let anonymous = item["anonymous"].as_bool().unwrap_or(false);
+            document["anonymous"] = Value::Bool(anonymous);
+            document["topic0"] = match anonymous {
+                true => Value::Null,
+                false => Value::String(hash),
+            };
+        }
+        _ => {
+            // functions and errors are both selected by the first 4 bytes of their hashed signature
+            document["selector"] = Value::String(hash[..10].to_string());
+            if kind == "function" {
+                document["outputs"] = parameter_list(&item["outputs"], false);
+                document["stateMutability"] = item.get("stateMutability").cloned().unwrap_or(Value::Null);
+            }
+        }
+    }
+    Option::Some(document)
+}
+
+// the parameters of an abi item with their canonical types, e.g. `(uint256,address)[]` for an
+// array of tuples, keeping the components of tuples for the names of their members, only the
+// parameters of events may be indexed
+fn parameter_list(params: &Value, event: bool) -> Value {
+    let empty: Vec<Value> = Vec::new();
+    let list: Vec<Value> = params
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .map(|param| {
+            let mut document = serde_json::json!({
+                "name": param["name"].as_str().unwrap_or_default(),
+                "type": canonical_type(param),
+            });
+            if event {
+                document["indexed"] = Value::Bool(param["indexed"].as_bool().unwrap_or(false));
+            }
+            if let Option::Some(internal_type) = param.get("internalType").filter(|t| t.is_string()) {
+                document["internalType"] = internal_type.clone();
+            }
+            if param.get("components").is_some() {
+                document["components"] = parameter_list(&param["components"], false);
+            }
+            document
+        })
+        .collect();
+    Value::Array(list)
+}
+
+// build the signature string of an abi item, e.g. "Transfer(address,address,uint256)"
+fn signature(item: &Value) -> String {
+    let empty: Vec<Value> = Vec::new();
+    let types: Vec<String> = item["inputs"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .map(canonical_type)
+        .collect();
+
+    format!("{}({})", item["name"].as_str().unwrap_or_default(), types.join(","))
+}
+
+// the type of an abi parameter as it is hashed in signatures, tuples are spelled out as their
+// component types and aliases under their explicit width, e.g. `(uint256,address)[]`
+fn canonical_type(param: &Value) -> String {
+    let typ = param["type"].as_str().unwrap_or_default();
+    let suffix_at = typ.find('[').unwrap_or(typ.len());
+    let (base, suffix) = typ.split_at(suffix_at);
+    let base = match base {
+        "tuple" => {
+            let empty: Vec<Value> = Vec::new();
+            let components: Vec<String> = param["components"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .map(canonical_type)
+                .collect();
+            format!("({})", components.join(","))
+        }
+        "uint" | "int" => format!("{}256", base),
+        "fixed" | "ufixed" => format!("{}128x18", base),
+        _ => base.to_string(),
+    };
+    format!("{}{}", base, suffix)
+}
+
+// the hashed signature, the topic0 of events and, in its first 4 bytes, the selector of functions and errors
+fn keccak(sig: &str) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(sig.as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+// normalize the accepted abi formats to a single json abi array
+fn normalize_abi(abi: &Value) -> Result<Value, ModuleError> {
+    match abi {
+        // a document encoded in a string, e.g. the raw json abi or a copy-pasted artifact
+        Value::String(document) => match serde_json::from_str::<Value>(document) {
+            Ok(abi) => normalize_abi(&abi),
+            Err(e) => Err(ModuleError::AbiFormatError { reason: e.to_string() }),
+        },
+        // hardhat and foundry artifacts carry the abi next to the bytecode and metadata
+        Value::Object(artifact) if artifact.contains_key("abi") => normalize_abi(&artifact["abi"]),
+        // the etherscan getabi response holds the abi as a json encoded string in `result`,
+        // or an error message in its place when the status is not "1"
+        Value::Object(response) if response.contains_key("status") && response.contains_key("result") => {
+            let result = response["result"].as_str().unwrap_or_default();
+            match (response["status"].as_str(), serde_json::from_str::<Value>(result)) {
+                (Option::Some("1"), Ok(abi)) => normalize_abi(&abi),
+                _ => Err(ModuleError::AbiFormatError { reason: format!("etherscan returned: {}", result) }),
+            }
+        }
+        // an abi, or a list of abi documents to merge into one table, the entries may be abi items,
+        // human-readable declarations, or whole documents in any of the accepted formats
+        Value::Array(entries) => {
+            let mut items: Vec<Value> = Vec::new();
+            for entry in entries.iter() {
+                let normalized = match entry {
+                    Value::String(s) if !s.trim_start().starts_with(['[', '{']) => {
+                        parse_declaration(s).into_iter().collect()
+                    }
+                    Value::Object(item) if !item.contains_key("abi") && !item.contains_key("result") => {
+                        vec![entry.clone()]
+                    }
+                    _ => normalize_abi(entry)?.as_array().cloned().unwrap_or_default(),
+                };
+
+                // the same item is commonly declared by several of the merged contracts
+                for item in normalized {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            Ok(Value::Array(items))
+        }
+        _ => Err(ModuleError::AbiFormatError { reason: "expected a json array or object".to_string() }),
+    }
+}
+
+// parse a human-readable declaration into its json abi form, e.g.
+// "function transfer(address to, uint256 amount) external returns (bool)"
+// events, functions, errors and constructors are understood, other declarations are skipped
+fn parse_declaration(declaration: &str) -> Option<Value> {
+    let declaration = declaration.trim();
+    let (kind, rest) = ["event", "function", "error", "constructor"]
+        .iter()
+        .find_map(|kind| declaration.strip_prefix(kind).map(|rest| (*kind, rest.trim())))?;
+    let open = rest.find('(')?;
+    let close = matching_paren(rest, open)?;
+
+    let inputs: Vec<Value> = split_components(&rest[open + 1..close])
+        .into_iter()
+        .map(parse_declared_param)
+        .collect();
+    let modifiers = &rest[close + 1..];
+
+    let mut item = serde_json::json!({
+        "type": kind,
+        "inputs": inputs,
+    });
+    if kind != "constructor" {
+        item["name"] = Value::String(rest[..open].trim().to_string());
+    }
+    match kind {
+        "event" => item["anonymous"] = Value::Bool(modifiers.trim() == "anonymous"),
+        "function" | "constructor" => {
+            let outputs: Vec<Value> = modifiers
+                .find("returns")
+                .and_then(|at| {
+                    let open = at + modifiers[at..].find('(')?;
+                    let close = matching_paren(modifiers, open)?;
+                    Option::Some(split_components(&modifiers[open + 1..close]))
+                })
+                .unwrap_or_default()
+                .into_iter()
+                .map(parse_declared_param)
+                .collect();
+            let mutability = modifiers
+                .split_whitespace()
+                .find(|w| ["view", "pure", "payable"].contains(w))
+                .unwrap_or("nonpayable");
+            if kind == "function" {
+                item["outputs"] = Value::Array(outputs);
+            }
+            item["stateMutability"] = Value::String(mutability.to_string());
+        }
+        _ => {}
+    }
+    Option::Some(item)
+}
+
+// parse a declared parameter such as `address indexed from` or `(uint256 id, address to)[] memory orders`
+fn parse_declared_param(param: &str) -> Value {
+    let param = param.trim();
+    let (typ, components, rest) = match param.strip_prefix("tuple").unwrap_or(param).starts_with('(') {
+        true => {
+            let open = param.find('(').unwrap_or(0);
+            let close = matching_paren(param, open).unwrap_or(param.len() - 1);
+            let components: Vec<Value> = split_components(&param[open + 1..close])
+                .into_iter()
+                .map(parse_declared_param)
+                .collect();
+
+            // the array suffix directly follows the closing parenthesis
+            let after = &param[close + 1..];
+            let suffix_len = after.find(|c: char| c.is_whitespace()).unwrap_or(after.len());
+            (format!("tuple{}", &after[..suffix_len]), Option::Some(components), &after[suffix_len..])
+        }
+        false => {
+            let typ_len = param.find(|c: char| c.is_whitespace()).unwrap_or(param.len());
+            (param[..typ_len].to_string(), Option::None, &param[typ_len..])
+        }
+    };
+
+    // the name is the word that isn't a modifier or data location
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let indexed = words.contains(&"indexed");
+    let name = words
+        .iter()
+        .find(|w| !["indexed", "memory", "calldata", "storage", "payable"].contains(*w))
+        .copied()
+        .unwrap_or_default();
+
+    let mut value = serde_json::json!({
+        "type": typ,
+        "name": name,
+        "indexed": indexed,
+    });
+    if let Option::Some(components) = components {
+        value["components"] = Value::Array(components);
+    }
+    value
+}
+
+// position of the parenthesis closing the one at `open`
+fn matching_paren(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices().skip_while(|(i, _)| *i < open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Option::Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    Option::None
+}
+
+// split the inside of an inline tuple on its top-level commas
+// e.g. `uint256,(address,bool)[]` -> [`uint256`, `(address,bool)[]`]
+fn split_components(inner: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !inner[start..].trim().is_empty() {
+        parts.push(inner[start..].trim());
+    }
+    parts
+}
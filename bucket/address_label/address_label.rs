@@ -0,0 +1,130 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the labelled addresses, e.g. {"0x28c6c06298d514db089934071355e5743bf21d60": {"label": "Binance 14",
+    // "category": "exchange"}}, matched regardless of case
+    pub labels: HashMap<String, Label>,
+    // the fields holding the addresses to annotate, each matched `field` gets `fieldLabel` and
+    // `fieldCategory` alongside it
+    #[serde(default = "fields_default")]
+    pub fields: Vec<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Label {
+    pub label: String,
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+fn fields_default() -> Vec<String> {
+    vec!["from".to_string(), "to".to_string(), "address".to_string()]
+}
+
+// shared so that reading the parameters for each record doesn't copy the labels
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // addresses are commonly supplied checksummed while records carry them in lowercase
+    parameter.labels = parameter.labels
+        .into_iter()
+        .map(|(address, label)| (address.trim().to_lowercase(), label))
+        .collect();
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(Arc::new(parameter));
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    // every annotated field the record carries gets both keys, null when its address has no label, so
+    // that the documents share one schema
+    for field in params.fields.iter() {
+        let address = match input.get(field) {
+            Option::Some(Value::String(address)) => address.trim().to_lowercase(),
+            Option::Some(_) => String::new(),
+            Option::None => continue,
+        };
+        let label = params.labels.get(&address);
+        input.insert(format!("{}Label", field), serde_json::json!(label.map(|l| &l.label)));
+        input.insert(format!("{}Category", field), serde_json::json!(label.and_then(|l| l.category.as_ref())));
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
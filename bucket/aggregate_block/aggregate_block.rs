@@ -0,0 +1,416 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{cmp, fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    BlockNumberError{number: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::BlockNumberError { number } =>
+                write!(f, "The block number {} is too large for 64 bits.", number),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the field the records of a block are grouped by, e.g. the `address` of logs for one group per
+    // contract, without it all the records of a block form a single group
+    #[serde(default)]
+    pub group_by: Option<String>,
+    // the field holding the aggregated integer, e.g. the `value` of transactions or decoded transfers
+    #[serde(default = "field_default")]
+    pub field: String,
+    #[serde(default = "aggregations_default")]
+    pub aggregations: Vec<Aggregation>,
+}
+
+// what is computed for each group, the record count or the sum, minimum and maximum of the field
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+fn field_default() -> String {
+    "value".to_string()
+}
+
+fn aggregations_default() -> Vec<Aggregation> {
+    vec![Aggregation::Count, Aggregation::Sum, Aggregation::Min, Aggregation::Max]
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            group_by: Option::None,
+            field: field_default(),
+            aggregations: aggregations_default(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// set once the input has ended, so that the end of stream is passed on after the last summary
+static ENDED: RwLock<bool> = RwLock::new(false);
+
+// the block being aggregated, its groups are kept in the order they were first seen
+struct Block {
+    number: u64,
+    count: u64,
+    groups: Vec<Group>,
+    index: HashMap<String, usize>,
+}
+
+struct Group {
+    key: Value,
+    count: u64,
+    // the records holding an integer in the field, the sum is none once it overflows 256 bits
+    values: u64,
+    sum: Option<U256>,
+    min: Option<U256>,
+    max: Option<U256>,
+}
+
+static BLOCK: RwLock<Option<Block>> = RwLock::new(Option::None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // a block aggregated under the previous parameters would mix both in its summary
+    *BLOCK.write()? = Option::None;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if *ENDED.read()? {
+        *ENDED.write()? = false;
+        return Ok(EndOfStream);
+    }
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        // the summary of the last block is emitted before the end of stream
+        EndOfStream => {
+            return Ok(match BLOCK.write()?.take() {
+                Option::Some(block) => {
+                    *ENDED.write()? = true;
+                    Some(serde_json::to_vec(&summary(&params, block))?)
+                }
+                Option::None => EndOfStream,
+            });
+        }
+    };
+
+    // records without a block number, e.g. of pending transactions, belong to no block and are skipped
+    let number = match input.get("blockNumber").and_then(quantity) {
+        Option::Some(number) => match number.to_u64() {
+            Option::Some(number) => number,
+            Option::None => {
+                lens_sdk::free_transport_buffer(ptr)?;
+                return Err(ModuleError::BlockNumberError { number: number.to_string() }.into());
+            }
+        },
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    // a block is complete once the records of another block arrive
+    let mut current = BLOCK.write()?;
+    let finished = match current.as_ref().is_some_and(|b| b.number != number) {
+        true => current.take(),
+        false => Option::None,
+    };
+    let block = current.get_or_insert_with(|| Block { number, count: 0, groups: Vec::new(), index: HashMap::new() });
+
+    // string keys are lowercased so that an address groups the same whatever its checksum casing
+    let key = match &params.group_by {
+        Option::Some(field) => input.get(field).cloned().unwrap_or(Value::Null),
+        Option::None => Value::Null,
+    };
+    let id = match &key {
+        Value::String(s) => format!("\"{}\"", s.to_lowercase()),
+        v => v.to_string(),
+    };
+    let position = match block.index.get(&id) {
+        Option::Some(position) => *position,
+        Option::None => {
+            block.groups.push(Group { key, count: 0, values: 0, sum: Option::Some(U256::default()), min: Option::None, max: Option::None });
+            block.index.insert(id, block.groups.len() - 1);
+            block.groups.len() - 1
+        }
+    };
+
+    block.count += 1;
+    let group = &mut block.groups[position];
+    group.count += 1;
+    // records whose field is missing or not an integer are counted but not summed
+    if let Option::Some(value) = input.get(&params.field).and_then(quantity) {
+        group.values += 1;
+        group.sum = group.sum.and_then(|sum| sum.checked_add(value));
+        group.min = Option::Some(group.min.map_or(value, |min| cmp::min(min, value)));
+        group.max = Option::Some(group.max.map_or(value, |max| cmp::max(max, value)));
+    }
+    // the block is released before a record that finishes none reads the next one
+    drop(current);
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    match finished {
+        Option::Some(finished) => Ok(Some(serde_json::to_vec(&summary(&params, finished))?)),
+        Option::None => try_transform(),
+    }
+}
+
+// the summary document of a block, with the requested aggregations of each group
+fn summary(params: &Parameters, block: Block) -> Value {
+    let groups: Vec<Value> = block.groups
+        .into_iter()
+        .map(|group| {
+            let mut document = serde_json::Map::new();
+            document.insert("group".to_string(), group.key);
+            for aggregation in params.aggregations.iter() {
+                // a group without any integer has no sum, minimum or maximum
+                let integer = |n: Option<U256>| match group.values {
+                    0 => Value::Null,
+                    _ => n.map_or(Value::Null, |n| Value::String(n.to_string())),
+                };
+                let (key, value) = match aggregation {
+                    Aggregation::Count => ("count", Value::from(group.count)),
+                    Aggregation::Sum => ("sum", integer(group.sum)),
+                    Aggregation::Min => ("min", integer(group.min)),
+                    Aggregation::Max => ("max", integer(group.max)),
+                };
+                document.insert(key.to_string(), value);
+            }
+            Value::Object(document)
+        })
+        .collect();
+
+    serde_json::json!({
+        "blockNumber": block.number,
+        "groupBy": params.group_by,
+        "field": params.field,
+        "recordCount": block.count,
+        "groups": groups,
+    })
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from_u64),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => {
+                // quantities are encoded without leading zeros so their digit count may be odd
+                let padded = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+                match padded.len() <= 64 {
+                    true => hex::decode(padded).ok().map(|bytes| U256::from_be_slice(&bytes)),
+                    false => Option::None,
+                }
+            }
+            Option::None => U256::from_dec_str(s.trim()),
+        },
+        _ => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    fn from_u64(n: u64) -> U256 {
+        U256([n, 0, 0, 0])
+    }
+
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            n = n.checked_mul_u64(10)?.checked_add_u64(c.to_digit(10)? as u64)?;
+        }
+        Option::Some(n)
+    }
+
+    fn checked_mul_u64(self, factor: u64) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let acc = self.0[i] as u128 * factor as u128 + carry;
+            *limb = acc as u64;
+            carry = acc >> 64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_add_u64(self, term: u64) -> Option<U256> {
+        let mut limbs = self.0;
+        let mut carry = term;
+        for limb in limbs.iter_mut() {
+            let (v, overflow) = limb.overflowing_add(carry);
+            *limb = v;
+            carry = overflow as u64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_add(self, other: U256) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_add(other.0[i]);
+            let (v, o2) = v.overflowing_add(carry as u64);
+            *limb = v;
+            carry = o1 || o2;
+        }
+        match carry {
+            false => Option::Some(U256(limbs)),
+            true => Option::None,
+        }
+    }
+
+    // the value as a u64, None when it doesn't fit
+    fn to_u64(self) -> Option<u64> {
+        match self.0[1..].iter().all(|l| *l == 0) {
+            true => Option::Some(self.0[0]),
+            false => Option::None,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+// integers compare from their most significant limb down
+impl Ord for U256 {
+    fn cmp(&self, other: &U256) -> cmp::Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &U256) -> Option<cmp::Ordering> {
+        Option::Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
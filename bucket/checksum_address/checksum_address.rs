@@ -0,0 +1,248 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the names of the address fields, at any depth of the document, e.g. the `from` and `to` of the
+    // calls nested in a trace, the addresses of the json-rpc objects by default
+    #[serde(default = "fields_default")]
+    pub fields: HashSet<String>,
+    #[serde(default)]
+    pub format: Format,
+    // also rewrite the address values of decoded `arguments`, whatever the argument is named
+    #[serde(default = "arguments_default")]
+    pub arguments: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    // EIP-55 mixed-case checksum, e.g. "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+    #[default]
+    Checksum,
+    Lowercase,
+}
+
+// the address fields of the json-rpc block, transaction, receipt, log, trace and withdrawal objects
+const ADDRESS_FIELDS: [&str; 8] = [
+    "address", "from", "to", "contractAddress", "miner", "author", "refundAddress", "creator",
+];
+
+fn fields_default() -> HashSet<String> {
+    ADDRESS_FIELDS.iter().map(|f| f.to_string()).collect()
+}
+
+fn arguments_default() -> bool {
+    true
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            fields: fields_default(),
+            format: Format::default(),
+            arguments: arguments_default(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    for (key, value) in input.iter_mut() {
+        rewrite(&params, key, value);
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// rewrite the value of `key` if it is an address field, and the fields nested in it otherwise, values
+// of address fields that aren't addresses, e.g. a null `to` of a contract creation, are left as they are
+fn rewrite(params: &Parameters, key: &str, value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            // a decoded argument carries its abi type next to its value, named `arguments` items and
+            // the tuple components nested in them alike
+            let typ = fields.get("type").and_then(|t| t.as_str()).unwrap_or_default().to_string();
+            let argument = params.arguments && is_address_type(&typ);
+            for (key, value) in fields.iter_mut() {
+                match argument && key == "value" {
+                    true => rewrite_addresses(params, &typ, value),
+                    false => rewrite(params, key, value),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                rewrite(params, key, item);
+            }
+        }
+        _ if params.fields.contains(key) => rewrite_addresses(params, "address", value),
+        _ => {}
+    }
+}
+
+// the abi types holding addresses, `address` and its arrays, and tuples which may have address
+// components, e.g. "address[2]" or "tuple[]"
+fn is_address_type(typ: &str) -> bool {
+    typ.starts_with("address") || typ.starts_with("tuple") || typ.starts_with('(')
+}
+
+// rewrite the addresses of a decoded value, the items of arrays and the components of tuples, the
+// decoded tuples don't carry their component types so any 20-byte hex string in them is taken for an
+// address, external `function` values keep their address in an `address` member
+fn rewrite_addresses(params: &Parameters, typ: &str, value: &mut Value) {
+    match value {
+        Value::String(s) => {
+            if let Option::Some(address) = format_address(s, params.format) {
+                *s = address;
+            }
+        }
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                rewrite_addresses(params, typ, item);
+            }
+        }
+        Value::Object(fields) if !typ.starts_with("address") => {
+            for value in fields.values_mut() {
+                rewrite_addresses(params, typ, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+// an address in the requested format, none if the string is not a 0x-prefixed 20-byte hex string
+fn format_address(address: &str, format: Format) -> Option<String> {
+    let digits = address.trim().strip_prefix("0x").or(address.trim().strip_prefix("0X"))?;
+    if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Option::None;
+    }
+    Option::Some(match format {
+        Format::Lowercase => format!("0x{}", digits.to_lowercase()),
+        Format::Checksum => to_checksum_address(digits),
+    })
+}
+
+// EIP-55: uppercase each hex letter whose nibble in keccak(lowercase address) is 8 or above
+fn to_checksum_address(address: &str) -> String {
+    let lower = address.trim_start_matches("0x").to_lowercase();
+    let mut hasher = Keccak256::new();
+    hasher.update(lower.as_bytes());
+    let hash = hex::encode(hasher.finalize());
+
+    let checksummed: String = lower
+        .chars()
+        .zip(hash.chars())
+        .map(|(c, h)| match h.to_digit(16).unwrap_or(0) >= 8 {
+            true => c.to_ascii_uppercase(),
+            false => c,
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_address_matches_the_eip55_vectors() {
+        for address in ["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB", "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb"] {
+            let upper = format!("0x{}", address[2..].to_uppercase());
+            assert_eq!(format_address(&upper, Format::Checksum).as_deref(), Option::Some(address));
+            assert_eq!(format_address(address, Format::Lowercase), Option::Some(address.to_lowercase()));
+        }
+    }
+
+    #[test]
+    fn format_address_leaves_other_strings_alone() {
+        assert_eq!(format_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea", Format::Checksum), Option::None);
+        assert_eq!(format_address("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed", Format::Checksum), Option::None);
+        assert_eq!(format_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beazz", Format::Checksum), Option::None);
+        assert!(!is_address_type("uint256") && is_address_type("address[2]") && is_address_type("tuple[]"));
+    }
+}
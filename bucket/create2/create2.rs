@@ -0,0 +1,250 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    DecodeError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::DecodeError { reason } =>
+                write!(f, "The contract address could not be computed. Reason: {}", reason),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set, they name the
+// fields the address inputs are read from, e.g. `from` as the sender of a transaction
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    #[serde(default = "deployer_field_default")]
+    pub deployer_field: String,
+    #[serde(default = "salt_field_default")]
+    pub salt_field: String,
+    // the hash of the init code, the init code itself is read from `initCode` when no hash is given
+    #[serde(default = "init_code_hash_field_default")]
+    pub init_code_hash_field: String,
+    #[serde(default = "sender_field_default")]
+    pub sender_field: String,
+    #[serde(default = "nonce_field_default")]
+    pub nonce_field: String,
+}
+
+fn deployer_field_default() -> String {
+    "deployer".to_string()
+}
+
+fn salt_field_default() -> String {
+    "salt".to_string()
+}
+
+fn init_code_hash_field_default() -> String {
+    "initCodeHash".to_string()
+}
+
+fn sender_field_default() -> String {
+    "sender".to_string()
+}
+
+fn nonce_field_default() -> String {
+    "nonce".to_string()
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            deployer_field: deployer_field_default(),
+            salt_field: salt_field_default(),
+            init_code_hash_field: init_code_hash_field_default(),
+            sender_field: sender_field_default(),
+            nonce_field: nonce_field_default(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // each address is computed when all of its inputs are present, records with neither are passed
+    // through as they are
+    let mut failure: Option<ModuleError> = Option::None;
+    let deployer = input.get(&params.deployer_field).and_then(|v| v.as_str());
+    let salt = input.get(&params.salt_field).and_then(|v| v.as_str());
+    let init_code_hash = input
+        .get(&params.init_code_hash_field)
+        .and_then(|v| v.as_str())
+        .map(|h| decode_hex(h, "init code hash"))
+        .or(input.get("initCode").and_then(|v| v.as_str()).map(|code| decode_hex(code, "init code").map(keccak)));
+    if let (Option::Some(deployer), Option::Some(salt), Option::Some(init_code_hash)) = (deployer, salt, init_code_hash) {
+        match create2_address(deployer, salt, init_code_hash) {
+            Ok(address) => {
+                input.insert("create2Address".to_string(), Value::String(address));
+            }
+            Err(e) => {
+                failure.get_or_insert(e);
+            }
+        }
+    }
+
+    let sender = input.get(&params.sender_field).and_then(|v| v.as_str());
+    let nonce = input.get(&params.nonce_field).filter(|v| !v.is_null());
+    if let (Option::Some(sender), Option::Some(nonce)) = (sender, nonce) {
+        match create_address(sender, nonce) {
+            Ok(address) => {
+                input.insert("createAddress".to_string(), Value::String(address));
+            }
+            Err(e) => {
+                failure.get_or_insert(e);
+            }
+        }
+    }
+    if let Option::Some(e) = failure {
+        input.insert("decodeError".to_string(), Value::String(e.to_string()));
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the address of a contract deployed with CREATE2 (EIP-1014),
+// keccak256(0xff ++ deployer ++ salt ++ keccak256(init_code))[12:]
+fn create2_address(deployer: &str, salt: &str, init_code_hash: Result<Vec<u8>, ModuleError>) -> Result<String, ModuleError> {
+    let deployer = fixed_hex(deployer, 20, "deployer")?;
+    // salts are often written as the quantity they were chosen as, which is left padded to 32 bytes
+    let salt = decode_hex(salt, "salt")?;
+    if salt.len() > 32 {
+        return Err(ModuleError::DecodeError { reason: format!("the salt is {} bytes, expected 32", salt.len()) });
+    }
+    let salt = [vec![0; 32 - salt.len()], salt].concat();
+    let init_code_hash = init_code_hash?;
+    if init_code_hash.len() != 32 {
+        return Err(ModuleError::DecodeError { reason: "the init code hash is not 32 bytes".to_string() });
+    }
+
+    let mut preimage = vec![0xff];
+    preimage.extend(deployer);
+    preimage.extend(salt);
+    preimage.extend(init_code_hash);
+    Ok(format!("0x{}", hex::encode(&keccak(preimage)[12..])))
+}
+
+// the address of a contract deployed with CREATE or by a creation transaction,
+// keccak256(rlp([sender, nonce]))[12:]
+fn create_address(sender: &str, nonce: &Value) -> Result<String, ModuleError> {
+    let sender = fixed_hex(sender, 20, "sender")?;
+    let nonce = match nonce {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => u64::from_str_radix(digits, 16).ok(),
+            Option::None => s.trim().parse::<u64>().ok(),
+        },
+        _ => Option::None,
+    }
+    .ok_or(ModuleError::DecodeError { reason: format!("the nonce {} is not a quantity", nonce) })?;
+
+    // the nonce is rlp encoded as its big-endian bytes without leading zeros, a single byte below 0x80
+    // is its own encoding, and values so short the list needs no length of length
+    let bytes: Vec<u8> = nonce.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+    let nonce_rlp = match bytes.as_slice() {
+        [b] if *b < 0x80 => vec![*b],
+        _ => [vec![0x80 + bytes.len() as u8], bytes].concat(),
+    };
+    let mut rlp = vec![0xc0 + (21 + nonce_rlp.len()) as u8, 0x80 + 20];
+    rlp.extend(sender);
+    rlp.extend(nonce_rlp);
+    Ok(format!("0x{}", hex::encode(&keccak(rlp)[12..])))
+}
+
+// decode a hex value that must be exactly `len` bytes long
+fn fixed_hex(value: &str, len: usize, what: &str) -> Result<Vec<u8>, ModuleError> {
+    let bytes = decode_hex(value, what)?;
+    match bytes.len() == len {
+        true => Ok(bytes),
+        false => Err(ModuleError::DecodeError { reason: format!("the {} is {} bytes, expected {}", what, bytes.len(), len) }),
+    }
+}
+
+fn decode_hex(value: &str, what: &str) -> Result<Vec<u8>, ModuleError> {
+    let value = value.trim().to_lowercase();
+    let digits = value.strip_prefix("0x").unwrap_or(&value);
+    // quantities may be written with an odd digit count
+    let digits = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+    hex::decode(digits).map_err(|e| ModuleError::DecodeError { reason: format!("the {} is not hex: {}", what, e) })
+}
+
+fn keccak(data: Vec<u8>) -> Vec<u8> {
+    let mut hasher = Keccak256::new();
+    hasher.update(&data);
+    hasher.finalize().to_vec()
+}
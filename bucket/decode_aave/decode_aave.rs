@@ -0,0 +1,319 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't lending pool events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// keccak256 of the aave v3 pool events
+// "Supply(address,address,address,uint256,uint16)"
+const SUPPLY_V3_TOPIC: &str = "0x2b627736bca15cd5381dcf80b0bf11fd197d01a037c52b927a881a10fb73ba61";
+// "Borrow(address,address,address,uint256,uint8,uint256,uint16)"
+const BORROW_V3_TOPIC: &str = "0xb3d084820fb1a9decffb176436bd02558d15fac9b0ddfed8c465bc7359d7dce0";
+// "Repay(address,address,address,uint256,bool)"
+const REPAY_V3_TOPIC: &str = "0xa534c8dbe71f871f9f3530e97a74601fea17b426cae02e1c5aee42c96c784051";
+// "FlashLoan(address,address,address,uint256,uint8,uint256,uint16)"
+const FLASH_LOAN_V3_TOPIC: &str = "0xefefaba5e921573100900a3ad9cf29f222d995fb3b6045797eaea7521bd8d6f0";
+
+// and of the aave v2 lending pool events
+// "Deposit(address,address,address,uint256,uint16)"
+const DEPOSIT_V2_TOPIC: &str = "0xde6857219544bb5b7746f48ed30be6386fefc61b2f864cacf559893bf50fd951";
+// "Borrow(address,address,address,uint256,uint256,uint256,uint16)"
+const BORROW_V2_TOPIC: &str = "0xc6a898309e823ee50bac64e45ca8adba6690e99e7841c45d754e2a38e9019d9b";
+// "Repay(address,address,address,uint256)"
+const REPAY_V2_TOPIC: &str = "0x4cdde6e09bb755c9a5589ebaec640bbfedff1362d4b255ebf8339782b9942faa";
+// "FlashLoan(address,address,address,uint256,uint256,uint16)"
+const FLASH_LOAN_V2_TOPIC: &str = "0x631042c832b07452973831137f2d73e395028b44b250dedc5abb0ee766e168ac";
+
+// declared the same way by both versions
+// "Withdraw(address,address,address,uint256)"
+const WITHDRAW_TOPIC: &str = "0x3115d1449a7b732c986cba18244e897a450f61e1bb8d589cd2e69e6c8924f9f7";
+// "LiquidationCall(address,address,address,uint256,uint256,address,bool)"
+const LIQUIDATION_CALL_TOPIC: &str = "0xe413a321e8681d831f4dbccbca790d2952b56f977908e45be37335533e005286";
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the lending event schema document of a pool event, `user` is the account whose position changes,
+// the borrower for repayments and liquidations and the initiator of flash loans, `asset` and `amount`
+// are the reserve and amount moved, the debt repaid for liquidations, fields an event doesn't carry are
+// null, logs of other events give None
+fn decode_log(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    let topics: Vec<Vec<u8>> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .map(|t| hex::decode(t?.strip_prefix("0x")?).ok().filter(|w| w.len() == 32))
+        .collect::<Option<Vec<Vec<u8>>>>()?;
+    let data = input.get("data")?.as_str()?.trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+    let words: Vec<&[u8]> = data.chunks(32).collect();
+
+    let mut output: HashMap<String, Value> = [
+        "user", "onBehalfOf", "asset", "amount", "rateMode", "borrowRate", "repayer", "to", "target", "premium",
+        "collateralAsset", "liquidatedCollateralAmount", "liquidator", "receiveAToken", "useATokens", "referralCode",
+    ]
+    .iter()
+    .map(|key| (key.to_string(), Value::Null))
+    .collect();
+    let mut set = |key: &str, value: Value| output.insert(key.to_string(), value);
+
+    let topic0 = format!("0x{}", hex::encode(topics.first()?));
+    let (action, version) = match (topic0.as_str(), topics.len(), words.len()) {
+        // supply and deposit share their layout, the supplier is in the data and the referral indexed
+        (SUPPLY_V3_TOPIC | DEPOSIT_V2_TOPIC, 4, 2) => {
+            set("asset", address(&topics[1]));
+            set("user", address(words[0]));
+            set("onBehalfOf", address(&topics[2]));
+            set("amount", uint(words[1]));
+            set("referralCode", Value::from(small(&topics[3])));
+            ("supply", Option::Some(if topic0 == SUPPLY_V3_TOPIC { "v3" } else { "v2" }))
+        }
+        (BORROW_V3_TOPIC | BORROW_V2_TOPIC, 4, 4) => {
+            set("asset", address(&topics[1]));
+            set("user", address(words[0]));
+            set("onBehalfOf", address(&topics[2]));
+            set("amount", uint(words[1]));
+            set("rateMode", rate_mode(small(words[2])));
+            set("borrowRate", uint(words[3]));
+            set("referralCode", Value::from(small(&topics[3])));
+            ("borrow", Option::Some(if topic0 == BORROW_V3_TOPIC { "v3" } else { "v2" }))
+        }
+        (REPAY_V3_TOPIC, 4, 2) | (REPAY_V2_TOPIC, 4, 1) => {
+            set("asset", address(&topics[1]));
+            set("user", address(&topics[2]));
+            set("repayer", address(&topics[3]));
+            set("amount", uint(words[0]));
+            if let Option::Some(use_a_tokens) = words.get(1) {
+                set("useATokens", Value::Bool(use_a_tokens[31] != 0));
+            }
+            ("repay", Option::Some(if topic0 == REPAY_V3_TOPIC { "v3" } else { "v2" }))
+        }
+        (WITHDRAW_TOPIC, 4, 1) => {
+            set("asset", address(&topics[1]));
+            set("user", address(&topics[2]));
+            set("to", address(&topics[3]));
+            set("amount", uint(words[0]));
+            ("withdraw", Option::None)
+        }
+        (LIQUIDATION_CALL_TOPIC, 4, 4) => {
+            set("collateralAsset", address(&topics[1]));
+            set("asset", address(&topics[2]));
+            set("user", address(&topics[3]));
+            set("amount", uint(words[0]));
+            set("liquidatedCollateralAmount", uint(words[1]));
+            set("liquidator", address(words[2]));
+            set("receiveAToken", Value::Bool(words[3][31] != 0));
+            ("liquidation", Option::None)
+        }
+        // a flash loan whose debt is left open as a borrow carries the borrow's rate mode
+        (FLASH_LOAN_V3_TOPIC, 4, 4) => {
+            set("target", address(&topics[1]));
+            set("user", address(words[0]));
+            set("asset", address(&topics[2]));
+            set("amount", uint(words[1]));
+            set("rateMode", rate_mode(small(words[2])));
+            set("premium", uint(words[3]));
+            set("referralCode", Value::from(small(&topics[3])));
+            ("flashLoan", Option::Some("v3"))
+        }
+        (FLASH_LOAN_V2_TOPIC, 4, 3) => {
+            set("target", address(&topics[1]));
+            set("user", address(&topics[2]));
+            set("asset", address(&topics[3]));
+            set("amount", uint(words[0]));
+            set("premium", uint(words[1]));
+            set("referralCode", Value::from(small(words[2])));
+            ("flashLoan", Option::Some("v2"))
+        }
+        _ => return Option::None,
+    };
+
+    output.insert("action".to_string(), Value::String(action.to_string()));
+    // withdrawals and liquidations are declared the same way by both versions
+    output.insert("version".to_string(), serde_json::json!(version));
+    let pool = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("pool".to_string(), serde_json::json!(pool));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the interest rate mode of a borrow, as the pool's DataTypes.InterestRateMode declares them
+fn rate_mode(mode: u64) -> Value {
+    Value::String(match mode {
+        0 => "none",
+        1 => "stable",
+        2 => "variable",
+        _ => "unknown",
+    }.to_string())
+}
+
+// the address in the low 20 bytes of a word
+fn address(word: &[u8]) -> Value {
+    Value::String(format!("0x{}", hex::encode(&word[12..])))
+}
+
+// an unsigned integer word as a decimal string
+fn uint(word: &[u8]) -> Value {
+    Value::String(U256::from_be_slice(word).to_string())
+}
+
+// the low 8 bytes of a word holding a small integer such as a referral code
+fn small(word: &[u8]) -> u64 {
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&word[24..]);
+    u64::from_be_bytes(low)
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
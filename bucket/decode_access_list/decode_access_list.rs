@@ -0,0 +1,193 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass transactions without an access list through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+// the intrinsic gas of an access list entry (EIP-2930) and the cold access surcharge it spares the
+// first access to it (EIP-2929), a listed address costs 2400 and saves its first access costing 2600,
+// a listed storage key costs 1900 and saves its first access costing 2100
+const ADDRESS_GAS: i64 = 2400;
+const STORAGE_KEY_GAS: i64 = 1900;
+const COLD_ACCOUNT_ACCESS_GAS: i64 = 2600;
+const COLD_SLOAD_GAS: i64 = 2100;
+
+// the precompiles are warm from the start of every transaction, up to the Prague BLS12-381 ones
+const PRECOMPILES: u8 = 0x11;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // legacy transactions have no access list and those of later types may leave it empty
+    let empty: Vec<Value> = Vec::new();
+    let entries = input.get("accessList").and_then(|v| v.as_array()).unwrap_or(&empty);
+    if entries.is_empty() {
+        let result_json = match params.keep_unmatched {
+            true => Option::Some(serde_json::to_vec(&input)?),
+            false => Option::None,
+        };
+        lens_sdk::free_transport_buffer(ptr)?;
+        return match result_json {
+            Option::Some(result_json) => Ok(Some(result_json)),
+            Option::None => try_transform(),
+        };
+    }
+
+    let from = input.get("from").and_then(|v| v.as_str()).map(|a| a.trim().to_lowercase());
+    let to = input.get("to").and_then(|v| v.as_str()).map(|a| a.trim().to_lowercase());
+
+    // the sender, the recipient and the precompiles are warm already, as is anything listed twice,
+    // listing them costs the intrinsic gas without sparing any cold access
+    let mut warm: Vec<String> = from.iter().chain(to.iter()).cloned().collect();
+    warm.extend((1..=PRECOMPILES).map(|n| format!("0x{:040x}", n)));
+
+    let mut documents = VecDeque::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let address = entry["address"].as_str().unwrap_or_default().trim().to_lowercase();
+        let storage_keys: Vec<String> = entry["storageKeys"]
+            .as_array()
+            .unwrap_or(&empty)
+            .iter()
+            .filter_map(|k| k.as_str())
+            .map(storage_key)
+            .collect();
+
+        let mut gas_saving = match warm.contains(&address) {
+            true => -ADDRESS_GAS,
+            false => COLD_ACCOUNT_ACCESS_GAS - ADDRESS_GAS,
+        };
+        warm.push(address.clone());
+        for key in storage_keys.iter() {
+            let slot = format!("{}:{}", address, key);
+            gas_saving += match warm.contains(&slot) {
+                true => -STORAGE_KEY_GAS,
+                false => COLD_SLOAD_GAS - STORAGE_KEY_GAS,
+            };
+            warm.push(slot);
+        }
+
+        // the saving assumes each listed entry is accessed by the transaction, which only its trace tells
+        let access_list_gas = ADDRESS_GAS + STORAGE_KEY_GAS * storage_keys.len() as i64;
+        let mut document = serde_json::Map::new();
+        document.insert("entryIndex".to_string(), Value::from(i));
+        document.insert("address".to_string(), Value::String(address));
+        document.insert("storageKeyCount".to_string(), Value::from(storage_keys.len()));
+        document.insert("storageKeys".to_string(), serde_json::json!(storage_keys));
+        document.insert("accessListGas".to_string(), Value::from(access_list_gas));
+        document.insert("gasSaving".to_string(), Value::from(gas_saving));
+        document.insert("from".to_string(), serde_json::json!(from));
+        document.insert("to".to_string(), serde_json::json!(to));
+        for key in ["hash", "type", "blockHash", "blockNumber", "transactionIndex"] {
+            document.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+        }
+        documents.push_back(serde_json::to_vec(&document)?);
+    }
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    let first = documents.pop_front();
+    PENDING.write()?.extend(documents);
+    match first {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
+
+// a storage key as a 0x-prefixed 32-byte word, keys that aren't hex are kept as they are
+fn storage_key(key: &str) -> String {
+    let key = key.trim().to_lowercase();
+    let digits = key.strip_prefix("0x").unwrap_or(&key);
+    match digits.len() <= 64 && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => format!("0x{:0>64}", digits),
+        false => key,
+    }
+}
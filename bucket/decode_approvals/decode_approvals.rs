@@ -0,0 +1,308 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // only emit approvals flagged as unlimited, e.g. to watch for wallets exposed to a drainer
+    #[serde(default)]
+    pub unlimited_only: bool,
+    // pass logs that aren't approvals through unchanged instead of dropping them, approvals filtered
+    // out by `unlimitedOnly` are dropped regardless
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// keccak256 of "Approval(address,address,uint256)" shared by erc20 and erc721 and
+// "ApprovalForAll(address,address,bool)" shared by erc721 and erc1155
+const APPROVAL_TOPIC: &str = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+const APPROVAL_FOR_ALL_TOPIC: &str = "0x17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31";
+
+// keccak256 of the Permit2 events "Approval(address,address,address,uint160,uint48)",
+// "Permit(address,address,address,uint160,uint48,uint48)" and "Lockdown(address,address,address)"
+const PERMIT2_APPROVAL_TOPIC: &str = "0xda9fa7c1b00402c17d0161b249b1ab8bbec047c5a52207b9c112deffd817036b";
+const PERMIT2_PERMIT_TOPIC: &str = "0xc6a377bfc4eb120024a8ac08eef205be16b817020812c73223e81d1bdb9708ec";
+const PERMIT2_LOCKDOWN_TOPIC: &str = "0x89b1add15eff56b3dfe299ad94e01f2b52fbcb80ae1a3baea6ae8c04cb2b98a4";
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+// the fields of the approval schema, those an event doesn't carry are null
+const FIELDS: &[&str] = &[
+    "eventName", "standard", "owner", "spender", "token", "amount", "tokenId", "unlimited", "approved",
+    "expiration", "nonce", "contract",
+];
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input) {
+        Option::Some(output) if params.unlimited_only && output["unlimited"] != Value::Bool(true) => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the approval schema document of an erc20, erc721 or Permit2 approval log, an approval is unlimited
+// when it grants the largest amount the standard can express, 2^256-1 for erc20 and 2^160-1 for Permit2,
+// or all of the owner's tokens as ApprovalForAll does, and revokes when it grants nothing, logs of
+// other events give None
+fn decode_log(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    let data = input.get("data").and_then(|d| d.as_str()).unwrap_or_default().trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+    let word = |i: usize| data.get(i * 32..i * 32 + 32);
+    let contract = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+
+    let mut output: HashMap<String, Value> = FIELDS.iter().map(|key| (key.to_string(), Value::Null)).collect();
+    let mut insert = |key: &str, value: Value| {
+        output.insert(key.to_string(), value);
+    };
+    match (topics.first().map(|t| t.as_str()), topics.len()) {
+        // erc20 leaves the amount unindexed where erc721 indexes the token id in a fourth topic
+        (Option::Some(APPROVAL_TOPIC), 3) if data.len() == 32 => {
+            let amount = U256::from_be_slice(&data);
+            insert("eventName", Value::String("Approval".to_string()));
+            insert("standard", Value::String("erc20".to_string()));
+            insert("owner", Value::String(topic_address(&topics[1])?));
+            insert("spender", Value::String(topic_address(&topics[2])?));
+            insert("token", serde_json::json!(contract));
+            insert("amount", Value::String(amount.to_string()));
+            insert("unlimited", Value::Bool(amount == U256::MAX));
+            insert("approved", Value::Bool(!amount.is_zero()));
+        }
+        (Option::Some(APPROVAL_TOPIC), 4) => {
+            let spender = topic_address(&topics[2])?;
+            insert("eventName", Value::String("Approval".to_string()));
+            insert("standard", Value::String("erc721".to_string()));
+            insert("owner", Value::String(topic_address(&topics[1])?));
+            insert("token", serde_json::json!(contract));
+            insert("tokenId", Value::String(topic_uint(&topics[3])?));
+            insert("unlimited", Value::Bool(false));
+            // approving the zero address clears the approval of the token
+            insert("approved", Value::Bool(spender != ZERO_ADDRESS));
+            insert("spender", Value::String(spender));
+        }
+        // erc1155 declares the same event, the two can't be told apart from the log alone and it is
+        // reported as erc721
+        (Option::Some(APPROVAL_FOR_ALL_TOPIC), 3) if data.len() == 32 => {
+            let approved = data[31] != 0;
+            insert("eventName", Value::String("ApprovalForAll".to_string()));
+            insert("standard", Value::String("erc721".to_string()));
+            insert("owner", Value::String(topic_address(&topics[1])?));
+            insert("spender", Value::String(topic_address(&topics[2])?));
+            insert("token", serde_json::json!(contract));
+            insert("unlimited", Value::Bool(approved));
+            insert("approved", Value::Bool(approved));
+        }
+        // Permit2 holds the approvals of many tokens, the token is indexed and the log is Permit2's own
+        (Option::Some(PERMIT2_APPROVAL_TOPIC), 4) | (Option::Some(PERMIT2_PERMIT_TOPIC), 4) => {
+            let permit = topics[0] == PERMIT2_PERMIT_TOPIC;
+            let amount = U256::from_be_slice(word(0)?);
+            insert("eventName", Value::String(if permit { "Permit" } else { "Approval" }.to_string()));
+            insert("standard", Value::String("permit2".to_string()));
+            insert("owner", Value::String(topic_address(&topics[1])?));
+            insert("token", Value::String(topic_address(&topics[2])?));
+            insert("spender", Value::String(topic_address(&topics[3])?));
+            insert("amount", Value::String(amount.to_string()));
+            insert("unlimited", Value::Bool(amount == U256::MAX_UINT160));
+            insert("approved", Value::Bool(!amount.is_zero()));
+            insert("expiration", Value::from(U256::from_be_slice(word(1)?).low_u64()));
+            if permit {
+                insert("nonce", Value::from(U256::from_be_slice(word(2)?).low_u64()));
+            }
+            insert("contract", serde_json::json!(contract));
+        }
+        // a lockdown revokes the approval of a spender for a token at once
+        (Option::Some(PERMIT2_LOCKDOWN_TOPIC), 2) => {
+            insert("eventName", Value::String("Lockdown".to_string()));
+            insert("standard", Value::String("permit2".to_string()));
+            insert("owner", Value::String(topic_address(&topics[1])?));
+            insert("token", Value::String(format!("0x{}", hex::encode(&word(0)?[12..]))));
+            insert("spender", Value::String(format!("0x{}", hex::encode(&word(1)?[12..]))));
+            insert("amount", Value::String("0".to_string()));
+            insert("unlimited", Value::Bool(false));
+            insert("approved", Value::Bool(false));
+            insert("contract", serde_json::json!(contract));
+        }
+        _ => return Option::None,
+    }
+
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the unsigned integer held by an indexed topic, as a decimal string
+fn topic_uint(topic: &str) -> Option<String> {
+    let word = hex::decode(topic.strip_prefix("0x").unwrap_or(topic)).ok()?;
+    match word.len() == 32 {
+        true => Option::Some(U256::from_be_slice(&word).to_string()),
+        false => Option::None,
+    }
+}
+
+// the address in the low 20 bytes of an indexed topic
+fn topic_address(topic: &str) -> Option<String> {
+    let word = topic.strip_prefix("0x").unwrap_or(topic);
+    match word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Option::Some(format!("0x{}", &word[24..])),
+        false => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // the largest amounts an erc20 and a Permit2 approval can grant, type(uint256).max and type(uint160).max
+    const MAX: U256 = U256([u64::MAX; 4]);
+    const MAX_UINT160: U256 = U256([u64::MAX, u64::MAX, u32::MAX as u64, 0]);
+
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,526 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the chain id of the arbitrum chain the messages are sent to, e.g. 42161 for arbitrum one, the
+    // ticket id of retryables is only computed when it is set
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    // pass logs that aren't arbitrum bridge events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// the message the bridge delivered to the inbox, it is logged just before the inbox logs the message's
+// data and carries the sender and l1 base fee its ticket id is computed from
+#[derive(Clone)]
+struct Delivered {
+    kind: u8,
+    sender: Vec<u8>,
+    base_fee: Vec<u8>,
+}
+
+// the messages delivered by the l1 transaction being read, keyed by their message index, those of
+// earlier transactions are dropped when the next one starts
+static DELIVERED: RwLock<Option<(Value, HashMap<u64, Delivered>)>> = RwLock::new(Option::None);
+
+// MessageDelivered(uint256,bytes32,address,uint8,address,bytes32,uint256,uint64) of the bridge
+const MESSAGE_DELIVERED: &str = "0x5e3c1311ea442664e8b1611bfabef659120ea7a0a2cfc0667700bebc69cbffe1";
+// InboxMessageDelivered(uint256,bytes) of the inbox
+const INBOX_MESSAGE_DELIVERED: &str = "0xff64905f73a67fb594e0f940a8075a860db489ad991e032f48c81123eb52d60b";
+// OutBoxTransactionExecuted(address,address,uint256,uint256) of the outbox, executing an L2ToL1Tx on l1
+const OUTBOX_TRANSACTION_EXECUTED: &str = "0x20af7f3bbfe38132b8900ae295cd9c8d1914be7052d061a511f3f728dab18964";
+// L2ToL1Tx(address,address,uint256,uint256,uint256,uint256,uint256,uint256,bytes) of ArbSys
+const L2_TO_L1_TX: &str = "0x3e7aafa77dbf186b7fd488006beff893744caa3c4f6f299e8a709fa2087374fc";
+// TicketCreated(bytes32) and RedeemScheduled(bytes32,bytes32,uint64,uint64,address,uint256,uint256) of
+// ArbRetryableTx
+const TICKET_CREATED: &str = "0x7c793cced5743dc5f531bbe2bfb5a9fa3f40adef29231e6ab165c08a29e3dd89";
+const REDEEM_SCHEDULED: &str = "0x5ccd009502509cf28762c67858994d85b163bb6e451f5e9df7c5e18c9c2e123e";
+
+// the kinds of l1 message the bridge delivers
+const L1_MESSAGE_KINDS: &[(u8, &str)] = &[
+    (3, "l2Message"),
+    (6, "endOfBlock"),
+    (7, "l2FundedByL1"),
+    (8, "rollupEvent"),
+    (9, "submitRetryable"),
+    (10, "batchForGasEstimation"),
+    (11, "initialize"),
+    (12, "ethDeposit"),
+    (13, "batchPostingReport"),
+];
+const SUBMIT_RETRYABLE: u8 = 9;
+const ETH_DEPOSIT: u8 = 12;
+
+// the EIP-2718 type of the transaction a retryable submission becomes on l2, its hash is the ticket id
+const SUBMIT_RETRYABLE_TX_TYPE: u8 = 0x69;
+
+// the fields of the documents, those an event doesn't carry are null, so retryable submissions join
+// their l2 tickets on `ticketId` and l2 to l1 messages their execution on l1 on `position`
+const FIELDS: &[&str] = &[
+    "messageIndex", "beforeInboxAcc", "inbox", "messageKind", "messageKindName", "sender", "messageDataHash",
+    "l1BaseFee", "timestamp", "to", "l2CallValue", "deposit", "maxSubmissionCost", "excessFeeRefundAddress",
+    "callValueRefundAddress", "gasLimit", "maxFeePerGas", "data", "dataSelector", "ticketId", "caller",
+    "destination", "hash", "position", "arbBlockNum", "ethBlockNum", "callvalue", "l2Sender", "retryTxHash",
+    "sequenceNum", "donatedGas", "gasDonor", "maxRefund", "submissionFeeRefund",
+];
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    *DELIVERED.write()? = Option::None;
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let transaction = input.get("transactionHash").cloned().unwrap_or(Value::Null);
+    let mut delivered = DELIVERED.write()?;
+    if delivered.as_ref().map(|(hash, _)| hash) != Option::Some(&transaction) {
+        *delivered = Option::Some((transaction, HashMap::new()));
+    }
+    let messages = &mut delivered.get_or_insert_with(|| (Value::Null, HashMap::new())).1;
+
+    let output = decode_log(&input, messages, params.chain_id);
+    // the delivered messages are released before a dropped log reads the next one
+    drop(delivered);
+
+    let output = match output {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fixed schema document of an arbitrum bridge event, logs of other events and those whose topics
+// and data don't add up to the event's arguments give None
+fn decode_log(input: &HashMap<String, Value>, messages: &mut HashMap<u64, Delivered>, chain_id: Option<u64>) -> Option<HashMap<String, Value>> {
+    let topics: Vec<Vec<u8>> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().and_then(hash_bytes))
+        .collect::<Option<Vec<Vec<u8>>>>()?;
+    let data = input.get("data")?.as_str()?.trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+    let topic = format!("0x{}", hex::encode(topics.first()?));
+    let word = |i: usize| data.get(i * 32..i * 32 + 32);
+
+    let mut output: HashMap<String, Value> = FIELDS.iter().map(|key| (key.to_string(), Value::Null)).collect();
+    let event_name = match topic.as_str() {
+        MESSAGE_DELIVERED if topics.len() == 3 => {
+            let index = U256::from_be_slice(&topics[1]).low_u64();
+            let kind = word(1)?[31];
+            let message = Delivered { kind, sender: word(2)?[12..].to_vec(), base_fee: word(4)?.to_vec() };
+            output.insert("messageIndex".to_string(), Value::from(index));
+            output.insert("beforeInboxAcc".to_string(), bytes32(&topics[2]));
+            output.insert("inbox".to_string(), address(word(0)?));
+            output.insert("sender".to_string(), address(word(2)?));
+            output.insert("messageDataHash".to_string(), bytes32(word(3)?));
+            output.insert("l1BaseFee".to_string(), uint(word(4)?));
+            output.insert("timestamp".to_string(), number(word(5)?));
+            insert_kind(&mut output, kind);
+            messages.insert(index, message);
+            "MessageDelivered"
+        }
+        INBOX_MESSAGE_DELIVERED if topics.len() == 2 => {
+            let index = U256::from_be_slice(&topics[1]).low_u64();
+            let start = read_usize(&data, 0)?;
+            let length = read_usize(&data, start)?;
+            let message = data.get(start.checked_add(32)?..)?.get(..length)?;
+            output.insert("messageIndex".to_string(), Value::from(index));
+            output.insert("data".to_string(), Value::String(format!("0x{}", hex::encode(message))));
+
+            // the kind of message is only known from the bridge's log, without it the data is taken
+            // for a retryable submission when it has the layout of one
+            let delivered = messages.remove(&index);
+            let kind = match &delivered {
+                Option::Some(delivered) => Option::Some(delivered.kind),
+                Option::None => read_retryable(message).map(|_| SUBMIT_RETRYABLE),
+            };
+            if let Option::Some(kind) = kind {
+                insert_kind(&mut output, kind);
+            }
+            if let Option::Some(delivered) = &delivered {
+                output.insert("sender".to_string(), address(&delivered.sender));
+                output.insert("l1BaseFee".to_string(), uint(&delivered.base_fee));
+            }
+            match kind {
+                Option::Some(SUBMIT_RETRYABLE) => {
+                    let retryable = read_retryable(message)?;
+                    retryable.insert_into(&mut output);
+                    if let (Option::Some(chain_id), Option::Some(delivered)) = (chain_id, &delivered) {
+                        let ticket_id = retryable.ticket_id(chain_id, index, delivered);
+                        output.insert("ticketId".to_string(), bytes32(&ticket_id));
+                    }
+                }
+                // eth deposits are packed as the recipient and the value
+                Option::Some(ETH_DEPOSIT) if message.len() == 52 => {
+                    output.insert("to".to_string(), address(&message[..20]));
+                    output.insert("deposit".to_string(), uint(&message[20..]));
+                }
+                _ => {}
+            }
+            "InboxMessageDelivered"
+        }
+        OUTBOX_TRANSACTION_EXECUTED if topics.len() == 4 => {
+            output.insert("to".to_string(), address(&topics[1]));
+            output.insert("l2Sender".to_string(), address(&topics[2]));
+            output.insert("position".to_string(), number(word(0)?));
+            "OutBoxTransactionExecuted"
+        }
+        L2_TO_L1_TX if topics.len() == 4 => {
+            let start = read_usize(&data, 5 * 32)?;
+            let length = read_usize(&data, start)?;
+            let calldata = data.get(start.checked_add(32)?..)?.get(..length)?;
+            output.insert("caller".to_string(), address(word(0)?));
+            output.insert("destination".to_string(), address(&topics[1]));
+            output.insert("hash".to_string(), bytes32(&topics[2]));
+            output.insert("position".to_string(), number(&topics[3]));
+            output.insert("arbBlockNum".to_string(), number(word(1)?));
+            output.insert("ethBlockNum".to_string(), number(word(2)?));
+            output.insert("timestamp".to_string(), number(word(3)?));
+            output.insert("callvalue".to_string(), uint(word(4)?));
+            insert_data(&mut output, calldata);
+            "L2ToL1Tx"
+        }
+        TICKET_CREATED if topics.len() == 2 => {
+            output.insert("ticketId".to_string(), bytes32(&topics[1]));
+            "TicketCreated"
+        }
+        REDEEM_SCHEDULED if topics.len() == 4 => {
+            output.insert("ticketId".to_string(), bytes32(&topics[1]));
+            output.insert("retryTxHash".to_string(), bytes32(&topics[2]));
+            output.insert("sequenceNum".to_string(), number(&topics[3]));
+            output.insert("donatedGas".to_string(), number(word(0)?));
+            output.insert("gasDonor".to_string(), address(word(1)?));
+            output.insert("maxRefund".to_string(), uint(word(2)?));
+            output.insert("submissionFeeRefund".to_string(), uint(word(3)?));
+            "RedeemScheduled"
+        }
+        _ => return Option::None,
+    };
+
+    output.insert("eventName".to_string(), Value::String(event_name.to_string()));
+    let contract = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("address".to_string(), serde_json::json!(contract));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the kind of an l1 message and its name, unknown kinds are named null
+fn insert_kind(output: &mut HashMap<String, Value>, kind: u8) {
+    let name = L1_MESSAGE_KINDS.iter().find(|(k, _)| *k == kind).map(|(_, name)| *name);
+    output.insert("messageKind".to_string(), Value::from(kind));
+    output.insert("messageKindName".to_string(), serde_json::json!(name));
+}
+
+// the calldata embedded in a message and the selector of the function it calls, if it is long enough
+fn insert_data(output: &mut HashMap<String, Value>, calldata: &[u8]) {
+    output.insert("data".to_string(), Value::String(format!("0x{}", hex::encode(calldata))));
+    let selector = calldata.get(..4).map(|s| format!("0x{}", hex::encode(s)));
+    output.insert("dataSelector".to_string(), serde_json::json!(selector));
+}
+
+// a retryable submission as the inbox packs it, words of the recipient, the l2 call value, the deposit,
+// the maximum submission cost, the two refund addresses, the gas limit, the maximum fee per gas and the
+// calldata length followed by the calldata
+struct Retryable<'a> {
+    to: &'a [u8],
+    l2_call_value: &'a [u8],
+    deposit: &'a [u8],
+    max_submission_cost: &'a [u8],
+    excess_fee_refund_address: &'a [u8],
+    call_value_refund_address: &'a [u8],
+    gas_limit: u64,
+    max_fee_per_gas: &'a [u8],
+    data: &'a [u8],
+}
+
+// read a retryable submission, None when the message doesn't have its layout
+fn read_retryable(message: &[u8]) -> Option<Retryable<'_>> {
+    let word = |i: usize| message.get(i * 32..i * 32 + 32);
+    let is_address = |w: &[u8]| w[..12].iter().all(|b| *b == 0);
+    let length = read_usize(message, 8 * 32)?;
+    if message.len() != 9 * 32 + length || ![0, 4, 5].iter().all(|i| word(*i).is_some_and(is_address)) {
+        return Option::None;
+    }
+    let gas_limit = word(6)?;
+    if gas_limit[..24].iter().any(|b| *b != 0) {
+        return Option::None;
+    }
+    Option::Some(Retryable {
+        to: &word(0)?[12..],
+        l2_call_value: word(1)?,
+        deposit: word(2)?,
+        max_submission_cost: word(3)?,
+        excess_fee_refund_address: &word(4)?[12..],
+        call_value_refund_address: &word(5)?[12..],
+        gas_limit: u64::from_be_bytes(gas_limit[24..].try_into().ok()?),
+        max_fee_per_gas: word(7)?,
+        data: &message[9 * 32..],
+    })
+}
+
+impl Retryable<'_> {
+    fn insert_into(&self, output: &mut HashMap<String, Value>) {
+        output.insert("to".to_string(), address(self.to));
+        output.insert("l2CallValue".to_string(), uint(self.l2_call_value));
+        output.insert("deposit".to_string(), uint(self.deposit));
+        output.insert("maxSubmissionCost".to_string(), uint(self.max_submission_cost));
+        output.insert("excessFeeRefundAddress".to_string(), address(self.excess_fee_refund_address));
+        output.insert("callValueRefundAddress".to_string(), address(self.call_value_refund_address));
+        output.insert("gasLimit".to_string(), Value::from(self.gas_limit));
+        output.insert("maxFeePerGas".to_string(), uint(self.max_fee_per_gas));
+        insert_data(output, self.data);
+    }
+
+    // the ticket id is the hash of the submit retryable transaction the message becomes on l2,
+    // keccak256(0x69 || rlp([chainId, requestId, from, l1BaseFee, deposit, gasFeeCap, gas, retryTo,
+    // retryValue, beneficiary, maxSubmissionFee, feeRefundAddr, retryData])) where the request id is the
+    // message index and a zero recipient is left empty
+    fn ticket_id(&self, chain_id: u64, index: u64, delivered: &Delivered) -> [u8; 32] {
+        let mut request_id = vec![0u8; 24];
+        request_id.extend(index.to_be_bytes());
+        let to: &[u8] = match self.to.iter().all(|b| *b == 0) {
+            true => &[],
+            false => self.to,
+        };
+        let fields = [
+            rlp_bytes(strip_zeros(&chain_id.to_be_bytes())),
+            rlp_bytes(&request_id),
+            rlp_bytes(&delivered.sender),
+            rlp_bytes(strip_zeros(&delivered.base_fee)),
+            rlp_bytes(strip_zeros(self.deposit)),
+            rlp_bytes(strip_zeros(self.max_fee_per_gas)),
+            rlp_bytes(strip_zeros(&self.gas_limit.to_be_bytes())),
+            rlp_bytes(to),
+            rlp_bytes(strip_zeros(self.l2_call_value)),
+            rlp_bytes(self.call_value_refund_address),
+            rlp_bytes(strip_zeros(self.max_submission_cost)),
+            rlp_bytes(self.excess_fee_refund_address),
+            rlp_bytes(self.data),
+        ];
+        let mut encoded = vec![SUBMIT_RETRYABLE_TX_TYPE];
+        encoded.extend(rlp_list(&fields.concat()));
+        Keccak256::digest(&encoded).into()
+    }
+}
+
+fn address(bytes: &[u8]) -> Value {
+    Value::String(format!("0x{}", hex::encode(&bytes[bytes.len().saturating_sub(20)..])))
+}
+
+fn bytes32(bytes: &[u8]) -> Value {
+    Value::String(format!("0x{}", hex::encode(bytes)))
+}
+
+fn uint(bytes: &[u8]) -> Value {
+    Value::String(U256::from_be_slice(bytes).to_string())
+}
+
+// a word small enough to be a json number, e.g. a block number, larger ones are decimal strings
+fn number(bytes: &[u8]) -> Value {
+    let n = U256::from_be_slice(bytes);
+    match n.0[1..].iter().all(|l| *l == 0) {
+        true => Value::from(n.low_u64()),
+        false => Value::String(n.to_string()),
+    }
+}
+
+// the rlp encoding of a byte string, integers are their big-endian bytes without leading zeros
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    match bytes {
+        [b] if *b < 0x80 => vec![*b],
+        _ => {
+            let mut encoded = rlp_length(bytes.len(), 0x80);
+            encoded.extend(bytes);
+            encoded
+        }
+    }
+}
+
+// the rlp encoding of a list of already encoded items
+fn rlp_list(items: &[u8]) -> Vec<u8> {
+    let mut encoded = rlp_length(items.len(), 0xc0);
+    encoded.extend(items);
+    encoded
+}
+
+// the rlp prefix of a payload, short payloads add their length to the offset, longer ones are
+// followed by the big-endian bytes of their length
+fn rlp_length(length: usize, offset: u8) -> Vec<u8> {
+    match length < 56 {
+        true => vec![offset + length as u8],
+        false => {
+            let bytes = (length as u64).to_be_bytes();
+            let bytes = strip_zeros(&bytes);
+            let mut encoded = vec![offset + 55 + bytes.len() as u8];
+            encoded.extend(bytes);
+            encoded
+        }
+    }
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    &bytes[zeros..]
+}
+
+// a 0x-prefixed 32-byte hash
+fn hash_bytes(hash: &str) -> Option<Vec<u8>> {
+    let hash = hash.trim().to_lowercase();
+    hex::decode(hash.strip_prefix("0x").unwrap_or(&hash)).ok().filter(|h| h.len() == 32)
+}
+
+// a word of the data read as an offset or length, None when it is out of bounds or too large
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = data.get(offset..offset.checked_add(32)?)?;
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(word[24..].try_into().ok()?)).ok(),
+        false => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
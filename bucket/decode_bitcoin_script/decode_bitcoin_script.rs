@@ -0,0 +1,484 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    #[serde(default)]
+    pub network: Network,
+}
+
+// the network addresses are encoded for, signet shares the addresses of testnet
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    // the base58check version bytes of pay-to-pubkey-hash and pay-to-script-hash addresses
+    fn versions(&self) -> (u8, u8) {
+        match self {
+            Network::Mainnet => (0x00, 0x05),
+            _ => (0x6f, 0xc4),
+        }
+    }
+
+    // the human-readable part of segwit addresses
+    fn hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_ALPHABET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+// the opcodes the standard scripts are made of
+const OP_0: u8 = 0x00;
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+const OP_RETURN: u8 = 0x6a;
+const OP_DUP: u8 = 0x76;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // the outputs of a whole transaction are decoded in place, and so is a record of a single output,
+    // records holding no output script are passed through as they are
+    match input.get_mut("vout").and_then(|v| v.as_array_mut()) {
+        Option::Some(outputs) => {
+            for output in outputs.iter_mut().filter_map(|o| o.as_object_mut()) {
+                decode_output(output, params.network);
+            }
+        }
+        Option::None => {
+            let mut output: serde_json::Map<String, Value> = input.into_iter().collect();
+            decode_output(&mut output, params.network);
+            input = output.into_iter().collect();
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// classify the script of a transaction output and insert its type, address and data, the script is
+// the `scriptPubKey` of bitcoind, in its `hex` or as a string, or the `scriptpubkey` of esplora
+fn decode_output(output: &mut serde_json::Map<String, Value>, network: Network) {
+    let script = output
+        .get("scriptPubKey")
+        .and_then(|s| s.get("hex").or(Option::Some(s)))
+        .or(output.get("scriptpubkey"))
+        .or(output.get("script"))
+        .and_then(|s| s.as_str())
+        .and_then(|s| hex::decode(s.trim().trim_start_matches("0x")).ok());
+    let script = match script {
+        Option::Some(script) => script,
+        Option::None => return,
+    };
+
+    let decoded = decode_script(&script, network);
+    output.insert("scriptType".to_string(), Value::String(decoded.kind.to_string()));
+    output.insert("address".to_string(), serde_json::json!(decoded.address));
+    if decoded.kind == "opReturn" {
+        let data: Vec<u8> = decoded.pushes.concat();
+        // memos are commonly text, other payloads such as omni or runes messages are binary
+        let text = String::from_utf8(data.clone()).ok().filter(|t| !t.chars().any(|c| c.is_control()));
+        output.insert("data".to_string(), Value::String(hex::encode(&data)));
+        output.insert("dataText".to_string(), serde_json::json!(text));
+        output.insert("dataPushes".to_string(), serde_json::json!(decoded.pushes.iter().map(hex::encode).collect::<Vec<String>>()));
+    }
+    if let Option::Some(required) = decoded.required {
+        output.insert("requiredSignatures".to_string(), Value::from(required));
+    }
+    if !decoded.pubkeys.is_empty() {
+        output.insert("pubkeys".to_string(), serde_json::json!(decoded.pubkeys.iter().map(hex::encode).collect::<Vec<String>>()));
+    }
+}
+
+struct Decoded {
+    kind: &'static str,
+    address: Option<String>,
+    // the data pushed after OP_RETURN
+    pushes: Vec<Vec<u8>>,
+    // the keys of bare pay-to-pubkey and multisig scripts, and how many must sign the latter
+    pubkeys: Vec<Vec<u8>>,
+    required: Option<u64>,
+}
+
+// classify an output script by the templates of the standard scripts, anything else is nonstandard
+fn decode_script(script: &[u8], network: Network) -> Decoded {
+    let mut decoded = Decoded { kind: "nonstandard", address: Option::None, pushes: Vec::new(), pubkeys: Vec::new(), required: Option::None };
+    let (p2pkh_version, p2sh_version) = network.versions();
+
+    match script {
+        // OP_DUP OP_HASH160 <20-byte pubkey hash> OP_EQUALVERIFY OP_CHECKSIG
+        [OP_DUP, OP_HASH160, 20, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG] if hash.len() == 20 => {
+            decoded.kind = "p2pkh";
+            decoded.address = Option::Some(base58check(p2pkh_version, hash));
+        }
+        // OP_HASH160 <20-byte script hash> OP_EQUAL
+        [OP_HASH160, 20, hash @ .., OP_EQUAL] if hash.len() == 20 => {
+            decoded.kind = "p2sh";
+            decoded.address = Option::Some(base58check(p2sh_version, hash));
+        }
+        // a witness version then its program, version 0 programs are a 20-byte key hash or a 32-byte
+        // script hash and version 1 programs of 32 bytes are taproot keys
+        [version, length, program @ ..]
+            if (*version == OP_0 || (OP_1..=OP_16).contains(version))
+                && (2..=40).contains(length)
+                && program.len() == *length as usize =>
+        {
+            let version = match *version {
+                OP_0 => 0,
+                v => v - OP_1 + 1,
+            };
+            decoded.kind = match (version, program.len()) {
+                (0, 20) => "p2wpkh",
+                (0, 32) => "p2wsh",
+                (0, _) => return decoded,
+                (1, 32) => "p2tr",
+                _ => "witnessUnknown",
+            };
+            decoded.address = Option::Some(segwit_address(network.hrp(), version, program));
+        }
+        // OP_RETURN followed by data pushes makes the output provably unspendable
+        [OP_RETURN, rest @ ..] => {
+            if let Option::Some(pushes) = read_pushes(rest) {
+                decoded.kind = "opReturn";
+                decoded.pushes = pushes;
+            }
+        }
+        // <33 or 65-byte pubkey> OP_CHECKSIG, the pubkey has no address of its own
+        [length, pubkey @ .., OP_CHECKSIG] if (*length == 33 || *length == 65) && pubkey.len() == *length as usize => {
+            decoded.kind = "p2pk";
+            decoded.pubkeys = vec![pubkey.to_vec()];
+        }
+        // OP_m <pubkeys> OP_n OP_CHECKMULTISIG, m of the n keys must sign
+        [m, rest @ .., n, OP_CHECKMULTISIG] if (OP_1..=OP_16).contains(m) && (OP_1..=OP_16).contains(n) => {
+            let pubkeys = read_pushes(rest).unwrap_or_default();
+            let (m, n) = ((m - OP_1 + 1) as usize, (n - OP_1 + 1) as usize);
+            if m <= n && pubkeys.len() == n && pubkeys.iter().all(|k| k.len() == 33 || k.len() == 65) {
+                decoded.kind = "multisig";
+                decoded.pubkeys = pubkeys;
+                decoded.required = Option::Some(m as u64);
+            }
+        }
+        _ => {}
+    }
+    decoded
+}
+
+// read a script made only of data pushes, none if it holds another opcode or a push runs past its end
+fn read_pushes(script: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut pushes = Vec::new();
+    let mut pos = 0;
+    while pos < script.len() {
+        let opcode = script[pos];
+        pos += 1;
+        let length = match opcode {
+            // OP_0 pushes an empty array
+            OP_0 => 0,
+            1..=0x4b => opcode as usize,
+            OP_PUSHDATA1 => read_length(script, &mut pos, 1)?,
+            OP_PUSHDATA2 => read_length(script, &mut pos, 2)?,
+            OP_PUSHDATA4 => read_length(script, &mut pos, 4)?,
+            // OP_1NEGATE and OP_1 to OP_16 push the small integer they name
+            0x4f | OP_1..=OP_16 => {
+                pushes.push(vec![match opcode {
+                    0x4f => 0x81,
+                    n => n - OP_1 + 1,
+                }]);
+                continue;
+            }
+            _ => return Option::None,
+        };
+        pushes.push(script.get(pos..pos + length)?.to_vec());
+        pos += length;
+    }
+    Option::Some(pushes)
+}
+
+// read the little-endian length of an OP_PUSHDATA push
+fn read_length(script: &[u8], pos: &mut usize, size: usize) -> Option<usize> {
+    let bytes = script.get(*pos..*pos + size)?;
+    *pos += size;
+    Option::Some(bytes.iter().rev().fold(0usize, |n, b| n << 8 | *b as usize))
+}
+
+// a base58check address, the version byte and payload followed by the first 4 bytes of their double sha256
+fn base58check(version: u8, payload: &[u8]) -> String {
+    let mut bytes = vec![version];
+    bytes.extend_from_slice(payload);
+    let checksum = sha256(&sha256(&bytes));
+    bytes.extend_from_slice(&checksum[..4]);
+    base58(&bytes)
+}
+
+// base58 with the bitcoin alphabet, each leading zero byte is kept as a leading '1'
+fn base58(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = Vec::new();
+    for b in bytes {
+        let mut carry = *b as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    let mut out = "1".repeat(zeros);
+    out.extend(digits.iter().rev().map(|d| BASE58_ALPHABET[*d as usize] as char));
+    out
+}
+
+// a segwit address, BIP-173 bech32 for version 0 programs and BIP-350 bech32m for later versions
+fn segwit_address(hrp: &str, version: u8, program: &[u8]) -> String {
+    // the program is regrouped from 8-bit bytes into 5-bit words, padding the last one with zeros
+    let mut data = vec![version];
+    let (mut acc, mut bits) = (0u32, 0);
+    for b in program {
+        acc = acc << 8 | *b as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            data.push((acc >> bits & 31) as u8);
+        }
+    }
+    if bits > 0 {
+        data.push((acc << (5 - bits) & 31) as u8);
+    }
+
+    let constant = match version {
+        0 => 1,
+        _ => 0x2bc830a3,
+    };
+    let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 31));
+    values.extend(&data);
+    values.extend([0u8; 6]);
+    let checksum = bech32_polymod(&values) ^ constant;
+    data.extend((0..6).map(|i| (checksum >> (5 * (5 - i)) & 31) as u8));
+
+    let encoded: String = data.iter().map(|d| BECH32_ALPHABET[*d as usize] as char).collect();
+    format!("{}1{}", hrp, encoded)
+}
+
+// the bch code checksum over the 5-bit values shared by bech32 and bech32m
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum = 1u32;
+    for v in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ *v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            if top >> i & 1 == 1 {
+                checksum ^= g;
+            }
+        }
+    }
+    checksum
+}
+
+// sha256 as specified by FIPS 180-4, bitcoin hashes its addresses with it and sha3 is all we ship
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    // the message is padded with a one bit, zeros and its bit length to whole 64-byte blocks
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend(((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([block[4 * i], block[4 * i + 1], block[4 * i + 2], block[4 * i + 3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, s) in state.iter().enumerate() {
+        digest[4 * i..4 * i + 4].copy_from_slice(&s.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_matches_the_fips_180_vectors() {
+        let digest = |data: &[u8]| hex::encode(sha256(data));
+        assert_eq!(digest(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(digest(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        // 56 bytes leave no room for the length in the first block so the padding takes a second one
+        assert_eq!(
+            digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        );
+    }
+
+    #[test]
+    fn base58check_encodes_the_genesis_coinbase_address() {
+        let hash = hex::decode("62e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap();
+        assert_eq!(base58check(0x00, &hash), "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+    }
+
+    #[test]
+    fn segwit_address_matches_the_bip173_and_bip350_vectors() {
+        let program = hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        assert_eq!(segwit_address("bc", 0, &program), "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        let program = [program.clone(), program].concat();
+        assert_eq!(
+            segwit_address("bc", 1, &program),
+            "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+        );
+
+        let script = hex::decode("5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433").unwrap();
+        let decoded = decode_script(&script, Network::Testnet);
+        assert_eq!(decoded.kind, "p2tr");
+        assert_eq!(decoded.address.as_deref(), Option::Some("tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c"));
+    }
+}
@@ -0,0 +1,337 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass transactions that carry no blobs through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // the fraction the blob base fee is raised by per excess blob gas, 3338477 since cancun, prague
+    // raised it to 5007716 along with the blob target
+    #[serde(default)]
+    pub blob_base_fee_update_fraction: Option<u64>,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+// the EIP-4844 constants, each blob uses a fixed amount of blob gas priced at least 1 wei
+const GAS_PER_BLOB: u64 = 131072;
+const MIN_BASE_FEE_PER_BLOB_GAS: u64 = 1;
+const BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 3338477;
+
+// the version byte of blob hashes committing to a KZG commitment, the only version so far
+const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // only type 3 transactions carry blobs, and they carry at least one
+    let empty: Vec<Value> = Vec::new();
+    let blob_hashes: Vec<String> = input
+        .get("blobVersionedHashes")
+        .and_then(|v| v.as_array())
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|h| h.as_str())
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    if blob_hashes.is_empty() {
+        let result_json = match params.keep_unmatched {
+            true => Option::Some(serde_json::to_vec(&input)?),
+            false => Option::None,
+        };
+        lens_sdk::free_transport_buffer(ptr)?;
+        return match result_json {
+            Option::Some(result_json) => Ok(Some(result_json)),
+            Option::None => try_transform(),
+        };
+    }
+
+    // the blob gas price is known from a joined receipt, otherwise it follows from the excess blob gas of
+    // a joined block, the price ceiling the sender agreed to is part of the transaction
+    let update_fraction = params.blob_base_fee_update_fraction.unwrap_or(BLOB_BASE_FEE_UPDATE_FRACTION);
+    let excess_blob_gas = input.get("excessBlobGas").and_then(quantity);
+    let blob_gas_price = input
+        .get("blobGasPrice")
+        .and_then(quantity)
+        .or(excess_blob_gas.and_then(|excess| blob_base_fee(excess.low_u64(), update_fraction)));
+    let max_fee_per_blob_gas = input.get("maxFeePerBlobGas").and_then(quantity);
+
+    let blob_gas = GAS_PER_BLOB * blob_hashes.len() as u64;
+    let blob_fee = blob_gas_price.and_then(|price| price.checked_mul_u64(GAS_PER_BLOB));
+    let max_blob_fee = max_fee_per_blob_gas.and_then(|price| price.checked_mul_u64(GAS_PER_BLOB));
+
+    // the block's blob gas covers the blobs of all its transactions
+    let block_blob_gas_used = input.get("blobGasUsed").and_then(quantity).map(|n| n.low_u64());
+
+    let mut documents = VecDeque::new();
+    for (i, blob_hash) in blob_hashes.iter().enumerate() {
+        let digits = blob_hash.strip_prefix("0x").unwrap_or(blob_hash);
+        let version = match digits.len() == 64 {
+            true => hex::decode(&digits[..2]).ok().and_then(|b| b.first().copied()),
+            false => Option::None,
+        };
+
+        let mut document = serde_json::Map::new();
+        document.insert("blobIndex".to_string(), Value::from(i));
+        document.insert("blobVersionedHash".to_string(), Value::String(blob_hash.clone()));
+        document.insert("version".to_string(), serde_json::json!(version));
+        document.insert("kzg".to_string(), Value::Bool(version == Option::Some(VERSIONED_HASH_VERSION_KZG)));
+        document.insert("blobGas".to_string(), Value::from(GAS_PER_BLOB));
+        document.insert("blobGasPrice".to_string(), serde_json::json!(blob_gas_price.map(|n| n.to_string())));
+        document.insert("blobFee".to_string(), serde_json::json!(blob_fee.map(|n| n.to_string())));
+        document.insert("maxFeePerBlobGas".to_string(), serde_json::json!(max_fee_per_blob_gas.map(|n| n.to_string())));
+        document.insert("maxBlobFee".to_string(), serde_json::json!(max_blob_fee.map(|n| n.to_string())));
+        document.insert("blobCount".to_string(), Value::from(blob_hashes.len()));
+        document.insert("transactionBlobGas".to_string(), Value::from(blob_gas));
+        document.insert("blockBlobGasUsed".to_string(), serde_json::json!(block_blob_gas_used));
+        document.insert("blockBlobCount".to_string(), serde_json::json!(block_blob_gas_used.map(|gas| gas / GAS_PER_BLOB)));
+        document.insert("excessBlobGas".to_string(), serde_json::json!(excess_blob_gas.map(|n| n.low_u64())));
+        for key in ["hash", "from", "to", "blockHash", "blockNumber", "transactionIndex"] {
+            document.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+        }
+        documents.push_back(serde_json::to_vec(&document)?);
+    }
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    let first = documents.pop_front();
+    PENDING.write()?.extend(documents);
+    match first {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
+
+// the blob base fee of a block with the given excess blob gas, the fake_exponential of EIP-4844 which
+// approximates MIN_BASE_FEE_PER_BLOB_GAS * e ** (excess / update_fraction) by its taylor series
+fn blob_base_fee(excess_blob_gas: u64, update_fraction: u64) -> Option<U256> {
+    let mut output = U256::default();
+    let mut accumulator = U256::from_u64(MIN_BASE_FEE_PER_BLOB_GAS).checked_mul_u64(update_fraction)?;
+    let mut i = 1u64;
+    while !accumulator.is_zero() {
+        output = output.checked_add(accumulator)?;
+        accumulator = accumulator.checked_mul_u64(excess_blob_gas)?.div_rem(update_fraction.checked_mul(i)?).0;
+        i += 1;
+    }
+    Option::Some(output.div_rem(update_fraction).0)
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from_u64),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => {
+                // quantities are encoded without leading zeros so their digit count may be odd
+                let padded = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+                match padded.len() <= 64 {
+                    true => hex::decode(padded).ok().map(|bytes| U256::from_be_slice(&bytes)),
+                    false => Option::None,
+                }
+            }
+            Option::None => U256::from_dec_str(s.trim()),
+        },
+        _ => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    fn from_u64(n: u64) -> U256 {
+        U256([n, 0, 0, 0])
+    }
+
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            n = n.checked_mul_u64(10)?.checked_add_u64(c.to_digit(10)? as u64)?;
+        }
+        Option::Some(n)
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    fn checked_mul_u64(self, factor: u64) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let acc = self.0[i] as u128 * factor as u128 + carry;
+            *limb = acc as u64;
+            carry = acc >> 64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_add_u64(self, term: u64) -> Option<U256> {
+        let mut limbs = self.0;
+        let mut carry = term;
+        for limb in limbs.iter_mut() {
+            let (v, overflow) = limb.overflowing_add(carry);
+            *limb = v;
+            carry = overflow as u64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_add(self, other: U256) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_add(other.0[i]);
+            let (v, o2) = v.overflowing_add(carry as u64);
+            *limb = v;
+            carry = o1 || o2;
+        }
+        match carry {
+            false => Option::Some(U256(limbs)),
+            true => Option::None,
+        }
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,296 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // remove the `transactions` of full blocks, leaving only their count
+    #[serde(default)]
+    pub drop_transactions: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    // blob gas fields are only present on blocks after cancun and are left out before it
+    for key in ["number", "timestamp", "gasLimit", "gasUsed", "size", "blobGasUsed", "excessBlobGas"] {
+        if let Option::Some(n) = input.get(key).and_then(quantity) {
+            input.insert(key.to_string(), number(n));
+        }
+    }
+    for key in ["difficulty", "totalDifficulty"] {
+        if let Option::Some(n) = input.get(key).and_then(quantity) {
+            input.insert(key.to_string(), Value::String(n.to_string()));
+        }
+    }
+
+    if let Option::Some(timestamp) = input.get("timestamp").and_then(quantity) {
+        input.insert("timestampIso".to_string(), Value::String(iso_8601(timestamp.low_u64())));
+    }
+
+    let gas_used = input.get("gasUsed").and_then(quantity).map(|n| n.low_u64());
+    let gas_limit = input.get("gasLimit").and_then(quantity).map(|n| n.low_u64()).filter(|n| *n > 0);
+    if let (Option::Some(used), Option::Some(limit)) = (gas_used, gas_limit) {
+        input.insert("gasUtilization".to_string(), serde_json::json!(used as f64 * 100.0 / limit as f64));
+    }
+
+    // the base fee was introduced by london, it is kept in wei and also given in gwei where fees are read
+    if let Option::Some(base_fee) = input.get("baseFeePerGas").and_then(quantity) {
+        input.insert("baseFeePerGas".to_string(), Value::String(base_fee.to_string()));
+        input.insert("baseFeePerGasGwei".to_string(), Value::String(gwei(base_fee)));
+    }
+
+    // withdrawals exist since shanghai, blocks before it have none to count
+    if let Option::Some(withdrawals) = input.get("withdrawals").and_then(|v| v.as_array()) {
+        input.insert("withdrawalCount".to_string(), Value::from(withdrawals.len()));
+    }
+
+    // the transactions are hashes or, on full blocks, whole transaction objects
+    if let Option::Some(transactions) = input.get("transactions").and_then(|v| v.as_array()) {
+        input.insert("transactionCount".to_string(), Value::from(transactions.len()));
+    }
+    if params.drop_transactions {
+        input.remove("transactions");
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// format a unix timestamp as an ISO-8601 date and time in UTC, e.g. "2015-07-30T15:26:28Z"
+fn iso_8601(timestamp: u64) -> String {
+    let (days, seconds) = (timestamp / 86400, timestamp % 86400);
+
+    // the civil date of a day count since 1970-01-01, after Howard Hinnant's civil_from_days,
+    // counted in 400-year eras of 146097 days starting on march 1st so leap days fall at the end
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, seconds / 3600, seconds % 3600 / 60, seconds % 60
+    )
+}
+
+// a wei amount in gwei as an exact decimal string, e.g. "12.5"
+fn gwei(wei: U256) -> String {
+    let (whole, fraction) = wei.div_rem(1_000_000_000);
+    match fraction {
+        0 => whole.to_string(),
+        _ => format!("{}.{}", whole, format!("{:09}", fraction).trim_end_matches('0')),
+    }
+}
+
+// a quantity small enough to be a json number, e.g. gas used, larger ones are decimal strings
+fn number(n: U256) -> Value {
+    match n.0[1..].iter().all(|l| *l == 0) {
+        true => Value::from(n.low_u64()),
+        false => Value::String(n.to_string()),
+    }
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from_u64),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => {
+                // quantities are encoded without leading zeros so their digit count may be odd
+                let padded = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+                match padded.len() <= 64 {
+                    true => hex::decode(padded).ok().map(|bytes| U256::from_be_slice(&bytes)),
+                    false => Option::None,
+                }
+            }
+            Option::None => U256::from_dec_str(s.trim()),
+        },
+        _ => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    fn from_u64(n: u64) -> U256 {
+        U256([n, 0, 0, 0])
+    }
+
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            n = n.checked_mul_u64(10)?.checked_add_u64(c.to_digit(10)? as u64)?;
+        }
+        Option::Some(n)
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    fn checked_mul_u64(self, factor: u64) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let acc = self.0[i] as u128 * factor as u128 + carry;
+            *limb = acc as u64;
+            carry = acc >> 64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_add_u64(self, term: u64) -> Option<U256> {
+        let mut limbs = self.0;
+        let mut carry = term;
+        for limb in limbs.iter_mut() {
+            let (v, overflow) = limb.overflowing_add(carry);
+            *limb = v;
+            carry = overflow as u64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
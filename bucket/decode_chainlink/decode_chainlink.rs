@@ -0,0 +1,291 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't aggregator round events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // the decimals of the feed's answers, 8 for the usd feeds and 18 for most eth ones
+    #[serde(default = "decimals_default")]
+    pub decimals: u32,
+    // the decimals of particular aggregators keyed by their address, overriding `decimals`
+    #[serde(default)]
+    pub feeds: HashMap<String, u32>,
+}
+
+fn decimals_default() -> u32 {
+    8
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            keep_unmatched: false,
+            decimals: decimals_default(),
+            feeds: HashMap::new(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// keccak256("AnswerUpdated(int256,uint256,uint256)") and keccak256("NewRound(uint256,address,uint256)")
+// of the chainlink aggregator interface, emitted by the aggregators and not by the proxies in front of them
+const ANSWER_UPDATED_TOPIC: &str = "0x0559884fd3a460db3073b7fc896cc77986f16e378210ded43186175bf646fc5f";
+const NEW_ROUND_TOPIC: &str = "0x0109fc6f55cf40689f02fbaad7af7fe7bbac8a3d2186600afc7d3e10cac60271";
+
+// the fields of the round schema, those that an event doesn't carry are null
+const FIELDS: &[&str] = &[
+    "eventName", "feed", "roundId", "answer", "price", "decimals", "updatedAt", "startedBy", "startedAt",
+];
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // the addresses are matched against the lowercased address of the log
+    parameter.feeds = parameter.feeds
+        .into_iter()
+        .map(|(address, decimals)| (address.trim().to_lowercase(), decimals))
+        .collect();
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input, &params) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the round document of an aggregator event, the answer is signed and given raw and as a price with
+// the feed's decimals, AnswerUpdated carries everything in its topics and NewRound the start time of
+// the round in its data, logs of other events give None
+fn decode_log(input: &HashMap<String, Value>, params: &Parameters) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    let words: Vec<Vec<u8>> = topics
+        .iter()
+        .skip(1)
+        .map(|t| hex::decode(t.strip_prefix("0x").unwrap_or(t)).ok().filter(|w| w.len() == 32))
+        .collect::<Option<Vec<Vec<u8>>>>()?;
+    let data = input.get("data").and_then(|d| d.as_str()).unwrap_or_default().trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+
+    let feed = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    let decimals = feed.as_ref().and_then(|f| params.feeds.get(f)).copied().unwrap_or(params.decimals);
+
+    let mut output: HashMap<String, Value> = FIELDS.iter().map(|key| (key.to_string(), Value::Null)).collect();
+    match (topics.first()?.as_str(), words.len()) {
+        (ANSWER_UPDATED_TOPIC, 3) => {
+            let answer = signed(&words[0]);
+            output.insert("eventName".to_string(), Value::String("AnswerUpdated".to_string()));
+            output.insert("roundId".to_string(), number(U256::from_be_slice(&words[1])));
+            output.insert("price".to_string(), Value::String(scale_decimal(&answer, decimals as usize)));
+            output.insert("answer".to_string(), Value::String(answer));
+            output.insert("decimals".to_string(), Value::from(decimals));
+            output.insert("updatedAt".to_string(), number(U256::from_be_slice(&words[2])));
+        }
+        (NEW_ROUND_TOPIC, 2) if data.len() == 32 => {
+            output.insert("eventName".to_string(), Value::String("NewRound".to_string()));
+            output.insert("roundId".to_string(), number(U256::from_be_slice(&words[0])));
+            output.insert("startedBy".to_string(), Value::String(format!("0x{}", hex::encode(&words[1][12..]))));
+            output.insert("startedAt".to_string(), number(U256::from_be_slice(&data)));
+        }
+        _ => return Option::None,
+    }
+    output.insert("feed".to_string(), serde_json::json!(feed));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// a two's complement word as a signed decimal string
+fn signed(word: &[u8]) -> String {
+    let n = U256::from_be_slice(word);
+    match word[0] & 0x80 != 0 {
+        true => format!("-{}", n.wrapping_neg()),
+        false => n.to_string(),
+    }
+}
+
+// shift the decimal point of an integer string `decimals` places to the left,
+// trailing fractional zeros are dropped, e.g. ("-1500", 3) -> "-1.5"
+fn scale_decimal(integer: &str, decimals: usize) -> String {
+    let (sign, digits) = match integer.strip_prefix('-') {
+        Option::Some(digits) => ("-", digits),
+        Option::None => ("", integer),
+    };
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => format!("{}{}", sign, whole),
+        false => format!("{}{}.{}", sign, whole, fraction),
+    }
+}
+
+// a quantity small enough to be a json number, e.g. gas used, larger ones are decimal strings
+fn number(n: U256) -> Value {
+    match n.0[1..].iter().all(|l| *l == 0) {
+        true => Value::from(n.low_u64()),
+        false => Value::String(n.to_string()),
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // two's complement negation modulo 2^256
+    fn wrapping_neg(self) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut carry = true;
+        for (i, l) in self.0.iter().enumerate() {
+            let (v, overflow) = (!l).overflowing_add(carry as u64);
+            limbs[i] = v;
+            carry = overflow;
+        }
+        U256(limbs)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,336 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't money market events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // the underlying asset of cTokens and the base asset of comets keyed by the market's address,
+    // their events don't carry it so `asset` is null for the markets left out
+    #[serde(default)]
+    pub underlying: HashMap<String, String>,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// keccak256 of the compound v2 cToken events, none of their arguments are indexed
+// "Mint(address,uint256,uint256)"
+const MINT_TOPIC: &str = "0x4c209b5fc8ad50758f13e2e1088ba56a560dff690a1c6fef26394f4c03821c4f";
+// "Redeem(address,uint256,uint256)"
+const REDEEM_TOPIC: &str = "0xe5b754fb1abb7f01b499791d0b820ae3b6af3424ac1c59768edb53f4ec31a929";
+// "Borrow(address,uint256,uint256,uint256)"
+const BORROW_TOPIC: &str = "0x13ed6866d4e1ee6da46f845c46d7e54120883d75c5ea9a2dacc1c4ca8984ab80";
+// "RepayBorrow(address,address,uint256,uint256,uint256)"
+const REPAY_BORROW_TOPIC: &str = "0x1a2a22cb034d26d1854bdc6666a5b91fe25efbbb5dcad3b0355478d6f5c362a1";
+// "LiquidateBorrow(address,address,uint256,address,uint256)"
+const LIQUIDATE_BORROW_TOPIC: &str = "0x298637f684da70674f26509b10f07ec2fbc77a335ab1e7d6215a4b2484d8bb52";
+
+// and of the compound v3 comet events
+// "Supply(address,address,uint256)"
+const SUPPLY_TOPIC: &str = "0xd1cf3d156d5f8f0d50f6c122ed609cec09d35c9b9fb3fff6ea0959134dae424e";
+// "Withdraw(address,address,uint256)"
+const WITHDRAW_TOPIC: &str = "0x9b1bfa7fa9ee420a16e124f794c35ac9f90472acc99140eb2f6447c714cad8eb";
+// "SupplyCollateral(address,address,address,uint256)"
+const SUPPLY_COLLATERAL_TOPIC: &str = "0xfa56f7b24f17183d81894d3ac2ee654e3c26388d17a28dbd9549b8114304e1f4";
+// "WithdrawCollateral(address,address,address,uint256)"
+const WITHDRAW_COLLATERAL_TOPIC: &str = "0xd6d480d5b3068db003533b170d67561494d72e3bf9fa40a266471351ebba9e16";
+// "AbsorbDebt(address,address,uint256,uint256)"
+const ABSORB_DEBT_TOPIC: &str = "0x1547a878dc89ad3c367b6338b4be6a65a5dd74fb77ae044da1e8747ef1f4f62f";
+// "AbsorbCollateral(address,address,address,uint256,uint256)"
+const ABSORB_COLLATERAL_TOPIC: &str = "0x9850ab1af75177e4a9201c65a2cf7976d5d28e40ef63494b44366f86b2f9412e";
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // the addresses are matched against the lowercased address of the log
+    parameter.underlying = parameter.underlying
+        .into_iter()
+        .map(|(market, asset)| (market.trim().to_lowercase(), asset.trim().to_lowercase()))
+        .collect();
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input, &params) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the lending event schema document of the aave lens for a market event, `user` is the account whose
+// position changes and `asset` the underlying of the market unless the event names a collateral asset,
+// the cToken amounts minted, redeemed and seized, the borrow balances after cToken borrows and repayments
+// and the usd value of comet absorptions are added as fields of their own, comets emit no borrow or
+// repay events, borrowing withdraws the base asset below zero and repaying supplies it, fields an event
+// doesn't carry are null, logs of other events give None
+fn decode_log(input: &HashMap<String, Value>, params: &Parameters) -> Option<HashMap<String, Value>> {
+    let topics: Vec<Vec<u8>> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .map(|t| hex::decode(t?.strip_prefix("0x")?).ok().filter(|w| w.len() == 32))
+        .collect::<Option<Vec<Vec<u8>>>>()?;
+    let data = input.get("data")?.as_str()?.trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+    let words: Vec<&[u8]> = data.chunks(32).collect();
+
+    let mut output: HashMap<String, Value> = [
+        "user", "onBehalfOf", "asset", "amount", "rateMode", "borrowRate", "repayer", "to", "target", "premium",
+        "collateralAsset", "liquidatedCollateralAmount", "liquidator", "receiveAToken", "useATokens", "referralCode",
+        "cTokenAmount", "accountBorrows", "totalBorrows", "usdValue",
+    ]
+    .iter()
+    .map(|key| (key.to_string(), Value::Null))
+    .collect();
+    let mut set = |key: &str, value: Value| output.insert(key.to_string(), value);
+
+    let market = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    let underlying = market.as_ref().and_then(|m| params.underlying.get(m)).cloned();
+    set("asset", serde_json::json!(underlying));
+
+    let topic0 = format!("0x{}", hex::encode(topics.first()?));
+    let (action, version) = match (topic0.as_str(), topics.len(), words.len()) {
+        (MINT_TOPIC, 1, 3) => {
+            set("user", address(words[0]));
+            set("onBehalfOf", address(words[0]));
+            set("amount", uint(words[1]));
+            set("cTokenAmount", uint(words[2]));
+            ("supply", "v2")
+        }
+        (REDEEM_TOPIC, 1, 3) => {
+            set("user", address(words[0]));
+            set("to", address(words[0]));
+            set("amount", uint(words[1]));
+            set("cTokenAmount", uint(words[2]));
+            ("withdraw", "v2")
+        }
+        // compound borrows accrue at the market's variable rate only
+        (BORROW_TOPIC, 1, 4) => {
+            set("user", address(words[0]));
+            set("onBehalfOf", address(words[0]));
+            set("amount", uint(words[1]));
+            set("rateMode", Value::String("variable".to_string()));
+            set("accountBorrows", uint(words[2]));
+            set("totalBorrows", uint(words[3]));
+            ("borrow", "v2")
+        }
+        (REPAY_BORROW_TOPIC, 1, 5) => {
+            set("repayer", address(words[0]));
+            set("user", address(words[1]));
+            set("amount", uint(words[2]));
+            set("accountBorrows", uint(words[3]));
+            set("totalBorrows", uint(words[4]));
+            ("repay", "v2")
+        }
+        // the collateral seized is counted in cTokens of the collateral market
+        (LIQUIDATE_BORROW_TOPIC, 1, 5) => {
+            set("liquidator", address(words[0]));
+            set("user", address(words[1]));
+            set("amount", uint(words[2]));
+            set("collateralAsset", address(words[3]));
+            set("liquidatedCollateralAmount", uint(words[4]));
+            set("cTokenAmount", uint(words[4]));
+            ("liquidation", "v2")
+        }
+        (SUPPLY_TOPIC, 3, 1) => {
+            set("user", address(&topics[1]));
+            set("onBehalfOf", address(&topics[2]));
+            set("amount", uint(words[0]));
+            ("supply", "v3")
+        }
+        (WITHDRAW_TOPIC, 3, 1) => {
+            set("user", address(&topics[1]));
+            set("to", address(&topics[2]));
+            set("amount", uint(words[0]));
+            ("withdraw", "v3")
+        }
+        (SUPPLY_COLLATERAL_TOPIC, 4, 1) => {
+            set("user", address(&topics[1]));
+            set("onBehalfOf", address(&topics[2]));
+            set("asset", address(&topics[3]));
+            set("amount", uint(words[0]));
+            ("supply", "v3")
+        }
+        (WITHDRAW_COLLATERAL_TOPIC, 4, 1) => {
+            set("user", address(&topics[1]));
+            set("to", address(&topics[2]));
+            set("asset", address(&topics[3]));
+            set("amount", uint(words[0]));
+            ("withdraw", "v3")
+        }
+        // an absorption takes over an underwater account, its debt in the base asset and each of its
+        // collateral assets are logged separately
+        (ABSORB_DEBT_TOPIC, 3, 2) => {
+            set("liquidator", address(&topics[1]));
+            set("user", address(&topics[2]));
+            set("amount", uint(words[0]));
+            set("usdValue", uint(words[1]));
+            ("liquidation", "v3")
+        }
+        (ABSORB_COLLATERAL_TOPIC, 4, 2) => {
+            set("liquidator", address(&topics[1]));
+            set("user", address(&topics[2]));
+            set("collateralAsset", address(&topics[3]));
+            set("liquidatedCollateralAmount", uint(words[0]));
+            set("usdValue", uint(words[1]));
+            ("liquidation", "v3")
+        }
+        _ => return Option::None,
+    };
+
+    output.insert("action".to_string(), Value::String(action.to_string()));
+    output.insert("version".to_string(), Value::String(version.to_string()));
+    output.insert("pool".to_string(), serde_json::json!(market));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the address in the low 20 bytes of a word
+fn address(word: &[u8]) -> Value {
+    Value::String(format!("0x{}", hex::encode(&word[12..])))
+}
+
+// an unsigned integer word as a decimal string
+fn uint(word: &[u8]) -> Value {
+    Value::String(U256::from_be_slice(word).to_string())
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
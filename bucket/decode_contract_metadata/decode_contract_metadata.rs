@@ -0,0 +1,321 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass contracts whose code has no metadata trailer through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_metadata(&input) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fixed schema document of the metadata trailer of a contract's runtime code, the compilers append
+// it cbor encoded and followed by its length as two big-endian bytes, which since vyper 0.3.10 count
+// themselves as well, creation code has the constructor
+// arguments after the runtime code so its trailer is generally not at the end, code without a trailer
+// gives None
+fn decode_metadata(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    let code = input
+        .get("code")
+        .or(input.get("bytecode"))
+        .or(input.get("deployedBytecode"))?
+        .as_str()?
+        .trim()
+        .to_lowercase();
+    let code = hex::decode(code.strip_prefix("0x").unwrap_or(&code)).ok()?;
+    if code.len() < 2 {
+        return Option::None;
+    }
+    let length = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize;
+    let (length, metadata) = match trailer(&code, length) {
+        Option::Some(metadata) => (length, metadata),
+        Option::None => (length.checked_sub(2)?, trailer(&code, length.checked_sub(2)?)?),
+    };
+    let start = code.len() - 2 - length;
+
+    // solc appends a map, vyper a map in its early versions and since 0.3.10 an array of the code section
+    // sizes ending in that map
+    let fields = match &metadata {
+        Value::Object(fields) => fields.clone(),
+        Value::Array(items) => items.last()?.as_object()?.clone(),
+        _ => return Option::None,
+    };
+    let (compiler, version) = match (fields.get("solc"), fields.get("vyper")) {
+        (Option::Some(version), _) => ("solc", compiler_version(version)),
+        (_, Option::Some(version)) => ("vyper", compiler_version(version)),
+        _ => match fields.keys().any(|k| ["ipfs", "bzzr0", "bzzr1"].contains(&k.as_str())) {
+            // solc only records its version since 0.5.9
+            true => ("solc", Option::None),
+            false => return Option::None,
+        },
+    };
+
+    // the ipfs hash is a sha2-256 multihash, which is the version 0 cid of the metadata file
+    let ipfs = fields
+        .get("ipfs")
+        .and_then(|h| h.as_str())
+        .and_then(|h| hex::decode(h.strip_prefix("0x").unwrap_or(h)).ok())
+        .map(|h| base58(&h));
+
+    let mut output = HashMap::new();
+    let address = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("address".to_string(), serde_json::json!(address));
+    output.insert("compiler".to_string(), Value::String(compiler.to_string()));
+    output.insert("compilerVersion".to_string(), serde_json::json!(version));
+    output.insert("ipfs".to_string(), serde_json::json!(ipfs));
+    output.insert("bzzr0".to_string(), fields.get("bzzr0").cloned().unwrap_or(Value::Null));
+    output.insert("bzzr1".to_string(), fields.get("bzzr1").cloned().unwrap_or(Value::Null));
+    output.insert("experimental".to_string(), Value::Bool(fields.get("experimental") == Option::Some(&Value::Bool(true))));
+    output.insert("metadata".to_string(), metadata);
+    output.insert("metadataLength".to_string(), Value::from(length));
+    output.insert("codeLength".to_string(), Value::from(start));
+    for key in ["blockHash", "blockNumber"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the cbor item of the `length` bytes before the two length bytes, the trailer must be a single item
+// spanning its length, anything else is code that happens to end in two plausible bytes
+fn trailer(code: &[u8], length: usize) -> Option<Value> {
+    let start = (code.len() - 2).checked_sub(length)?;
+    let trailer = &code[start..code.len() - 2];
+    let mut pos = 0;
+    let metadata = cbor(trailer, &mut pos, 0)?;
+    match pos == trailer.len() {
+        true => Option::Some(metadata),
+        false => Option::None,
+    }
+}
+
+// the compiler version of a release encoded as its three version bytes, or of a prerelease as a string
+fn compiler_version(version: &Value) -> Option<String> {
+    match version {
+        Value::String(s) => match s.strip_prefix("0x").and_then(|h| hex::decode(h).ok()) {
+            Option::Some(bytes) if bytes.len() == 3 => Option::Some(format!("{}.{}.{}", bytes[0], bytes[1], bytes[2])),
+            _ => Option::Some(s.clone()),
+        },
+        Value::Array(parts) => {
+            let parts: Vec<String> = parts.iter().map(|p| p.to_string()).collect();
+            Option::Some(parts.join("."))
+        }
+        _ => Option::None,
+    }
+}
+
+// decode the cbor item at `pos` as json, byte strings become 0x-prefixed hex, indefinite lengths, tags
+// and floats, which the compilers don't emit, give None
+fn cbor(data: &[u8], pos: &mut usize, depth: usize) -> Option<Value> {
+    if depth > 16 {
+        return Option::None;
+    }
+    let initial = *data.get(*pos)?;
+    *pos += 1;
+    let (major, info) = (initial >> 5, initial & 0x1f);
+
+    // the argument follows in the next 1, 2, 4 or 8 bytes unless it is small enough for the initial byte
+    let argument = match info {
+        0..=23 => info as u64,
+        24..=27 => {
+            let width = 1usize << (info - 24);
+            let bytes = data.get(*pos..*pos + width)?;
+            *pos += width;
+            bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64)
+        }
+        _ => return Option::None,
+    };
+
+    match major {
+        0 => Option::Some(Value::from(argument)),
+        1 => Option::Some(Value::from(-1 - argument as i64)),
+        2 | 3 => {
+            let end = pos.checked_add(argument as usize)?;
+            let bytes = data.get(*pos..end)?;
+            *pos = end;
+            match major {
+                2 => Option::Some(Value::String(format!("0x{}", hex::encode(bytes)))),
+                _ => String::from_utf8(bytes.to_vec()).ok().map(Value::String),
+            }
+        }
+        4 => {
+            let mut items = Vec::new();
+            for _ in 0..argument {
+                items.push(cbor(data, pos, depth + 1)?);
+            }
+            Option::Some(Value::Array(items))
+        }
+        5 => {
+            let mut fields = serde_json::Map::new();
+            for _ in 0..argument {
+                let key = match cbor(data, pos, depth + 1)? {
+                    Value::String(key) => key,
+                    key => key.to_string(),
+                };
+                fields.insert(key, cbor(data, pos, depth + 1)?);
+            }
+            Option::Some(Value::Object(fields))
+        }
+        7 => match info {
+            20 => Option::Some(Value::Bool(false)),
+            21 => Option::Some(Value::Bool(true)),
+            22 | 23 => Option::Some(Value::Null),
+            _ => Option::None,
+        },
+        _ => Option::None,
+    }
+}
+
+// base58 with the bitcoin alphabet, each leading zero byte is kept as a leading '1'
+fn base58(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = Vec::new();
+    for b in bytes {
+        let mut carry = *b as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    let mut out = "1".repeat(zeros);
+    out.extend(digits.iter().rev().map(|d| BASE58_ALPHABET[*d as usize] as char));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(code: &str) -> Option<HashMap<String, Value>> {
+        decode_metadata(&HashMap::from([("code".to_string(), Value::from(code))]))
+    }
+
+    #[test]
+    fn decode_metadata_reads_a_vyper_0_3_10_trailer_counting_its_length_bytes() {
+        let output = metadata("0x6003841901238000a16576797065728300030a0013").unwrap();
+        assert_eq!(output["compiler"], "vyper");
+        assert_eq!(output["compilerVersion"], "0.3.10");
+        assert_eq!(output["codeLength"], 2);
+        assert_eq!(output["metadata"], serde_json::json!([0x123, [], 0, { "vyper": [0, 3, 10] }]));
+    }
+
+    #[test]
+    fn decode_metadata_reads_a_solc_trailer() {
+        // the trailer of the WETH9 runtime code, compiled by solc 0.4.19
+        let output = metadata(
+            "0x6003a165627a7a72305820deb4c2ccab3c2fdca32ab3f46728389c2fe2c165d5fafa07661e4e004f6c344a0029",
+        ).unwrap();
+        assert_eq!(output["compiler"], "solc");
+        assert_eq!(output["compilerVersion"], Value::Null);
+        assert_eq!(output["bzzr0"], "0xdeb4c2ccab3c2fdca32ab3f46728389c2fe2c165d5fafa07661e4e004f6c344a");
+        assert_eq!(output["codeLength"], 2);
+        assert_eq!(output["metadataLength"], 41);
+    }
+
+    #[test]
+    fn decode_metadata_ignores_code_without_a_trailer() {
+        assert!(metadata("0x6080604052600080fd").is_none());
+        assert!(metadata("0x00").is_none());
+    }
+}
@@ -0,0 +1,331 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    #[serde(default)]
+    pub encoding: Encoding,
+    #[serde(default)]
+    pub grouping: Grouping,
+    // pass records without events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+// how the attribute keys and values are encoded, tendermint before 0.35 and the cosmos sdk before
+// 0.47 base64 encode them while later versions deliver them as plain strings
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    // base64 when every key of the transaction decodes to an attribute name, plain otherwise
+    #[default]
+    Auto,
+    Base64,
+    Plain,
+}
+
+// what each emitted document holds
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Grouping {
+    // one document per event
+    #[default]
+    Event,
+    // one document per message with its events, the events of the transaction itself, e.g. its
+    // fee and signatures, are grouped without a message index
+    Message,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // the events are in the `tx_result` of tendermint's rpc and the `result` of its websocket, or at
+    // the top of the cosmos sdk's TxResponse
+    let result = input.get("tx_result").or(input.get("result")).filter(|r| r.is_object());
+    let result = result.unwrap_or(&Value::Null);
+    let events = read_events(result.get("logs").or(input.get("logs")), result.get("events").or(input.get("events")));
+    if events.is_empty() {
+        let result_json = match params.keep_unmatched {
+            true => Option::Some(serde_json::to_vec(&input)?),
+            false => Option::None,
+        };
+        lens_sdk::free_transport_buffer(ptr)?;
+        return match result_json {
+            Option::Some(result_json) => Ok(Some(result_json)),
+            Option::None => try_transform(),
+        };
+    }
+
+    let base64 = match params.encoding {
+        Encoding::Base64 => true,
+        Encoding::Plain => false,
+        Encoding::Auto => events
+            .iter()
+            .flat_map(|(_, event)| attributes(event))
+            .all(|(key, _)| base64_decode(key).is_some_and(|k| is_attribute_name(&k))),
+    };
+
+    // the transaction context every document carries, a failed transaction has a non-zero code
+    let context = |document: &mut serde_json::Map<String, Value>| {
+        let hash = input.get("txhash").or(input.get("hash")).or(input.get("txHash")).cloned();
+        document.insert("txHash".to_string(), hash.unwrap_or(Value::Null));
+        document.insert("height".to_string(), serde_json::json!(input.get("height").and_then(quantity)));
+        let code = result.get("code").or(input.get("code")).and_then(quantity).unwrap_or(0);
+        document.insert("code".to_string(), Value::from(code));
+        document.insert("success".to_string(), Value::Bool(code == 0));
+    };
+
+    let decoded: Vec<(Option<u64>, Value)> = events
+        .iter()
+        .enumerate()
+        .map(|(i, (message_index, event))| {
+            let mut attributes = serde_json::Map::new();
+            for (key, value) in self::attributes(event) {
+                let (key, value) = match base64 {
+                    true => (decode_text(key), decode_text(value)),
+                    false => (key.to_string(), value.to_string()),
+                };
+                // a key repeated within an event, e.g. by the events older sdks merged per message,
+                // collects its values in an array
+                match attributes.get_mut(&key) {
+                    Option::Some(Value::Array(values)) => values.push(Value::String(value)),
+                    Option::Some(previous) => *previous = serde_json::json!([previous.clone(), value]),
+                    Option::None => {
+                        attributes.insert(key, Value::String(value));
+                    }
+                }
+            }
+
+            // the sdk tags the events of a message with its index since 0.50
+            let message_index = message_index.or(attributes.get("msg_index").and_then(quantity));
+            let event = serde_json::json!({
+                "eventIndex": i,
+                "messageIndex": message_index,
+                "type": event["type"],
+                "attributes": attributes,
+            });
+            (message_index, event)
+        })
+        .collect();
+
+    let mut documents = VecDeque::new();
+    match params.grouping {
+        Grouping::Event => {
+            for (_, event) in decoded {
+                let mut document = match event {
+                    Value::Object(document) => document,
+                    _ => continue,
+                };
+                context(&mut document);
+                documents.push_back(serde_json::to_vec(&document)?);
+            }
+        }
+        Grouping::Message => {
+            // messages are emitted in the order their first event appears
+            let mut groups: Vec<(Option<u64>, Vec<Value>)> = Vec::new();
+            for (message_index, event) in decoded {
+                match groups.iter_mut().find(|(index, _)| *index == message_index) {
+                    Option::Some((_, events)) => events.push(event),
+                    Option::None => groups.push((message_index, vec![event])),
+                }
+            }
+            for (message_index, events) in groups {
+                let mut document = serde_json::Map::new();
+                document.insert("messageIndex".to_string(), serde_json::json!(message_index));
+                document.insert("eventCount".to_string(), Value::from(events.len()));
+                document.insert("events".to_string(), Value::Array(events));
+                context(&mut document);
+                documents.push_back(serde_json::to_vec(&document)?);
+            }
+        }
+    }
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    let first = documents.pop_front();
+    PENDING.write()?.extend(documents);
+    match first {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
+
+// the events of a transaction with the index of the message that emitted them, the `logs` of sdks
+// before 0.50 group the events per message, json encoded in a string by the oldest ones, while the
+// flat `events` carry the events of the transaction itself too
+fn read_events(logs: Option<&Value>, events: Option<&Value>) -> Vec<(Option<u64>, Value)> {
+    let logs = match logs {
+        Option::Some(Value::String(log)) => serde_json::from_str::<Value>(log).ok(),
+        logs => logs.cloned(),
+    };
+    let empty: Vec<Value> = Vec::new();
+    let grouped: Vec<(Option<u64>, Value)> = logs
+        .as_ref()
+        .and_then(|l| l.as_array())
+        .unwrap_or(&empty)
+        .iter()
+        .enumerate()
+        .flat_map(|(i, log)| {
+            let index = log.get("msg_index").and_then(quantity).unwrap_or(i as u64);
+            log["events"].as_array().unwrap_or(&empty).iter().map(move |e| (Option::Some(index), e.clone()))
+        })
+        .collect();
+    if !grouped.is_empty() {
+        return grouped;
+    }
+
+    events
+        .and_then(|e| e.as_array())
+        .unwrap_or(&empty)
+        .iter()
+        .map(|e| (Option::None, e.clone()))
+        .collect()
+}
+
+// the key and value of each attribute of an event, a missing value is empty
+fn attributes(event: &Value) -> Vec<(&str, &str)> {
+    event["attributes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|a| (a["key"].as_str().unwrap_or_default(), a["value"].as_str().unwrap_or_default()))
+        .collect()
+}
+
+// attribute names are identifiers such as `sender`, `msg_index` or `_contract_address`
+fn is_attribute_name(name: &[u8]) -> bool {
+    !name.is_empty() && name.iter().all(|c| c.is_ascii_alphanumeric() || b"_-./".contains(c))
+}
+
+// the text of a base64 encoded attribute, values that aren't utf-8 are kept in hex
+fn decode_text(encoded: &str) -> String {
+    match base64_decode(encoded) {
+        Option::Some(bytes) => match String::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(e) => format!("0x{}", hex::encode(e.into_bytes())),
+        },
+        Option::None => encoded.to_string(),
+    }
+}
+
+// decode standard base64 with optional padding, none if the input holds another character
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim().trim_end_matches('=');
+    let mut bytes = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in encoded.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Option::None,
+        };
+        acc = acc << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    // a single leftover character can't hold a whole byte
+    match encoded.len() % 4 {
+        1 => Option::None,
+        _ => Option::Some(bytes),
+    }
+}
+
+// read a json number or a decimal string, e.g. a block height or a message index
+fn quantity(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.trim().parse::<u64>().ok(),
+        _ => Option::None,
+    }
+}
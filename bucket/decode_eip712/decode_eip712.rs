@@ -0,0 +1,807 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+// the variants keep the `Error` suffix that ParametersNotSetError gives the errors of every lens
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    TypesError{reason: String},
+    DecodeError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::TypesError { reason } =>
+                write!(f, "The typed data definition could not be read. Reason: {}", reason),
+            ModuleError::DecodeError { reason } =>
+                write!(f, "The typed data could not be decoded. Reason: {}", reason),
+        }
+    }
+}
+
+// the members of a struct type in declaration order, as (name, type) pairs
+type Members = Vec<(String, String)>;
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the EIP-712 domain, e.g. {"name": "USD Coin", "version": "2", "chainId": 1, "verifyingContract": "0x..."}
+    pub domain: serde_json::Map<String, Value>,
+    // the struct types as in eth_signTypedData, EIP712Domain may be left out and is then derived from `domain`
+    pub types: HashMap<String, Vec<TypedMember>>,
+    // the struct type of the payloads
+    pub primary_type: String,
+    // the record field holding the payload, the abi encoded struct as hex or the message as json
+    #[serde(default = "field_default")]
+    pub field: String,
+    // the struct types by name and the domain separator, built by set_param
+    #[serde(skip)]
+    pub structs: HashMap<String, Members>,
+    #[serde(skip)]
+    pub domain_separator: [u8; 32],
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TypedMember {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: String,
+}
+
+fn field_default() -> String {
+    "data".to_string()
+}
+
+// shared so that reading the parameters for each record doesn't copy the types
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
+
+// the members of EIP712Domain in the order the standard lists them
+const DOMAIN_MEMBERS: [(&str, &str); 5] = [
+    ("name", "string"),
+    ("version", "string"),
+    ("chainId", "uint256"),
+    ("verifyingContract", "address"),
+    ("salt", "bytes32"),
+];
+
+// struct types nest through their members, deeper nesting than this is taken to be a cycle
+const MAX_DEPTH: usize = 32;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    parameter.structs = parameter.types
+        .iter()
+        .map(|(name, members)| (name.clone(), members.iter().map(|m| (m.name.clone(), m.typ.clone())).collect()))
+        .collect();
+    parameter.structs.entry("EIP712Domain".to_string()).or_insert_with(|| {
+        DOMAIN_MEMBERS
+            .iter()
+            .filter(|(name, _)| parameter.domain.contains_key(*name))
+            .map(|(name, typ)| (name.to_string(), typ.to_string()))
+            .collect()
+    });
+
+    // every member must be of an atomic type, a dynamic type, or a declared struct
+    for (name, members) in parameter.structs.iter() {
+        for (member, typ) in members.iter() {
+            let base = typ.split('[').next().unwrap_or_default();
+            if !parameter.structs.contains_key(base) && !is_elementary(base) {
+                return Err(ModuleError::TypesError {
+                    reason: format!("member `{}` of {} is of the undeclared type {}", member, name, typ),
+                }.into());
+            }
+        }
+    }
+    if !parameter.structs.contains_key(&parameter.primary_type) {
+        return Err(ModuleError::TypesError { reason: format!("the primary type {} is not declared", parameter.primary_type) }.into());
+    }
+
+    let domain = Value::Object(parameter.domain.clone());
+    parameter.domain_separator = hash_struct(&parameter.structs, "EIP712Domain", &domain, 0)
+        .map_err(|reason| ModuleError::TypesError { reason: format!("the domain: {}", reason) })?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(Arc::new(parameter));
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    // a hex payload is abi decoded into the message, a json message is only hashed
+    let message = match input.get(&params.field) {
+        Option::Some(Value::String(payload)) => {
+            let payload = payload.trim().to_lowercase();
+            hex::decode(payload.strip_prefix("0x").unwrap_or(&payload))
+                .map_err(|e| format!("the payload is not hex: {}", e))
+                .and_then(|data| {
+                    let param = ParamType::from_abi(&abi_param(&params.structs, &params.primary_type, 0)?);
+                    decode_value(&param, &data, 0).map_err(|e| match e {
+                        ModuleError::DecodeError { reason } => reason,
+                        e => e.to_string(),
+                    })
+                })
+        }
+        Option::Some(message @ Value::Object(_)) => Ok(message.clone()),
+        _ => Err(format!("the record has no `{}` payload", params.field)),
+    };
+
+    input.insert("primaryType".to_string(), Value::String(params.primary_type.clone()));
+    input.insert("domainSeparator".to_string(), Value::String(format!("0x{}", hex::encode(params.domain_separator))));
+    let hashed = message.and_then(|message| {
+        let struct_hash = hash_struct(&params.structs, &params.primary_type, &message, 0)?;
+        Ok((message, struct_hash))
+    });
+    match hashed {
+        Ok((message, struct_hash)) => {
+            // the digest that is signed, keccak256("\x19\x01" ++ domainSeparator ++ hashStruct(message))
+            let mut hasher = Keccak256::new();
+            hasher.update([0x19, 0x01]);
+            hasher.update(params.domain_separator);
+            hasher.update(struct_hash);
+            input.insert("message".to_string(), message);
+            input.insert("structHash".to_string(), Value::String(format!("0x{}", hex::encode(struct_hash))));
+            input.insert("digest".to_string(), Value::String(format!("0x{}", hex::encode(hasher.finalize()))));
+        }
+        Err(reason) => {
+            input.insert("decodeError".to_string(), Value::String(ModuleError::DecodeError { reason }.to_string()));
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// whether a type is one of the atomic or dynamic types rather than a struct
+fn is_elementary(typ: &str) -> bool {
+    matches!(typ, "address" | "bool" | "string" | "bytes")
+        || int_bits(typ, "uint").is_some()
+        || int_bits(typ, "int").is_some()
+        || bytes_width(typ).is_some()
+}
+
+// the abi parameter a struct is encoded as, a tuple of its members with struct members nested as tuples
+fn abi_param(structs: &HashMap<String, Members>, typ: &str, depth: usize) -> Result<Value, String> {
+    let base = typ.split('[').next().unwrap_or_default();
+    let suffix = &typ[base.len()..];
+    let members = match structs.get(base) {
+        Option::Some(members) => members,
+        Option::None => return Ok(serde_json::json!({"type": typ})),
+    };
+    if depth > MAX_DEPTH {
+        return Err(format!("the type {} nests too deeply", base));
+    }
+
+    let components = members
+        .iter()
+        .map(|(name, typ)| {
+            let mut component = abi_param(structs, typ, depth + 1)?;
+            component["name"] = Value::String(name.clone());
+            Ok(component)
+        })
+        .collect::<Result<Vec<Value>, String>>()?;
+    Ok(serde_json::json!({"type": format!("tuple{}", suffix), "components": components}))
+}
+
+// encodeType of EIP-712, the struct followed by the structs it references sorted by name, e.g.
+// "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+fn encode_type(structs: &HashMap<String, Members>, name: &str) -> String {
+    let mut referenced = BTreeSet::new();
+    let mut pending = vec![name.to_string()];
+    while let Option::Some(next) = pending.pop() {
+        for (_, typ) in structs.get(&next).into_iter().flatten() {
+            let base = typ.split('[').next().unwrap_or_default();
+            if base != name && structs.contains_key(base) && referenced.insert(base.to_string()) {
+                pending.push(base.to_string());
+            }
+        }
+    }
+
+    std::iter::once(name.to_string())
+        .chain(referenced)
+        .map(|s| {
+            let members: Vec<String> = structs[&s].iter().map(|(name, typ)| format!("{} {}", typ, name)).collect();
+            format!("{}({})", s, members.join(","))
+        })
+        .collect()
+}
+
+// hashStruct of EIP-712, keccak256(typeHash ++ encodeData(value))
+fn hash_struct(structs: &HashMap<String, Members>, name: &str, value: &Value, depth: usize) -> Result<[u8; 32], String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("the type {} nests too deeply", name));
+    }
+
+    let mut hasher = Keccak256::new();
+    hasher.update(Keccak256::digest(encode_type(structs, name).as_bytes()));
+    for (member, typ) in structs[name].iter() {
+        let field = value.get(member).ok_or(format!("{} has no member `{}`", name, member))?;
+        let encoded = encode_member(structs, typ, field, depth)
+            .map_err(|reason| format!("member `{}` of {}: {}", member, name, reason))?;
+        hasher.update(encoded);
+    }
+    Ok(hasher.finalize().into())
+}
+
+// the 32-byte encoding of a member in encodeData, dynamic values, arrays and structs are hashed
+fn encode_member(structs: &HashMap<String, Members>, typ: &str, value: &Value, depth: usize) -> Result<[u8; 32], String> {
+    if let Option::Some((elem, len)) = parse_array(typ) {
+        let items = value.as_array().ok_or(format!("expected an array for {}", typ))?;
+        if len.is_some_and(|len| len != items.len()) {
+            return Err(format!("expected {} elements for {}, got {}", len.unwrap_or_default(), typ, items.len()));
+        }
+        let mut hasher = Keccak256::new();
+        for item in items.iter() {
+            hasher.update(encode_member(structs, elem, item, depth)?);
+        }
+        return Ok(hasher.finalize().into());
+    }
+    if structs.contains_key(typ) {
+        return hash_struct(structs, typ, value, depth + 1);
+    }
+
+    let text = match value {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => return Err(format!("expected a value for {}", typ)),
+    };
+    let hex_bytes = |s: &str| hex::decode(s.strip_prefix("0x").unwrap_or(s)).map_err(|e| format!("`{}` is not hex: {}", s, e));
+
+    let mut word = [0u8; 32];
+    match typ {
+        "string" => word = Keccak256::digest(text.as_bytes()).into(),
+        "bytes" => word = Keccak256::digest(hex_bytes(&text)?).into(),
+        "bool" => match text.as_str() {
+            "true" => word[31] = 1,
+            "false" => {}
+            _ => return Err(format!("`{}` is not a bool", text)),
+        },
+        "address" => {
+            let bytes = hex_bytes(&text)?;
+            if bytes.len() != 20 {
+                return Err(format!("`{}` is not a 20-byte address", text));
+            }
+            word[12..].copy_from_slice(&bytes);
+        }
+        _ if bytes_width(typ).is_some() => {
+            let bytes = hex_bytes(&text)?;
+            if bytes.len() > bytes_width(typ).unwrap_or(32) {
+                return Err(format!("`{}` is too long for {}", text, typ));
+            }
+            word[..bytes.len()].copy_from_slice(&bytes);
+        }
+        _ => {
+            // integers are decimal, negative for intN, or 0x-prefixed hex
+            let signed = int_bits(typ, "int").is_some();
+            let bits = int_bits(typ, "uint").or(int_bits(typ, "int")).ok_or(format!("unsupported type: {}", typ))?;
+            let (negative, digits) = match text.strip_prefix('-') {
+                Option::Some(digits) if signed => (true, digits),
+                _ => (false, text.as_str()),
+            };
+            let magnitude = match digits.strip_prefix("0x") {
+                Option::Some(h) if h.len() <= 64 => Option::Some(U256::from_be_slice(&hex_bytes(&format!("{:0>width$}", h, width = h.len() + h.len() % 2))?)),
+                Option::Some(_) => Option::None,
+                Option::None => U256::from_dec_str(digits),
+            }
+            .ok_or(format!("`{}` is not an integer", text))?;
+
+            // the magnitude must fit the type, a signed minimum is one more than its maximum
+            let limit = if signed { bits - 1 } else { bits };
+            let fits = magnitude.bits() <= limit || (negative && magnitude.bits() == limit + 1 && magnitude.trailing_zeros() == limit);
+            if !fits {
+                return Err(format!("`{}` is out of range for {}", text, typ));
+            }
+            word = match negative {
+                true => magnitude.wrapping_neg().to_be_bytes(),
+                false => magnitude.to_be_bytes(),
+            };
+        }
+    }
+    Ok(word)
+}
+
+// recursive model of an abi type, built from an abi input or tuple component
+#[derive(Clone, Debug)]
+enum ParamType {
+    Elementary(String),
+    Array(Box<ParamType>, Option<usize>),
+    Tuple(Vec<(String, ParamType)>),
+}
+
+impl ParamType {
+    fn from_abi(param: &Value) -> ParamType {
+        let typ = param["type"].as_str().unwrap_or_default();
+        ParamType::from_type(typ, param)
+    }
+
+    // `param` carries the `components` used when the innermost type is a tuple
+    fn from_type(typ: &str, param: &Value) -> ParamType {
+        if let Option::Some((elem, len)) = parse_array(typ) {
+            return ParamType::Array(Box::new(ParamType::from_type(elem, param)), len);
+        }
+
+        if typ == "tuple" {
+            let empty: Vec<Value> = Vec::new();
+            let components = param["components"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .map(|c| (c["name"].as_str().unwrap_or_default().to_string(), ParamType::from_abi(c)))
+                .collect();
+            return ParamType::Tuple(components);
+        }
+
+        // tuples spelled out inline carry no component names, e.g. `tuple(uint256[],address)`
+        if let Option::Some(inner) = typ.strip_prefix("tuple").unwrap_or(typ).strip_prefix('(') {
+            if let Option::Some(inner) = inner.strip_suffix(')') {
+                let components = split_components(inner)
+                    .into_iter()
+                    .map(|c| (String::new(), ParamType::from_type(c, &Value::Null)))
+                    .collect();
+                return ParamType::Tuple(components);
+            }
+        }
+
+        ParamType::Elementary(typ.to_string())
+    }
+
+    // whether the type is encoded in the tail with its head slot holding an offset
+    fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Elementary(typ) => typ == "string" || typ == "bytes",
+            ParamType::Array(_, Option::None) => true,
+            ParamType::Array(elem, Option::Some(_)) => elem.is_dynamic(),
+            ParamType::Tuple(components) => components.iter().any(|(_, c)| c.is_dynamic()),
+        }
+    }
+
+    // number of bytes the type occupies in the head
+    fn head_size(&self) -> usize {
+        if self.is_dynamic() {
+            return 32;
+        }
+        match self {
+            ParamType::Array(elem, Option::Some(k)) => k * elem.head_size(),
+            ParamType::Tuple(components) => components.iter().map(|(_, c)| c.head_size()).sum(),
+            _ => 32,
+        }
+    }
+}
+
+// decode the value of type `param` whose head slot is at `offset`
+// offsets held in dynamic heads are relative to the start of `data`
+fn decode_value(param: &ParamType, data: &[u8], offset: usize) -> Result<Value, ModuleError> {
+    match param {
+        ParamType::Array(elem, len) => {
+            let (region, base, count) = match len {
+                // dynamic array, the tail holds the element count followed by the encoded elements
+                Option::None => {
+                    let start = read_usize(data, offset)?;
+                    (tail(data, start.saturating_add(32))?, 0, read_usize(data, start)?)
+                }
+                // fixed array of dynamic elements, the tail holds the k element heads
+                Option::Some(k) if elem.is_dynamic() => (tail(data, read_usize(data, offset)?)?, 0, *k),
+                // fixed array of static elements, the k elements are encoded inline
+                Option::Some(k) => (data, offset, *k),
+            };
+            let size = elem.head_size();
+            let values = (0..count)
+                .map(|i| decode_value(elem, region, base + i * size))
+                .collect::<Result<Vec<Value>, ModuleError>>()?;
+            Ok(Value::Array(values))
+        }
+        ParamType::Tuple(components) => {
+            // a dynamic tuple is encoded in the tail and its members' offsets are relative to it,
+            // a static tuple is encoded inline
+            let (region, mut cursor) = match param.is_dynamic() {
+                true => (tail(data, read_usize(data, offset)?)?, 0),
+                false => (data, offset),
+            };
+            let mut values = serde_json::Map::new();
+            for (i, (name, component)) in components.iter().enumerate() {
+                values.insert(component_key(i, name), decode_value(component, region, cursor)?);
+                cursor += component.head_size();
+            }
+            Ok(Value::Object(values))
+        }
+        ParamType::Elementary(typ) => match typ.as_str() {
+            "string" => Ok(Value::String(decode_string(data, offset)?)),
+            "bytes" => Ok(Value::String(decode_bytes(data, offset)?)),
+            _ => decode_word(typ, &hex::encode(read_word(data, offset)?)),
+        },
+    }
+}
+
+// the key of a decoded tuple member, its name from the abi `components` or `field<position>` for unnamed members
+fn component_key(index: usize, name: &str) -> String {
+    match name.is_empty() {
+        true => format!("field{}", index),
+        false => name.to_string(),
+    }
+}
+
+// split the inside of an inline tuple on its top-level commas
+// e.g. `uint256,(address,bool)[]` -> [`uint256`, `(address,bool)[]`]
+fn split_components(inner: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !inner[start..].trim().is_empty() {
+        parts.push(inner[start..].trim());
+    }
+    parts
+}
+
+// split an array type into its element type and length, `None` for dynamic arrays
+// e.g. `uint256[3][]` -> (`uint256[3]`, None)
+fn parse_array(typ: &str) -> Option<(&str, Option<usize>)> {
+    let body = typ.strip_suffix(']')?;
+    let open = body.rfind('[')?;
+    let len = &body[open + 1..];
+    if len.is_empty() {
+        return Option::Some((&body[..open], Option::None));
+    }
+    len.parse().ok().map(|k| (&body[..open], Option::Some(k)))
+}
+
+// read the 32-byte slot at `offset`
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8], ModuleError> {
+    offset
+        .checked_add(32)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(ModuleError::DecodeError {
+            reason: format!("the slot at byte {} is out of bounds of {} bytes of data", offset, data.len()),
+        })
+}
+
+// the data from `start` onwards
+fn tail(data: &[u8], start: usize) -> Result<&[u8], ModuleError> {
+    data.get(start..).ok_or(ModuleError::DecodeError {
+        reason: format!("the offset {} is out of bounds of {} bytes of data", start, data.len()),
+    })
+}
+
+// read a 32-byte slot as an offset or length, which must fit the address space
+fn read_usize(data: &[u8], offset: usize) -> Result<usize, ModuleError> {
+    let word = read_word(data, offset)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&word[24..32]);
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(buf)).map_err(|e| ModuleError::DecodeError { reason: e.to_string() }),
+        false => Err(ModuleError::DecodeError { reason: format!("the slot at byte {} is too large for an offset or length", offset) }),
+    }
+}
+
+// read the payload of a dynamic `string` or `bytes` whose head slot is at `offset`
+// the head holds the tail offset, the tail holds the length followed by the payload
+fn read_dynamic(data: &[u8], offset: usize) -> Result<&[u8], ModuleError> {
+    let start = read_usize(data, offset)?;
+    let len = read_usize(data, start)?;
+    let begin = start.saturating_add(32);
+    begin
+        .checked_add(len)
+        .and_then(|end| data.get(begin..end))
+        .ok_or(ModuleError::DecodeError {
+            reason: format!("the {} byte payload at byte {} is out of bounds of {} bytes of data", len, begin, data.len()),
+        })
+}
+
+fn decode_string(data: &[u8], offset: usize) -> Result<String, ModuleError> {
+    Ok(String::from_utf8_lossy(read_dynamic(data, offset)?).to_string())
+}
+
+fn decode_bytes(data: &[u8], offset: usize) -> Result<String, ModuleError> {
+    Ok(format!("0x{}", hex::encode(read_dynamic(data, offset)?)))
+}
+
+// decode a static elementary type from its 32-byte slot
+fn decode_word(typ: &str, hex_data: &str) -> Result<Value, ModuleError> {
+    let clean = hex_data.trim_start_matches("0x");
+    if clean.len() != 64 || !clean.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ModuleError::DecodeError { reason: format!("`{}` is not a 32-byte hex word", hex_data) });
+    }
+
+    match typ {
+        // an external function is a 20-byte address followed by a 4-byte selector, left-aligned
+        "function" => Ok(serde_json::json!({
+            "address": format!("0x{}", &clean[..40]),
+            "selector": format!("0x{}", &clean[40..48]),
+        })),
+        _ => Ok(Value::String(decode_param(typ, hex_data)?)),
+    }
+}
+
+// decode a static elementary type other than `function` from a 32-byte hex word
+fn decode_param(typ: &str, hex_data: &str) -> Result<String, ModuleError> {
+    let clean = hex_data.trim_start_matches("0x");
+    let invalid = |e: hex::FromHexError| ModuleError::DecodeError { reason: e.to_string() };
+
+    match typ {
+        _ if int_bits(typ, "uint").is_some() => {
+            // uintN is right-aligned in the slot, keep only the low N bits
+            let bits = int_bits(typ, "uint").unwrap_or(256);
+            let low = &clean[clean.len().saturating_sub(bits / 4)..];
+            Ok(U256::from_be_slice(&hex::decode(low).map_err(invalid)?).to_string())
+        }
+        _ if int_bits(typ, "int").is_some() => {
+            // intN is sign-extended to the slot, the low N bits hold the two's complement value
+            let bits = int_bits(typ, "int").unwrap_or(256);
+            let low = &clean[clean.len().saturating_sub(bits / 4)..];
+            Ok(decode_signed(&hex::decode(low).map_err(invalid)?))
+        }
+        _ if parse_fixed(typ).is_some() => {
+            // fixedMxN holds the intM/uintM value v and represents v / 10^N
+            let (signed, bits, decimals) = parse_fixed(typ).unwrap_or((true, 128, 18));
+            let integer = match signed {
+                true => decode_param(&format!("int{}", bits), hex_data)?,
+                false => decode_param(&format!("uint{}", bits), hex_data)?,
+            };
+            Ok(scale_decimal(&integer, decimals))
+        }
+        "address" if clean.len() >= 64 => {
+            let addr = &clean[24..64]; // last 20 bytes (40 hex chars)
+            Ok(format!("0x{}", addr))
+        }
+        "bool" => {
+            let b = clean.ends_with("1");
+            Ok(b.to_string())
+        }
+        _ if bytes_width(typ).is_some() => {
+            // bytesN is left-aligned in the slot, keep only the first N bytes
+            let width = bytes_width(typ).unwrap_or(32);
+            Ok(format!("0x{}", &clean[..(width * 2).min(clean.len())]))
+        }
+        _ => Err(ModuleError::DecodeError { reason: format!("unsupported type: {}", typ) }),
+    }
+}
+
+// width of an `uintN`/`intN` type, 8 to 256 in steps of 8, a bare `uint`/`int` is 256 bits
+fn int_bits(typ: &str, prefix: &str) -> Option<usize> {
+    let width = typ.strip_prefix(prefix)?;
+    if width.is_empty() {
+        return Option::Some(256);
+    }
+    match width.parse::<usize>() {
+        Ok(bits) if (8..=256).contains(&bits) && bits.is_multiple_of(8) => Option::Some(bits),
+        _ => Option::None,
+    }
+}
+
+// signedness, width and decimals of a `fixedMxN`/`ufixedMxN` type
+// M is 8 to 256 in steps of 8 and N is 1 to 80, a bare `fixed`/`ufixed` is `fixed128x18`
+fn parse_fixed(typ: &str) -> Option<(bool, usize, usize)> {
+    let (signed, rest) = match typ.strip_prefix("ufixed") {
+        Option::Some(rest) => (false, rest),
+        Option::None => (true, typ.strip_prefix("fixed")?),
+    };
+    if rest.is_empty() {
+        return Option::Some((signed, 128, 18));
+    }
+
+    let (bits, decimals) = rest.split_once('x')?;
+    let bits: usize = bits.parse().ok()?;
+    let decimals: usize = decimals.parse().ok()?;
+    match (8..=256).contains(&bits) && bits.is_multiple_of(8) && (1..=80).contains(&decimals) {
+        true => Option::Some((signed, bits, decimals)),
+        false => Option::None,
+    }
+}
+
+// shift the decimal point of an integer string `decimals` places to the left,
+// trailing fractional zeros are dropped, e.g. ("-1500", 3) -> "-1.5"
+fn scale_decimal(integer: &str, decimals: usize) -> String {
+    let (sign, digits) = match integer.strip_prefix('-') {
+        Option::Some(digits) => ("-", digits),
+        Option::None => ("", integer),
+    };
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => format!("{}{}", sign, whole),
+        false => format!("{}{}.{}", sign, whole, fraction),
+    }
+}
+
+// width of a fixed `bytesN` type, 1 to 32
+fn bytes_width(typ: &str) -> Option<usize> {
+    match typ.strip_prefix("bytes")?.parse::<usize>() {
+        Ok(width) if (1..=32).contains(&width) => Option::Some(width),
+        _ => Option::None,
+    }
+}
+
+// decode big-endian two's complement bytes into a signed decimal string
+fn decode_signed(bytes: &[u8]) -> String {
+    let negative = bytes.first().map(|b| b & 0x80 != 0).unwrap_or(false);
+    if !negative {
+        return U256::from_be_slice(bytes).to_string();
+    }
+
+    // sign-extend to 256 bits, the magnitude is then the two's complement negation
+    let mut extended = vec![0xffu8; 32usize.saturating_sub(bytes.len())];
+    extended.extend_from_slice(bytes);
+    format!("-{}", U256::from_be_slice(&extended).wrapping_neg())
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            let digit = c.to_digit(10)? as u128;
+            let mut carry = digit;
+            for limb in n.0.iter_mut() {
+                let acc = *limb as u128 * 10 + carry;
+                *limb = acc as u64;
+                carry = acc >> 64;
+            }
+            if carry != 0 {
+                return Option::None;
+            }
+        }
+        Option::Some(n)
+    }
+
+    fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().rev().enumerate() {
+            bytes[i * 8..i * 8 + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    // the number of significant bits
+    fn bits(&self) -> usize {
+        match self.0.iter().rposition(|l| *l != 0) {
+            Option::Some(i) => i * 64 + 64 - self.0[i].leading_zeros() as usize,
+            Option::None => 0,
+        }
+    }
+
+    fn trailing_zeros(&self) -> usize {
+        match self.0.iter().position(|l| *l != 0) {
+            Option::Some(i) => i * 64 + self.0[i].trailing_zeros() as usize,
+            Option::None => 256,
+        }
+    }
+
+    // two's complement negation modulo 2^256
+    fn wrapping_neg(self) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut carry = true;
+        for (i, l) in self.0.iter().enumerate() {
+            let (v, overflow) = (!l).overflowing_add(carry as u64);
+            limbs[i] = v;
+            carry = overflow;
+        }
+        U256(limbs)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
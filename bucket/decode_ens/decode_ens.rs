@@ -0,0 +1,419 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't ens events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // names to recognize by their node, e.g. "vitalik.eth", in addition to those learnt from registrations
+    #[serde(default)]
+    pub names: Vec<String>,
+    // how many of the names learnt from registrations are remembered, the earliest learnt are forgotten
+    // beyond that and the later events of their node carry no name
+    #[serde(default = "capacity_default")]
+    pub capacity: usize,
+}
+
+fn capacity_default() -> usize {
+    10000
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            keep_unmatched: false,
+            names: Vec::new(),
+            capacity: capacity_default(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// the names met so far keyed by their node, events of the registry and resolvers only carry the node,
+// the configured names are kept and those learnt in a stream are forgotten at its end
+struct Names {
+    configured: Option<HashMap<String, String>>,
+    learnt: Option<HashMap<String, String>>,
+    order: VecDeque<String>,
+}
+
+static NAMES: RwLock<Names> = RwLock::new(Names { configured: Option::None, learnt: Option::None, order: VecDeque::new() });
+
+// keccak256 of the events of the .eth registrar controllers,
+// "NameRegistered(string,bytes32,address,uint256,uint256)" and, since the premium was split out of the cost,
+// "NameRegistered(string,bytes32,address,uint256,uint256,uint256)", and "NameRenewed(string,bytes32,uint256,uint256)"
+const CONTROLLER_REGISTERED_TOPIC: &str = "0xca6abbe9d7f11422cb6ca7629fbf6fe9efb1c621f71ce8f02b9f2a230097404f";
+const CONTROLLER_REGISTERED_PREMIUM_TOPIC: &str = "0x69e37f151eb98a09618ddaa80c8cfaf1ce5996867c489f45b555b412271ebf27";
+const CONTROLLER_RENEWED_TOPIC: &str = "0x3da24c024582931cfaf8267d8ed24d13a82a8068d5bd337d30ec45cea4e506ae";
+// of the base registrar, which only knows the labelhash as a token id,
+// "NameRegistered(uint256,address,uint256)" and "NameRenewed(uint256,uint256)"
+const REGISTRAR_REGISTERED_TOPIC: &str = "0xb3d987963d01b2f68493b4bdb130988f157ea43070d4ad840fee0466ed9370d9";
+const REGISTRAR_RENEWED_TOPIC: &str = "0x9b87a00e30f1ac65d898f070f8a3488fe60517182d0a2098e1b4b93a54aa9bd6";
+// of the registry, "NewOwner(bytes32,bytes32,address)" and "Transfer(bytes32,address)"
+const NEW_OWNER_TOPIC: &str = "0xce0457fe73731f824cc272376169235128c118b49d344817417c6d108d155e82";
+const TRANSFER_TOPIC: &str = "0xd4735d920b0f87494915f556dd9b54c8f309026070caea5c737245152564d266";
+// and of the public resolver, "AddrChanged(bytes32,address)"
+const ADDR_CHANGED_TOPIC: &str = "0x52d7d861f09ab3d26239d492e8968629f95e9e318cf0b73bfddc441522a15fd2";
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let names = parameter.names.iter().map(|name| (hex_word(&namehash(name)), name.clone())).collect();
+    *NAMES.write()? = Names { configured: Option::Some(names), learnt: Option::None, order: VecDeque::new() };
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => {
+            NAMES.write()?.forget();
+            return Ok(EndOfStream);
+        }
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let mut names = NAMES.write()?;
+    let output = decode_log(&input, &mut names, params.capacity);
+    // the names are released before a dropped log reads the next one
+    drop(names);
+
+    let output = match output {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fixed schema document of an ens event, every event is keyed by the `node` of the name it
+// concerns so registrations, ownership and resolution can be joined, fields an event doesn't carry are
+// null, logs of other events give None
+fn decode_log(input: &HashMap<String, Value>, names: &mut Names, capacity: usize) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    let data = input.get("data")?.as_str()?.trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+    let words: Vec<&[u8]> = data.chunks(32).collect();
+    let word = |i: usize| words.get(i).copied().filter(|w| w.len() == 32);
+
+    let mut output: HashMap<String, Value> = [
+        "node", "name", "label", "labelhash", "parentNode", "owner", "expires", "cost", "premium", "addr",
+    ]
+    .iter()
+    .map(|key| (key.to_string(), Value::Null))
+    .collect();
+    let mut set = |key: &str, value: Value| output.insert(key.to_string(), value);
+
+    let eth_node = namehash("eth");
+    let (event_name, node) = match (topics.first().map(|t| t.as_str()), topics.len()) {
+        // the controllers emit the plain label of the registered .eth name
+        (Option::Some(CONTROLLER_REGISTERED_TOPIC | CONTROLLER_REGISTERED_PREMIUM_TOPIC), 3) => {
+            let label = read_string(&data, 0)?;
+            set("label", Value::String(label.clone()));
+            set("name", Value::String(format!("{}.eth", label)));
+            set("owner", Value::String(topic_address(&topics[2])?));
+            set("cost", uint(word(1)?));
+            let expires = match topics[0] == CONTROLLER_REGISTERED_PREMIUM_TOPIC {
+                true => {
+                    set("premium", uint(word(2)?));
+                    word(3)?
+                }
+                false => word(2)?,
+            };
+            set("expires", uint(expires));
+            ("NameRegistered", subnode(&eth_node, &labelhash(&label)))
+        }
+        (Option::Some(CONTROLLER_RENEWED_TOPIC), 2) => {
+            let label = read_string(&data, 0)?;
+            set("label", Value::String(label.clone()));
+            set("name", Value::String(format!("{}.eth", label)));
+            set("cost", uint(word(1)?));
+            set("expires", uint(word(2)?));
+            ("NameRenewed", subnode(&eth_node, &labelhash(&label)))
+        }
+        (Option::Some(REGISTRAR_REGISTERED_TOPIC), 3) => {
+            set("owner", Value::String(topic_address(&topics[2])?));
+            set("expires", uint(word(0)?));
+            ("NameRegistered", subnode(&eth_node, &topic_word(&topics[1])?))
+        }
+        (Option::Some(REGISTRAR_RENEWED_TOPIC), 2) => {
+            set("expires", uint(word(0)?));
+            ("NameRenewed", subnode(&eth_node, &topic_word(&topics[1])?))
+        }
+        // a subnode is given an owner, the node of the event is the parent's
+        (Option::Some(NEW_OWNER_TOPIC), 3) => {
+            let parent = topic_word(&topics[1])?;
+            set("parentNode", Value::String(hex_word(&parent)));
+            set("owner", Value::String(address(word(0)?)));
+            ("NewOwner", subnode(&parent, &topic_word(&topics[2])?))
+        }
+        (Option::Some(TRANSFER_TOPIC), 2) => {
+            set("owner", Value::String(address(word(0)?)));
+            ("Transfer", topic_word(&topics[1])?)
+        }
+        (Option::Some(ADDR_CHANGED_TOPIC), 2) => {
+            set("addr", Value::String(address(word(0)?)));
+            ("AddrChanged", topic_word(&topics[1])?)
+        }
+        _ => return Option::None,
+    };
+    let node = hex_word(&node);
+
+    // names are learnt from the controllers' registrations and given to the later events of their node
+    match output.get("name").and_then(|n| n.as_str()) {
+        Option::Some(name) => names.learn(node.clone(), name.to_string(), capacity),
+        Option::None => {
+            if let Option::Some(name) = names.get(&node) {
+                output.insert("name".to_string(), Value::String(name.clone()));
+            }
+        }
+    }
+    if let Option::Some(label) = output.get("label").and_then(|l| l.as_str()) {
+        output.insert("labelhash".to_string(), Value::String(hex_word(&labelhash(label))));
+    } else if matches!(event_name, "NameRegistered" | "NameRenewed") {
+        output.insert("labelhash".to_string(), Value::String(topics[1].clone()));
+    } else if event_name == "NewOwner" {
+        output.insert("labelhash".to_string(), Value::String(topics[2].clone()));
+    }
+
+    output.insert("eventName".to_string(), Value::String(event_name.to_string()));
+    output.insert("node".to_string(), Value::String(node));
+    let contract = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("contract".to_string(), serde_json::json!(contract));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+impl Names {
+    // the name of a node, those learnt from registrations over the configured ones
+    fn get(&self, node: &str) -> Option<&String> {
+        let learnt = self.learnt.as_ref().and_then(|learnt| learnt.get(node));
+        learnt.or(self.configured.as_ref().and_then(|configured| configured.get(node)))
+    }
+
+    // remember the name of a node, forgetting the earliest learnt names beyond `capacity`
+    fn learn(&mut self, node: String, name: String, capacity: usize) {
+        let learnt = self.learnt.get_or_insert_with(HashMap::new);
+        if learnt.insert(node.clone(), name).is_none() {
+            self.order.push_back(node);
+        }
+        while learnt.len() > capacity {
+            match self.order.pop_front() {
+                Option::Some(earliest) => {
+                    learnt.remove(&earliest);
+                }
+                Option::None => break,
+            }
+        }
+    }
+
+    fn forget(&mut self) {
+        self.learnt = Option::None;
+        self.order.clear();
+    }
+}
+
+// the keccak256 hash of a single label, e.g. "vitalik" of "vitalik.eth"
+fn labelhash(label: &str) -> [u8; 32] {
+    Keccak256::digest(label.as_bytes()).into()
+}
+
+// the node of a label under its parent node, keccak256(parent ++ labelhash)
+fn subnode(parent: &[u8; 32], label: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(parent);
+    hasher.update(label);
+    hasher.finalize().into()
+}
+
+// the EIP-137 node of a dotted name, built from the root by hashing in the labels right to left
+fn namehash(name: &str) -> [u8; 32] {
+    name.trim()
+        .to_lowercase()
+        .rsplit('.')
+        .filter(|label| !label.is_empty())
+        .fold([0u8; 32], |node, label| subnode(&node, &labelhash(label)))
+}
+
+fn hex_word(word: &[u8; 32]) -> String {
+    format!("0x{}", hex::encode(word))
+}
+
+// the 32 bytes of an indexed topic
+fn topic_word(topic: &str) -> Option<[u8; 32]> {
+    hex::decode(topic.strip_prefix("0x").unwrap_or(topic)).ok()?.try_into().ok()
+}
+
+// the address in the low 20 bytes of a word
+fn address(word: &[u8]) -> String {
+    format!("0x{}", hex::encode(&word[12..]))
+}
+
+// an unsigned integer word as a decimal string
+fn uint(word: &[u8]) -> Value {
+    Value::String(U256::from_be_slice(word).to_string())
+}
+
+// the string whose head slot is at `offset`
+fn read_string(data: &[u8], offset: usize) -> Option<String> {
+    let start = read_usize(data, offset)?;
+    let len = read_usize(data, start)?;
+    let bytes = data.get(start.checked_add(32)?..)?.get(..len)?;
+    Option::Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+// a word of the data read as an offset or length, None when it is out of bounds or too large
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = data.get(offset..offset.checked_add(32)?)?;
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(word[24..].try_into().ok()?)).ok(),
+        false => Option::None,
+    }
+}
+
+// the address in the low 20 bytes of an indexed topic
+fn topic_address(topic: &str) -> Option<String> {
+    let word = topic.strip_prefix("0x").unwrap_or(topic);
+    match word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Option::Some(format!("0x{}", &word[24..])),
+        false => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,315 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't erc1155 events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // emit a batch transfer as one document per transferred id and amount
+    #[serde(default)]
+    pub explode_batches: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// documents of an exploded batch that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+// keccak256 of "TransferSingle(address,address,address,uint256,uint256)",
+// "TransferBatch(address,address,address,uint256[],uint256[])", "ApprovalForAll(address,address,bool)"
+// and "URI(string,uint256)"
+const TRANSFER_SINGLE_TOPIC: &str = "0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+const TRANSFER_BATCH_TOPIC: &str = "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+const APPROVAL_FOR_ALL_TOPIC: &str = "0x17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31";
+const URI_TOPIC: &str = "0x6bb7ff708619ba0610cba295a58592e0451dee2622938c8755667688daf3529b";
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    // a batch is exploded into documents shaped like single transfers, which keep the batch's
+    // event name and carry their position in it
+    let exploded = match (output.get("ids"), output.get("values")) {
+        (Option::Some(Value::Array(ids)), Option::Some(Value::Array(values))) if params.explode_batches => {
+            let mut documents = VecDeque::new();
+            for (i, (id, value)) in ids.iter().zip(values.iter()).enumerate() {
+                let mut document = output.clone();
+                document.remove("ids");
+                document.remove("values");
+                document.insert("id".to_string(), id.clone());
+                document.insert("value".to_string(), value.clone());
+                document.insert("batchIndex".to_string(), Value::from(i));
+                documents.push_back(serde_json::to_vec(&document)?);
+            }
+            Option::Some(documents)
+        }
+        _ => Option::None,
+    };
+    let result_json = match exploded {
+        Option::Some(mut documents) => {
+            // an empty batch transfers nothing and has nothing to emit
+            let first = documents.pop_front();
+            PENDING.write()?.extend(documents);
+            match first {
+                Option::Some(result_json) => result_json,
+                Option::None => {
+                    lens_sdk::free_transport_buffer(ptr)?;
+                    return try_transform();
+                }
+            }
+        }
+        Option::None => serde_json::to_vec(&output)?,
+    };
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fixed schema document of an erc1155 log, amounts and ids are decimal strings, logs of other
+// events and those whose data doesn't match the event give None
+fn decode_log(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    let data = input.get("data").and_then(|d| d.as_str()).unwrap_or_default().trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+
+    let mut output = HashMap::new();
+    match (topics.first().map(|t| t.as_str()), topics.len()) {
+        (Option::Some(TRANSFER_SINGLE_TOPIC), 4) if data.len() == 64 => {
+            output.insert("eventName".to_string(), Value::String("TransferSingle".to_string()));
+            output.insert("operator".to_string(), Value::String(topic_address(&topics[1])?));
+            output.insert("from".to_string(), Value::String(topic_address(&topics[2])?));
+            output.insert("to".to_string(), Value::String(topic_address(&topics[3])?));
+            output.insert("id".to_string(), Value::String(U256::from_be_slice(&data[..32]).to_string()));
+            output.insert("value".to_string(), Value::String(U256::from_be_slice(&data[32..]).to_string()));
+        }
+        (Option::Some(TRANSFER_BATCH_TOPIC), 4) => {
+            // the ids and amounts are index aligned, lists of different lengths can't be paired
+            let ids = read_uint_array(&data, 0)?;
+            let values = read_uint_array(&data, 32)?;
+            if ids.len() != values.len() {
+                return Option::None;
+            }
+            output.insert("eventName".to_string(), Value::String("TransferBatch".to_string()));
+            output.insert("operator".to_string(), Value::String(topic_address(&topics[1])?));
+            output.insert("from".to_string(), Value::String(topic_address(&topics[2])?));
+            output.insert("to".to_string(), Value::String(topic_address(&topics[3])?));
+            output.insert("ids".to_string(), serde_json::json!(ids));
+            output.insert("values".to_string(), serde_json::json!(values));
+        }
+        // erc721 declares the same event, the two can't be told apart from the log alone
+        (Option::Some(APPROVAL_FOR_ALL_TOPIC), 3) if data.len() == 32 => {
+            output.insert("eventName".to_string(), Value::String("ApprovalForAll".to_string()));
+            output.insert("owner".to_string(), Value::String(topic_address(&topics[1])?));
+            output.insert("operator".to_string(), Value::String(topic_address(&topics[2])?));
+            output.insert("approved".to_string(), Value::Bool(data[31] != 0));
+        }
+        (Option::Some(URI_TOPIC), 2) => {
+            output.insert("eventName".to_string(), Value::String("URI".to_string()));
+            output.insert("value".to_string(), Value::String(read_string(&data, 0)?));
+            output.insert("id".to_string(), Value::String(topic_uint(&topics[1])?));
+        }
+        _ => return Option::None,
+    }
+
+    let contract = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("contract".to_string(), serde_json::json!(contract));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the dynamic region whose offset is held in the head slot at `offset`, starting with its length word
+fn read_dynamic(data: &[u8], offset: usize) -> Option<(usize, &[u8])> {
+    let start = read_usize(data, offset)?;
+    let len = read_usize(data, start)?;
+    Option::Some((len, data.get(start.checked_add(32)?..)?))
+}
+
+// a word of the data read as an offset or length, None when it is out of bounds or too large
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = data.get(offset..offset.checked_add(32)?)?;
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(word[24..].try_into().ok()?)).ok(),
+        false => Option::None,
+    }
+}
+
+// the uint256[] whose head slot is at `offset`, as decimal strings
+fn read_uint_array(data: &[u8], offset: usize) -> Option<Vec<String>> {
+    let (len, elements) = read_dynamic(data, offset)?;
+    let elements = elements.get(..len.checked_mul(32)?)?;
+    Option::Some(elements.chunks(32).map(|word| U256::from_be_slice(word).to_string()).collect())
+}
+
+// the string whose head slot is at `offset`
+fn read_string(data: &[u8], offset: usize) -> Option<String> {
+    let (len, bytes) = read_dynamic(data, offset)?;
+    Option::Some(String::from_utf8_lossy(bytes.get(..len)?).into_owned())
+}
+
+// the unsigned integer held by an indexed topic, as a decimal string
+fn topic_uint(topic: &str) -> Option<String> {
+    let word = hex::decode(topic.strip_prefix("0x").unwrap_or(topic)).ok()?;
+    match word.len() == 32 {
+        true => Option::Some(U256::from_be_slice(&word).to_string()),
+        false => Option::None,
+    }
+}
+
+// the address in the low 20 bytes of an indexed topic
+fn topic_address(topic: &str) -> Option<String> {
+    let word = topic.strip_prefix("0x").unwrap_or(topic);
+    match word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Option::Some(format!("0x{}", &word[24..])),
+        false => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,353 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't vault deposits or withdrawals through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // the fractional digits the share price is given with
+    #[serde(default = "precision_default")]
+    pub precision: usize,
+    // the decimals of the vault's underlying asset and of its shares, 18 for most vaults
+    #[serde(default = "decimals_default")]
+    pub asset_decimals: u32,
+    #[serde(default = "decimals_default")]
+    pub share_decimals: u32,
+    // the decimals of particular vaults keyed by their address, overriding `assetDecimals` and `shareDecimals`
+    #[serde(default)]
+    pub vaults: HashMap<String, VaultDecimals>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultDecimals {
+    pub asset_decimals: u32,
+    pub share_decimals: u32,
+}
+
+fn precision_default() -> usize {
+    18
+}
+
+fn decimals_default() -> u32 {
+    18
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            keep_unmatched: false,
+            precision: precision_default(),
+            asset_decimals: decimals_default(),
+            share_decimals: decimals_default(),
+            vaults: HashMap::new(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// keccak256("Deposit(address,address,uint256,uint256)") and
+// keccak256("Withdraw(address,address,address,uint256,uint256)") of EIP-4626
+const DEPOSIT_TOPIC: &str = "0xdcbc1c05240f31ff3ad067ef1ee35ce4997762752e3a095284754544f4c709d7";
+const WITHDRAW_TOPIC: &str = "0xfbde797d201c681b91056529119e0b02407c7bb96a4a2c75c01fc9667232c8db";
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // the addresses are matched against the lowercased address of the log
+    parameter.vaults = parameter.vaults
+        .into_iter()
+        .map(|(address, decimals)| (address.trim().to_lowercase(), decimals))
+        .collect();
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input, &params) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the document of a vault deposit or withdrawal, the sender deposits assets for shares minted to the
+// owner, or withdraws the assets of shares burnt from the owner to the receiver, logs of other events,
+// e.g. weth's Deposit(address,uint256), give None
+fn decode_log(input: &HashMap<String, Value>, params: &Parameters) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    let (event_name, sender, receiver, owner) = match (topics.first()?.as_str(), topics.len()) {
+        (DEPOSIT_TOPIC, 3) => ("Deposit", topic_address(&topics[1])?, Option::None, topic_address(&topics[2])?),
+        (WITHDRAW_TOPIC, 4) => ("Withdraw", topic_address(&topics[1])?, Option::Some(topic_address(&topics[2])?), topic_address(&topics[3])?),
+        _ => return Option::None,
+    };
+
+    let data = input.get("data")?.as_str()?.trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+    if data.len() != 64 {
+        return Option::None;
+    }
+    let assets = U256::from_be_slice(&data[..32]);
+    let shares = U256::from_be_slice(&data[32..]);
+
+    let mut output = HashMap::new();
+    output.insert("eventName".to_string(), Value::String(event_name.to_string()));
+    let vault = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("vault".to_string(), serde_json::json!(vault));
+    output.insert("sender".to_string(), Value::String(sender));
+    output.insert("receiver".to_string(), serde_json::json!(receiver));
+    output.insert("owner".to_string(), Value::String(owner));
+    output.insert("assets".to_string(), Value::String(assets.to_string()));
+    output.insert("shares".to_string(), Value::String(shares.to_string()));
+    let decimals = vault.as_ref().and_then(|v| params.vaults.get(v)).copied().unwrap_or(VaultDecimals {
+        asset_decimals: params.asset_decimals,
+        share_decimals: params.share_decimals,
+    });
+    output.insert("sharePrice".to_string(), serde_json::json!(share_price(assets, shares, decimals, params.precision)));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the assets one whole share was exchanged for as a decimal string truncated to `precision` digits,
+// e.g. "1.052", the raw amounts are brought to whole units by the decimals of the asset and of the
+// shares, None when no shares changed hands or the amounts are too large for the digits to be
+// computed in 256 bits
+fn share_price(assets: U256, shares: U256, decimals: VaultDecimals, precision: usize) -> Option<String> {
+    if shares.is_zero() {
+        return Option::None;
+    }
+    // assets / 10^asset_decimals over shares / 10^share_decimals, only the difference of the decimals scales
+    let (mut assets, mut shares) = (assets, shares);
+    for _ in decimals.asset_decimals..decimals.share_decimals {
+        assets = assets.checked_mul_u64(10)?;
+    }
+    for _ in decimals.share_decimals..decimals.asset_decimals {
+        shares = shares.checked_mul_u64(10)?;
+    }
+    let (whole, mut remainder) = assets.div_rem_u256(shares);
+    // the fractional digits are long division by the shares one decimal digit at a time
+    let mut fraction = String::new();
+    for _ in 0..precision {
+        if remainder.is_zero() {
+            break;
+        }
+        let (digit, rest) = remainder.checked_mul_u64(10)?.div_rem_u256(shares);
+        fraction.push_str(&digit.to_string());
+        remainder = rest;
+    }
+    match fraction.trim_end_matches('0') {
+        "" => Option::Some(whole.to_string()),
+        fraction => Option::Some(format!("{}.{}", whole, fraction)),
+    }
+}
+
+// the address in the low 20 bytes of an indexed topic
+fn topic_address(topic: &str) -> Option<String> {
+    let word = topic.strip_prefix("0x").unwrap_or(topic);
+    match word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Option::Some(format!("0x{}", &word[24..])),
+        false => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    fn checked_mul_u64(self, factor: u64) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let acc = self.0[i] as u128 * factor as u128 + carry;
+            *limb = acc as u64;
+            carry = acc >> 64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    // binary long division by a nonzero divisor, the remainder is shifted in one bit at a time and
+    // the divisor taken from it whenever it fits, a bit shifted out of the remainder means it does
+    fn div_rem_u256(self, divisor: U256) -> (U256, U256) {
+        let mut quotient = U256::default();
+        let mut remainder = U256::default();
+        for i in (0..256).rev() {
+            let overflow = remainder.0[3] >> 63 == 1;
+            let mut limbs = [0u64; 4];
+            for (j, limb) in limbs.iter_mut().enumerate() {
+                *limb = remainder.0[j] << 1 | if j > 0 { remainder.0[j - 1] >> 63 } else { self.bit(i) as u64 };
+            }
+            remainder = U256(limbs);
+            if overflow || remainder.ge(&divisor) {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient.0[i / 64] |= 1 << (i % 64);
+            }
+        }
+        (quotient, remainder)
+    }
+
+    fn ge(&self, other: &U256) -> bool {
+        for i in (0..4).rev() {
+            if self.0[i] != other.0[i] {
+                return self.0[i] > other.0[i];
+            }
+        }
+        true
+    }
+
+    fn wrapping_sub(self, other: U256) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_sub(other.0[i]);
+            let (v, o2) = v.overflowing_sub(borrow as u64);
+            *limb = v;
+            borrow = o1 || o2;
+        }
+        U256(limbs)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
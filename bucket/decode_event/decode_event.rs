@@ -2,7 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 use std::collections::HashMap;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::error::Error;
 use std::{fmt, error};
 use serde::Deserialize;
@@ -16,9 +16,14 @@ extern "C" {
     fn next() -> *mut u8;
 }
 
+// the variants keep the `Error` suffix that ParametersNotSetError gives the errors of every lens
+#[allow(clippy::enum_variant_names)]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
 enum ModuleError {
     ParametersNotSetError,
+    AbiFormatError{reason: String},
+    DecodeError{reason: String},
+    UnknownEventError{topic0: String},
 }
 
 impl error::Error for ModuleError { }
@@ -27,16 +32,203 @@ impl fmt::Display for ModuleError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &*self {
             ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::AbiFormatError { reason } =>
+                write!(f, "The abi parameter could not be read. Reason: {}", reason),
+            ModuleError::DecodeError { reason } =>
+                write!(f, "The log could not be decoded. Reason: {}", reason),
+            ModuleError::UnknownEventError { topic0 } =>
+                write!(f, "The log's event is in none of the abis. Topic0: {}", topic0),
         }
     }
 }
 
 #[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Parameters {
-    pub abi: String,
+    // a json abi document, or that document encoded in a string
+    pub abi: Value,
+    // anonymous events are only matched against the logs emitted by this contract, and against none
+    // when it is unset as any log of as many topics as their indexed inputs would match
+    #[serde(default)]
+    pub anonymous_address: Option<String>,
+    #[serde(default)]
+    pub address_format: AddressFormat,
+    #[serde(default)]
+    pub number_format: NumberFormat,
+    #[serde(default)]
+    pub topic_validation: TopicValidation,
+    // labels of solidity enums keyed by `EventName.paramName`, indexed by the enum value
+    #[serde(default)]
+    pub enums: HashMap<String, Vec<String>>,
+    // token decimals keyed by argument name, emitting contract address, or `address.argumentName`
+    #[serde(default)]
+    pub scaling: HashMap<String, usize>,
+    // abis of specific contracts, keyed by contract address
+    #[serde(default)]
+    pub abis_by_address: HashMap<String, Value>,
+    // text signatures keyed by topic0, used for logs whose event is in none of the abis
+    #[serde(default)]
+    pub signature_db: HashMap<String, String>,
+    #[serde(default)]
+    pub mode: Mode,
+    #[serde(default)]
+    pub removed_logs: RemovedLogs,
+    #[serde(default)]
+    pub output_shape: OutputShape,
+    #[serde(default)]
+    pub unknown_event_policy: UnknownEventPolicy,
+    // event names or topic0 hashes, when set only logs of these events are emitted
+    #[serde(default)]
+    pub include_events: Vec<String>,
+    // event names or topic0 hashes whose logs are never emitted
+    #[serde(default)]
+    pub exclude_events: Vec<String>,
+    // when false the raw log fields are dropped from successfully decoded records
+    #[serde(default = "include_raw_default")]
+    pub include_raw: bool,
+    // lookup tables of `abi` and `abis_by_address`, built by set_param
+    #[serde(skip)]
+    pub events: EventTable,
+    #[serde(skip)]
+    pub events_by_address: HashMap<String, EventTable>,
 }
 
-static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+// where the decoded fields are placed in the output record
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputShape {
+    // merged into the input record
+    #[default]
+    Flat,
+    // under `decodedEvent` as `{ name, signature, args: { name: value } }`
+    Nested,
+}
+
+// what happens to logs whose event is in none of the abis nor the signature database
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownEventPolicy {
+    // the log is emitted unchanged
+    #[default]
+    Passthrough,
+    Drop,
+    // the transform returns an error for the log
+    Error,
+}
+
+fn include_raw_default() -> bool {
+    true
+}
+
+// the events of an abi indexed by topic0, so matching a log is a single lookup
+#[derive(Clone, Debug, Default)]
+pub struct EventTable {
+    // non-anonymous definitions keyed by their signature hash, overloads share an entry
+    by_topic0: HashMap<String, Vec<Event>>,
+    // anonymous definitions, matched by their number of indexed inputs instead
+    anonymous: Vec<Event>,
+}
+
+impl EventTable {
+    fn from_abi(abi: &Value) -> EventTable {
+        let mut table = EventTable::default();
+        let empty: Vec<Value> = Vec::new();
+        for item in abi.as_array().unwrap_or(&empty).iter().filter(|item| item["type"] == "event") {
+            let event = Event::from_abi(item);
+            match item["anonymous"] == true {
+                true => table.anonymous.push(event),
+                false => table.by_topic0.entry(event.topic0.clone()).or_default().push(event),
+            }
+        }
+        table
+    }
+}
+
+// an abi event with what decoding its logs needs worked out once, when the abi is set, rather than
+// for every log
+#[derive(Clone, Debug)]
+pub struct Event {
+    item: Value,
+    signature: String,
+    topic0: String,
+    // the type of each input, in the order they are declared
+    params: Vec<ParamType>,
+    indexed_count: usize,
+}
+
+impl Event {
+    fn from_abi(item: &Value) -> Event {
+        let empty: Vec<Value> = Vec::new();
+        let signature = event_signature(item);
+        Event {
+            item: item.clone(),
+            topic0: signature_hash(&signature),
+            signature,
+            params: item["inputs"].as_array().unwrap_or(&empty).iter().map(ParamType::from_abi).collect(),
+            indexed_count: indexed_count(item),
+        }
+    }
+}
+
+// what happens to logs flagged `removed: true`, which are delivered again after a reorg drops them
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RemovedLogs {
+    // removed logs are decoded and passed through with their `removed` flag
+    #[default]
+    Keep,
+    // removed logs are skipped
+    Drop,
+    // removed logs are replaced by a document identifying the log with `removed: true`
+    Tombstone,
+}
+
+// how decode failures (malformed logs, unsupported types, abi mismatches) are handled
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    // the transform returns an error for the record
+    Strict,
+    // the record is emitted with a `decodeError` field and whatever could be decoded
+    #[default]
+    Lenient,
+}
+
+// how logs with fewer topics than the event has indexed inputs are handled
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TopicValidation {
+    // the record gets a `decodeError` instead of its arguments, or fails in strict mode like any other
+    // decode failure
+    #[default]
+    Strict,
+    // the missing indexed arguments are emitted as null
+    Lenient,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressFormat {
+    #[default]
+    Lowercase,
+    // EIP-55 mixed-case checksum
+    Checksum,
+}
+
+// how integer values are rendered, fixed point values are always decimal
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberFormat {
+    #[default]
+    Decimal,
+    // 0x-prefixed without leading zeros, negative values are prefixed with `-`
+    Hex,
+    // `{ "value": decimal, "raw": hex }`
+    Both,
+}
+
+// shared so that reading the parameters for each record doesn't copy the abis and their tables
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
 
 #[no_mangle]
 pub extern fn alloc(size: usize) -> *mut u8 {
@@ -52,11 +244,27 @@ pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
 }
 
 fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
-    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
         .ok_or(ModuleError::ParametersNotSetError)?;
 
+    parameter.abi = normalize_abi(&parameter.abi)?;
+    parameter.abis_by_address = parameter.abis_by_address
+        .iter()
+        .map(|(address, abi)| Ok((normalize_hex(address), normalize_abi(abi)?)))
+        .collect::<Result<HashMap<String, Value>, ModuleError>>()?;
+    parameter.signature_db = parameter.signature_db
+        .iter()
+        .map(|(topic0, sig)| (normalize_hex(topic0), sig.clone()))
+        .collect();
+    parameter.anonymous_address = parameter.anonymous_address.as_deref().map(normalize_hex);
+    parameter.events = EventTable::from_abi(&parameter.abi);
+    parameter.events_by_address = parameter.abis_by_address
+        .iter()
+        .map(|(address, abi)| (address.clone(), EventTable::from_abi(abi)))
+        .collect();
+
     let mut dst = PARAMETERS.write()?;
-    *dst = Some(parameter);
+    *dst = Some(Arc::new(parameter));
     Ok(())
 }
 
@@ -74,7 +282,7 @@ pub extern fn transform() -> *mut u8 {
 
 fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
     let ptr = unsafe { next() };
-    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
         Some(v) => v,
         // Implementations of `transform` are free to handle nil however they like. In this
         // implementation we chose to return nil given a nil input.
@@ -82,194 +290,1346 @@ fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
         EndOfStream => return Ok(EndOfStream)
     };
 
-    let tx_hash = input["transactionHash"]
-        .as_str()
-        .unwrap_or_default()
-        .to_string();
+    // the input buffer is freed whether the log is emitted, dropped or fails, and a dropped log is
+    // skipped for the next input as the filter lenses do rather than answered with nil
+    let result = transform_log(input);
+    lens_sdk::free_transport_buffer(ptr)?;
+    match result? {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
+
+// decode a log into its output record, None when the log is dropped
+fn transform_log(mut input: HashMap<String, Value>) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    // get the params, from here we can get the abi and parse it later
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    // a log without its position fails in strict mode, lenient mode decodes it with a null `hash`
+    // and `block` and notes what was missing
+    let (tx_hash, block_number) = match log_position(&input) {
+        Ok((tx_hash, block_number)) => (Value::String(tx_hash), Value::String(block_number)),
+        Err(e) if params.mode == Mode::Lenient => {
+            input.insert("decodeError".to_string(), Value::String(e.to_string()));
+            (Value::Null, Value::Null)
+        }
+        Err(e) => return Err(e.into()),
+    };
 
-    let block_number = input["blockNumber"]
-        .as_i64()
+    if input.get("removed").and_then(|v| v.as_bool()).unwrap_or(false) {
+        match params.removed_logs {
+            RemovedLogs::Keep => {}
+            RemovedLogs::Drop => {
+                return Ok(Option::None);
+            }
+            RemovedLogs::Tombstone => {
+                let mut tombstone: HashMap<String, serde_json::Value> = ["transactionHash", "blockHash", "blockNumber", "logIndex", "address", "removed"]
+                    .iter()
+                    .filter_map(|key| input.get(*key).map(|v| (key.to_string(), v.clone())))
+                    .collect();
+                tombstone.insert("hash".to_string(), tx_hash);
+                tombstone.insert("block".to_string(), block_number);
+                let result_json = serde_json::to_vec(&tombstone)?;
+                return Ok(Option::Some(result_json));
+            }
+        }
+    }
+
+    let emitter = input
+        .get("address")
+        .and_then(|v| v.as_str())
+        .filter(|address| !address.trim().is_empty())
+        .map(normalize_hex)
+        .unwrap_or_default();
+
+    // the event table of the abi registered for the emitting contract takes precedence over the merged table
+    let default_table = EventTable::default();
+    let contract_table = params.events_by_address.get(&emitter).unwrap_or(&default_table);
+
+    // get all topics (the first is the sign, the rest are unindexed args)
+    let topics = match read_topics(&input) {
+        Ok(topics) => topics,
+        Err(e) if params.mode == Mode::Lenient => {
+            // a malformed log is only emitted when its event is selected, as far as a readable first
+            // topic tells which event it is
+            let topic0: Vec<String> = input
+                .get("topics")
+                .and_then(|v| v.as_array())
+                .and_then(|topics| topics.first())
+                .and_then(|v| v.as_str())
+                .map(normalize_hex)
+                .into_iter()
+                .collect();
+            let event = find_event(&[contract_table, &params.events], &topic0, &emitter, &params)
+                .filter(|(_, anonymous)| !anonymous)
+                .map(|(event, _)| event);
+            if !is_selected(event, &topic0, &params) {
+                return Ok(Option::None);
+            }
+
+            input.insert("decodeError".to_string(), serde_json::Value::String(e.to_string()));
+            let result_json = serde_json::to_vec(&input)?;
+            return Ok(Option::Some(result_json));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // get the data (with the data you get the remaining args)
+    let data: String = input
+        .get("data")
+        .and_then(|v| v.as_str())
         .unwrap_or_default()
         .to_string();
 
-    // get all topics (the first is the sign, the rest are unindexed args)
-    let topics: Vec<String> = input["topics"]
-        .as_array()
-        .unwrap()
+    let definition = find_event(&[contract_table, &params.events], &topics, &emitter, &params);
+
+    // the signature database is only consulted for events that are in none of the abis
+    let fallback = match definition {
+        Option::Some(_) => Option::None,
+        Option::None => topics.first()
+            .and_then(|topic0| params.signature_db.get(topic0))
+            .and_then(|sig| event_from_signature(sig, topics.len().saturating_sub(1)))
+            .map(|item| Event::from_abi(&item)),
+    };
+
+    // drop logs of events that aren't selected before decoding them
+    let event = definition.map(|(event, _)| event).or(fallback.as_ref());
+    if !is_selected(event, &topics, &params) {
+        return Ok(Option::None);
+    }
+
+    if event.is_none() {
+        match params.unknown_event_policy {
+            UnknownEventPolicy::Passthrough => {
+                let result_json = serde_json::to_vec(&input)?;
+                return Ok(Option::Some(result_json));
+            }
+            UnknownEventPolicy::Drop => {
+                return Ok(Option::None);
+            }
+            UnknownEventPolicy::Error => {
+                return Err(ModuleError::UnknownEventError { topic0: topics.first().cloned().unwrap_or_default() }.into());
+            }
+        }
+    }
+
+    // insert the tx hash and block number
+    input.insert("hash".to_string(), tx_hash);
+    input.insert("block".to_string(), block_number);
+
+    match definition {
+        // every topic of an anonymous event is an argument
+        Option::Some((event, true)) => {
+            input.insert("signature".to_string(), serde_json::Value::String(event.signature.clone()));
+            insert_log_fields(&mut input, &event.item, &emitter, &params);
+
+            let decoded = decode_arguments(event, &topics, &data, &emitter, &params);
+            insert_arguments(&mut input, decoded, &params)?;
+        }
+        Option::Some((event, false)) => {
+            // set the data to be the function
+            let sig = event.signature.clone();
+            input.insert("signature".to_string(), serde_json::Value::String(sig.clone()));
+            insert_log_fields(&mut input, &event.item, &emitter, &params);
+
+            let expected = event.indexed_count + 1;
+            if topics.len() < expected && params.topic_validation == TopicValidation::Strict {
+                let e = ModuleError::DecodeError {
+                    reason: format!("the log has {} topics where {} has {}", topics.len(), sig, expected),
+                };
+                if params.mode == Mode::Strict {
+                    return Err(e.into());
+                }
+                input.insert("decodeError".to_string(), serde_json::Value::String(e.to_string()));
+            } else {
+                // start from the second topic because the first is the signature
+                let decoded = decode_arguments(event, &topics[1..], &data, &emitter, &params);
+                insert_arguments(&mut input, decoded, &params)?;
+            }
+        }
+        Option::None => {
+            // fall back to the signature database for a best-effort decode
+            if let Option::Some(event) = fallback {
+                input.insert("signature".to_string(), serde_json::Value::String(event.signature.clone()));
+                input.insert("signatureSource".to_string(), serde_json::Value::String("signatureDb".to_string()));
+                insert_log_fields(&mut input, &event.item, &emitter, &params);
+
+                let decoded = decode_arguments(&event, &topics[1..], &data, &emitter, &params);
+                insert_arguments(&mut input, decoded, &params)?;
+            }
+        }
+    }
+
+    // input.insert("data".to_string(), serde_json::Value::String(topic0.to_string()));
+
+    // the decoded arguments carry everything the raw topics and data held, the position fields stay
+    let decoded = input.contains_key("arguments") && !input.contains_key("decodeError");
+    if params.output_shape == OutputShape::Nested {
+        nest_decoded_event(&mut input);
+    }
+    if !params.include_raw && decoded {
+        for key in ["topics", "data"] {
+            input.remove(key);
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input.clone())?;
+    Ok(Option::Some(result_json))
+}
+
+// whether a log passes includeEvents and excludeEvents, an event is listed by its name or its
+// topic0 hash, logs of unknown events are listed by their first topic
+fn is_selected(event: Option<&Event>, topics: &[String], params: &Parameters) -> bool {
+    let name = event.and_then(|event| event.item["name"].as_str());
+    let topic0 = match event {
+        Option::Some(event) => Option::Some(event.topic0.clone()),
+        Option::None => topics.first().cloned(),
+    };
+    let listed = |events: &Vec<String>| events
         .iter()
-        .map(|v| v.as_str().unwrap().to_string())
-        .collect();
+        .any(|e| Option::Some(e.as_str()) == name || Option::Some(normalize_hex(e)) == topic0);
 
-    // collect the first topic which is the signature like "Transfer(address,address,uint256)"
-    let topic0 = &topics[0];
+    (params.include_events.is_empty() || listed(&params.include_events)) && !listed(&params.exclude_events)
+}
 
-    // get the params, from here we can get the abi and parse it later
-    let params = PARAMETERS.read()?
-        .clone()
-        .ok_or(ModuleError::ParametersNotSetError)?
-        .clone();
+// the transaction hash and block number of a log, the number is a json number or a hex or decimal
+// quantity as rpc logs give it
+fn log_position(input: &HashMap<String, Value>) -> Result<(String, String), ModuleError> {
+    let tx_hash = input
+        .get("transactionHash")
+        .and_then(|v| v.as_str())
+        .ok_or(ModuleError::DecodeError { reason: "the log has no transactionHash".to_string() })?;
+    let block_number = input
+        .get("blockNumber")
+        .and_then(quantity)
+        .ok_or(ModuleError::DecodeError { reason: "the log has no blockNumber".to_string() })?;
+    Ok((tx_hash.to_string(), block_number.to_string()))
+}
+
+// read the topics of a log, which must be an array of hex strings
+fn read_topics(input: &HashMap<String, Value>) -> Result<Vec<String>, ModuleError> {
+    let topics = input
+        .get("topics")
+        .and_then(|v| v.as_array())
+        .ok_or(ModuleError::DecodeError { reason: "the log has no topics array".to_string() })?;
 
-    // parse the abi params into an array
-    let parsed_abi: Vec<Value> = match serde_json::from_str(&params.abi) {
-        Ok(v) => v,
-        Err(e) => return Ok(None)
+    topics
+        .iter()
+        .map(|v| v.as_str().map(normalize_hex))
+        .collect::<Option<Vec<String>>>()
+        .ok_or(ModuleError::DecodeError { reason: "the log has a topic that is not a string".to_string() })
+}
+
+// normalize a hex string such as a topic or address to lowercase with a 0x prefix, so sources
+// delivering mixed case or unprefixed hex still match the abi
+fn normalize_hex(hex_data: &str) -> String {
+    let clean = hex_data.trim().to_lowercase();
+    format!("0x{}", clean.strip_prefix("0x").unwrap_or(&clean))
+}
+
+// insert the fields documents are keyed and joined on, the event name, the emitting contract and
+// the log's position as numbers whether the source delivers them as numbers or hex quantities
+fn insert_log_fields(input: &mut HashMap<String, Value>, item: &Value, emitter: &str, params: &Parameters) {
+    input.insert("eventName".to_string(), item["name"].clone());
+    if !emitter.is_empty() {
+        input.insert("address".to_string(), Value::String(format_address(emitter, params)));
+    }
+    for key in ["logIndex", "transactionIndex"] {
+        if let Option::Some(index) = input.get(key).and_then(quantity) {
+            input.insert(key.to_string(), Value::from(index));
+        }
+    }
+}
+
+// move the decoded fields under `decodedEvent`, arguments become a map of name to value and the
+// enum labels and scaled amounts of arguments, if any, maps of their own
+fn nest_decoded_event(input: &mut HashMap<String, Value>) {
+    let arguments = match input.remove("arguments") {
+        Option::Some(Value::Array(arguments)) => arguments,
+        _ => return,
     };
-    
-    //loop through the array
-    for item in parsed_abi.iter() {
-        //  filter by event
-        if item["type"] == "event" {
-            let name = item["name"].as_str().unwrap();
-
-            // extract types from inputs
-            let empty: Vec<Value> = Vec::new();
-            let inputs = item["inputs"].as_array().unwrap_or(&empty);
 
-            // extract the types to be reused
-            let types: Vec<&str> = inputs
-                .iter()
-                .map(|input| input["type"].as_str().unwrap())
+    let mut nested = serde_json::Map::new();
+    nested.insert("name".to_string(), input.remove("eventName").unwrap_or_default());
+    nested.insert("signature".to_string(), input.remove("signature").unwrap_or_default());
+
+    let mut args = serde_json::Map::new();
+    let mut labels = serde_json::Map::new();
+    let mut scaled = serde_json::Map::new();
+    for (i, argument) in arguments.iter().enumerate() {
+        let key = component_key(i, argument["name"].as_str().unwrap_or_default());
+        if let Option::Some(label) = argument.get("label") {
+            labels.insert(key.clone(), label.clone());
+        }
+        if let Option::Some(amount) = argument.get("scaled") {
+            scaled.insert(key.clone(), amount.clone());
+        }
+        args.insert(key, argument["value"].clone());
+    }
+    nested.insert("args".to_string(), Value::Object(args));
+    if !labels.is_empty() {
+        nested.insert("labels".to_string(), Value::Object(labels));
+    }
+    if !scaled.is_empty() {
+        nested.insert("scaled".to_string(), Value::Object(scaled));
+    }
+
+    input.insert("decodedEvent".to_string(), Value::Object(nested));
+}
+
+// read a number or a decimal or 0x-prefixed hex string as an integer
+fn quantity(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(hex_digits) => u64::from_str_radix(hex_digits, 16).ok(),
+            Option::None => s.trim().parse().ok(),
+        },
+        _ => Option::None,
+    }
+}
+
+// insert decoded arguments, strict mode fails the record on a decode failure
+// while lenient mode keeps whatever was decodable and notes the failure
+fn insert_arguments(
+    input: &mut HashMap<String, Value>,
+    decoded: (Vec<Value>, Option<ModuleError>),
+    params: &Parameters,
+) -> Result<(), ModuleError> {
+    let (arguments, failure) = decoded;
+    if let Option::Some(e) = failure {
+        if params.mode == Mode::Strict {
+            return Err(e);
+        }
+        input.insert("decodeError".to_string(), Value::String(e.to_string()));
+    }
+    input.insert("arguments".to_string(), Value::Array(arguments));
+    Ok(())
+}
+
+// find the abi event a log was emitted by, flagging whether it is anonymous, the tables are searched in
+// order and a topic0 match in any of them is preferred over an anonymous event of the same arity
+fn find_event<'a>(tables: &[&'a EventTable], topics: &[String], emitter: &str, params: &Parameters) -> Option<(&'a Event, bool)> {
+    for table in tables {
+        // every definition whose signature hash equals the first topic, anonymous events never have it in topic0
+        let candidates = topics
+            .first()
+            .and_then(|topic0| table.by_topic0.get(topic0))
+            .map(|candidates| candidates.as_slice())
+            .unwrap_or_default();
+
+        // overloads sharing a topic0 only differ in their indexed flags (e.g. ERC-20 and ERC-721 Transfer),
+        // so prefer the definition whose indexed inputs account for the remaining topics
+        let definition = candidates
+            .iter()
+            .find(|event| event.indexed_count + 1 == topics.len())
+            .or(candidates.first());
+
+        if let Option::Some(event) = definition {
+            return Option::Some((event, false));
+        }
+    }
+
+    // anonymous events are matched by their number of indexed inputs instead, every topic is an argument,
+    // which any log of that many topics has, so only the logs of the hinted contract are matched
+    if params.anonymous_address.as_deref() != Option::Some(emitter) {
+        return Option::None;
+    }
+    tables
+        .iter()
+        .flat_map(|table| table.anonymous.iter())
+        .find(|event| event.indexed_count == topics.len())
+        .map(|event| (event, true))
+}
+
+// normalize the accepted abi formats to a single json abi array
+fn normalize_abi(abi: &Value) -> Result<Value, ModuleError> {
+    match abi {
+        // a document encoded in a string, e.g. the raw json abi or a copy-pasted artifact
+        Value::String(document) => match serde_json::from_str::<Value>(document) {
+            Ok(abi) => normalize_abi(&abi),
+            Err(e) => Err(ModuleError::AbiFormatError { reason: e.to_string() }),
+        },
+        // hardhat and foundry artifacts carry the abi next to the bytecode and metadata
+        Value::Object(artifact) if artifact.contains_key("abi") => normalize_abi(&artifact["abi"]),
+        // the etherscan getabi response holds the abi as a json encoded string in `result`,
+        // or an error message in its place when the status is not "1"
+        Value::Object(response) if response.contains_key("status") && response.contains_key("result") => {
+            let result = response["result"].as_str().unwrap_or_default();
+            match (response["status"].as_str(), serde_json::from_str::<Value>(result)) {
+                (Option::Some("1"), Ok(abi)) => normalize_abi(&abi),
+                _ => Err(ModuleError::AbiFormatError { reason: format!("etherscan returned: {}", result) }),
+            }
+        }
+        // an abi, or a list of abi documents to merge into one table, the entries may be abi items,
+        // human-readable declarations, or whole documents in any of the accepted formats
+        Value::Array(entries) => {
+            let mut items: Vec<Value> = Vec::new();
+            for entry in entries.iter() {
+                let normalized = match entry {
+                    Value::String(s) if !s.trim_start().starts_with(['[', '{']) => {
+                        parse_declaration(s).into_iter().collect()
+                    }
+                    Value::Object(item) if !item.contains_key("abi") && !item.contains_key("result") => {
+                        vec![entry.clone()]
+                    }
+                    _ => normalize_abi(entry)?.as_array().cloned().unwrap_or_default(),
+                };
+
+                // the same event is commonly declared by several of the merged contracts
+                for item in normalized {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            Ok(Value::Array(items))
+        }
+        _ => Err(ModuleError::AbiFormatError { reason: "expected a json array or object".to_string() }),
+    }
+}
+
+// parse a human-readable event declaration into its json abi form, e.g.
+// "event Transfer(address indexed from, address indexed to, uint256 value)"
+// declarations other than events are not needed to decode logs and are skipped
+fn parse_declaration(declaration: &str) -> Option<Value> {
+    let rest = declaration.trim().strip_prefix("event ")?.trim();
+    let open = rest.find('(')?;
+    let close = matching_paren(rest, open)?;
+
+    let inputs: Vec<Value> = split_components(&rest[open + 1..close])
+        .into_iter()
+        .map(parse_declared_param)
+        .collect();
+
+    Option::Some(serde_json::json!({
+        "type": "event",
+        "name": rest[..open].trim(),
+        "inputs": inputs,
+        "anonymous": rest[close + 1..].trim() == "anonymous",
+    }))
+}
+
+// parse a declared parameter such as `address indexed from` or `(uint256 id, address to)[] orders`
+fn parse_declared_param(param: &str) -> Value {
+    let param = param.trim();
+    let (typ, components, rest) = match param.strip_prefix("tuple").unwrap_or(param).starts_with('(') {
+        true => {
+            let open = param.find('(').unwrap_or(0);
+            let close = matching_paren(param, open).unwrap_or(param.len() - 1);
+            let components: Vec<Value> = split_components(&param[open + 1..close])
+                .into_iter()
+                .map(parse_declared_param)
                 .collect();
 
-            // build signature string
-            let sig = format!("{}({})", name, types.join(","));
-
-            // hash the signature
-            let mut hasher = Keccak256::new();
-            hasher.update(sig.as_bytes());
-            let hashedsig = format!("0x{}", hex::encode(hasher.finalize()));
-
-            // insert the tx hash and block number
-            input.insert("hash".to_string(), serde_json::Value::String(tx_hash.clone()));
-            input.insert("block".to_string(), serde_json::Value::String(block_number.clone()));
-
-            // if hashedname is equals to topic0, we have found the event match now
-            // now we can save the signature as the data and end the loop
-            if hashedsig == *topic0 {
-                // set the data to be the function
-                input.insert("signature".to_string(), serde_json::Value::String(sig));
-
-                // now take the rest of the topics and decode them using the inputs types
-
-                // Decode the rest of the topics using the indexed input types
-                let mut arguments = Vec::new();
-                let mut indexed_items = Vec::new();
-
-                // start from the second value because the first it the signature
-                let mut topic_index = 1;
-
-                for input_item in inputs.iter() {
-                    let name = input_item["name"].as_str().unwrap_or_default();
-                    let typ = input_item["type"].as_str().unwrap_or_default();
-
-                    if input_item["indexed"].as_bool().unwrap_or(false) {
-                        let topic = &topics[topic_index];
-
-                        // decode this value
-                        let value = decode_param(typ, topic);
-
-                        arguments.push(serde_json::json!({
-                            "name": name,
-                            "type": typ,
-                            "value": value.to_string()
-                        }));
-                    } else {
-                        indexed_items.push(serde_json::json!({
-                            "name": name,
-                            "type": typ,
-                        }));
+            // the array suffix directly follows the closing parenthesis
+            let after = &param[close + 1..];
+            let suffix_len = after.find(|c: char| c.is_whitespace()).unwrap_or(after.len());
+            (format!("tuple{}", &after[..suffix_len]), Option::Some(components), &after[suffix_len..])
+        }
+        false => {
+            let typ_len = param.find(|c: char| c.is_whitespace()).unwrap_or(param.len());
+            (param[..typ_len].to_string(), Option::None, &param[typ_len..])
+        }
+    };
+
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let indexed = words.contains(&"indexed");
+    let name = words.iter().find(|w| **w != "indexed").copied().unwrap_or_default();
+
+    let mut value = serde_json::json!({
+        "type": typ,
+        "name": name,
+        "indexed": indexed,
+    });
+    if let Option::Some(components) = components {
+        value["components"] = Value::Array(components);
+    }
+    value
+}
+
+// build an abi event from a text signature such as "Transfer(address,address,uint256)"
+// the signature has no parameter names or indexed flags, so arguments are named positionally
+// and, as solidity events usually index their leading parameters, the first `indexed` are indexed
+fn event_from_signature(sig: &str, indexed: usize) -> Option<Value> {
+    let open = sig.find('(')?;
+    let close = matching_paren(sig, open)?;
+
+    let inputs: Vec<Value> = split_components(&sig[open + 1..close])
+        .into_iter()
+        .enumerate()
+        .map(|(i, typ)| {
+            let mut input = parse_declared_param(typ);
+            input["name"] = Value::String(format!("arg{}", i));
+            input["indexed"] = Value::Bool(i < indexed);
+            input
+        })
+        .collect();
+
+    Option::Some(serde_json::json!({
+        "type": "event",
+        "name": sig[..open].trim(),
+        "inputs": inputs,
+        "anonymous": false,
+    }))
+}
+
+// position of the parenthesis closing the one at `open`
+fn matching_paren(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices().skip_while(|(i, _)| *i < open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Option::Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    Option::None
+}
+
+// build the signature string of an abi event, e.g. "Transfer(address,address,uint256)"
+fn event_signature(item: &Value) -> String {
+    let name = item["name"].as_str().unwrap_or_default();
+
+    // extract types from inputs
+    let empty: Vec<Value> = Vec::new();
+    let inputs = item["inputs"].as_array().unwrap_or(&empty);
+
+    // extract the types to be reused, tuples are spelled out in canonical form
+    let types: Vec<String> = inputs
+        .iter()
+        .map(|input| ParamType::from_abi(input).canonical())
+        .collect();
+
+    format!("{}({})", name, types.join(","))
+}
+
+// hash the signature, giving the topic0 of the event
+fn signature_hash(sig: &str) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(sig.as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+// number of inputs of an abi event that are stored in topics
+fn indexed_count(item: &Value) -> usize {
+    let empty: Vec<Value> = Vec::new();
+    item["inputs"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter(|i| i["indexed"].as_bool().unwrap_or(false))
+        .count()
+}
+
+// decode the arguments of an abi event, `topics` holds only the indexed argument topics
+// indexed arguments without a topic and arguments that failed to decode are emitted as null,
+// the first failure is returned alongside the arguments
+fn decode_arguments(
+    event: &Event,
+    topics: &[String],
+    data: &str,
+    emitter: &str,
+    params: &Parameters,
+) -> (Vec<Value>, Option<ModuleError>) {
+    let sig = &event.signature;
+
+    // data that isn't hex fails every non-indexed argument
+    let (data_bytes, mut failure) = match hex::decode(data.strip_prefix("0x").unwrap_or(data)) {
+        Ok(bytes) => (bytes, Option::None),
+        Err(e) => (Vec::new(), Option::Some(ModuleError::DecodeError {
+            reason: format!("the data of {} is not hex: {}", sig, e),
+        })),
+    };
+
+    let empty: Vec<Value> = Vec::new();
+    let inputs = event.item["inputs"].as_array().unwrap_or(&empty);
+
+    // a data section shorter than the heads of the non-indexed parameters, or not made of whole
+    // slots, means a truncated log or an abi that doesn't match it
+    let expected: usize = inputs
+        .iter()
+        .zip(event.params.iter())
+        .filter(|(input_item, _)| !input_item["indexed"].as_bool().unwrap_or(false))
+        .map(|(_, param)| param.head_size())
+        .sum();
+    if failure.is_none() && data_bytes.len() < expected {
+        failure = Option::Some(ModuleError::DecodeError {
+            reason: format!("expected at least {} bytes of data for event {}, got {}", expected, sig, data_bytes.len()),
+        });
+    } else if failure.is_none() && !data_bytes.len().is_multiple_of(32) {
+        failure = Option::Some(ModuleError::DecodeError {
+            reason: format!("expected whole 32-byte slots of data for event {}, got {} bytes", sig, data_bytes.len()),
+        });
+    }
+
+    let mut record = |name: &str, result: Result<Value, ModuleError>| match result {
+        Ok(value) => value,
+        Err(ModuleError::DecodeError { reason }) => {
+            failure.get_or_insert(ModuleError::DecodeError {
+                reason: format!("argument `{}` of {}: {}", name, sig, reason),
+            });
+            Value::Null
+        }
+        Err(e) => {
+            failure.get_or_insert(e);
+            Value::Null
+        }
+    };
+
+    // Decode the topics using the indexed input types
+    let mut arguments = Vec::new();
+    let mut types = Vec::new();
+    let mut data_items = Vec::new();
+    let mut topic_index = 0;
+
+    for (input_item, param) in inputs.iter().zip(event.params.iter()) {
+        let name = input_item["name"].as_str().unwrap_or_default();
+        let typ = input_item["type"].as_str().unwrap_or_default();
+
+        if input_item["indexed"].as_bool().unwrap_or(false) {
+            // decode this value, indexed reference types (string, bytes, arrays and tuples) are
+            // stored as the keccak hash of their encoding so only the hash can be emitted
+            let value = match (topics.get(topic_index), param) {
+                (Option::None, _) => Value::Null,
+                (Option::Some(topic), ParamType::Elementary(t)) if t != "string" && t != "bytes" => {
+                    record(name, decode_word(typ, topic, params))
+                }
+                (Option::Some(topic), _) => serde_json::json!({
+                    "type": typ,
+                    "indexed": true,
+                    "valueHash": topic,
+                }),
+            };
+
+            arguments.push(argument(input_item, value));
+            types.push(param);
+            topic_index += 1;
+        } else {
+            data_items.push((input_item, param));
+        }
+    }
+
+    // the data section is the abi encoding of the non-indexed parameters as one tuple, the head holds
+    // a slot per static member (or several for static tuples and fixed arrays) and a slot per dynamic
+    // member holding its tail offset from the start of `data`, so heads are walked by head size
+    // rather than in fixed 32-byte steps
+    let mut offset = 0;
+
+    for (input_item, param) in data_items {
+        let name = input_item["name"].as_str().unwrap_or_default();
+
+        // dynamic parameters occupy one head slot pointing into the tail,
+        // static ones are encoded inline and may span several slots
+        let val = record(name, decode_value(param, &data_bytes, offset, params));
+
+        arguments.push(argument(input_item, val));
+
+        offset += param.head_size();
+        types.push(param);
+    }
+
+    // resolve the labels of enum arguments, values outside the configured labels resolve to null
+    let event_name = event.item["name"].as_str().unwrap_or_default();
+    for argument in arguments.iter_mut() {
+        let key = format!("{}.{}", event_name, argument["name"].as_str().unwrap_or_default());
+        if let Option::Some(labels) = params.enums.get(&key) {
+            let label = argument["value"]
+                .as_str()
+                .and_then(|v| v.parse::<usize>().ok())
+                .and_then(|i| labels.get(i));
+            argument["label"] = serde_json::json!(label);
+        }
+    }
+
+    // scale integer amounts by their token decimals, the most specific key wins
+    for argument in arguments.iter_mut() {
+        let name = argument["name"].as_str().unwrap_or_default();
+        let typ = argument["type"].as_str().unwrap_or_default();
+        if int_bits(typ, "uint").is_none() && int_bits(typ, "int").is_none() {
+            continue;
+        }
+
+        let decimals = params.scaling.iter()
+            .find(|(key, _)| key.to_lowercase() == format!("{}.{}", emitter, name.to_lowercase()))
+            .or(params.scaling.iter().find(|(key, _)| key.as_str() == name))
+            .or(params.scaling.iter().find(|(key, _)| !emitter.is_empty() && key.to_lowercase() == emitter))
+            .map(|(_, decimals)| *decimals);
+
+        if let (Option::Some(decimals), Option::Some(raw)) = (decimals, argument["value"].as_str()) {
+            argument["scaled"] = Value::String(scale_decimal(raw, decimals));
+        }
+    }
+
+    // render integers in the configured format, after the passes above have read them as decimals
+    for (argument, param) in arguments.iter_mut().zip(types.iter()) {
+        format_numbers(param, &mut argument["value"], params.number_format);
+    }
+
+    (arguments, failure)
+}
+
+// the emitted object of a decoded argument, the abi `internalType` (e.g. `contract IERC20`,
+// `struct Order`, `enum Status`) is carried along when the abi has one
+fn argument(input_item: &Value, value: Value) -> Value {
+    let mut argument = serde_json::json!({
+        "name": input_item["name"].as_str().unwrap_or_default(),
+        "type": input_item["type"].as_str().unwrap_or_default(),
+        "value": value,
+    });
+    if let Option::Some(internal_type) = input_item.get("internalType").filter(|t| t.is_string()) {
+        argument["internalType"] = internal_type.clone();
+    }
+    argument
+}
+
+// render the integers of a decoded value, walking into arrays and tuples
+fn format_numbers(param: &ParamType, value: &mut Value, format: NumberFormat) {
+    match param {
+        ParamType::Elementary(typ) if int_bits(typ, "uint").is_some() || int_bits(typ, "int").is_some() => {
+            let hex = match value.as_str().and_then(decimal_to_hex) {
+                Option::Some(hex) => hex,
+                Option::None => return,
+            };
+            match format {
+                NumberFormat::Decimal => {}
+                NumberFormat::Hex => *value = Value::String(hex),
+                NumberFormat::Both => *value = serde_json::json!({ "value": value.clone(), "raw": hex }),
+            }
+        }
+        ParamType::Array(elem, _) => {
+            if let Value::Array(values) = value {
+                for v in values.iter_mut() {
+                    format_numbers(elem, v, format);
+                }
+            }
+        }
+        ParamType::Tuple(components) => {
+            if let Value::Object(values) = value {
+                for (i, (name, component)) in components.iter().enumerate() {
+                    if let Option::Some(v) = values.get_mut(&component_key(i, name)) {
+                        format_numbers(component, v, format);
                     }
-                    topic_index += 1;
                 }
+            }
+        }
+        ParamType::Elementary(_) => {}
+    }
+}
+
+// convert a decimal integer string to a 0x-prefixed hex quantity, keeping its sign
+fn decimal_to_hex(decimal: &str) -> Option<String> {
+    let (sign, digits) = match decimal.strip_prefix('-') {
+        Option::Some(digits) => ("-", digits),
+        Option::None => ("", decimal),
+    };
+    U256::from_dec_str(digits).map(|n| format!("{}0x{:x}", sign, n))
+}
+
+// recursive model of an abi type, built from an abi input or tuple component
+#[derive(Clone, Debug)]
+enum ParamType {
+    Elementary(String),
+    Array(Box<ParamType>, Option<usize>),
+    Tuple(Vec<(String, ParamType)>),
+}
+
+impl ParamType {
+    fn from_abi(param: &Value) -> ParamType {
+        let typ = param["type"].as_str().unwrap_or_default();
+        ParamType::from_type(typ, param)
+    }
 
-                // get the data (with the data you get the remaining args)
-                let data: String = input["data"].as_str().unwrap().to_string();
-
-                // using the indexed_items array we can get the type and name to use to decode the index item
-                // in the data var then add it to the arguments
-                
-                // Decode non-indexed values from the `data` hex string
-                let data_bytes = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).unwrap();
-                let mut offset = 0;
-
-                for input_item in indexed_items.iter() {
-                    let name = input_item["name"].as_str().unwrap();
-                    let typ = input_item["type"].as_str().unwrap();
-
-                    let value = match typ {
-                        "address" => {
-                            // address is 20 bytes, right-aligned in 32 bytes
-                            let start = offset + 12; // skip 12 bytes (24 hex chars) of padding
-                            let end = offset + 32;
-                            let addr = &data_bytes[start..end];
-                            format!("0x{}", hex::encode(addr))
-                        }
-                        "uint256" => {
-                            let start = offset;
-                            let end = offset + 32;
-                            let uint = &data_bytes[start..end];
-                            format!("0x{}", hex::encode(uint))
-                        }
-                        "bool" => {
-                            let b = data_bytes[offset + 31]; // last byte
-                            (b != 0).to_string()
-                        }
-                        "bytes32" => {
-                            let start = offset;
-                            let end = offset + 32;
-                            let bytes = &data_bytes[start..end];
-                            format!("0x{}", hex::encode(bytes))
-                        }
-                        _ => {
-                            format!("unsupported type: {}", typ)
-                        }
-                    };
-
-                    // decode param before inserting
-                    let val = decode_param(typ, &value);
-
-                    arguments.push(serde_json::json!({
-                        "name": name,
-                        "type": typ,
-                        "value": val,
-                    }));
-
-                    offset += 32;
+    // `param` carries the `components` used when the innermost type is a tuple
+    fn from_type(typ: &str, param: &Value) -> ParamType {
+        if let Option::Some((elem, len)) = parse_array(typ) {
+            return ParamType::Array(Box::new(ParamType::from_type(elem, param)), len);
+        }
+
+        if typ == "tuple" {
+            let empty: Vec<Value> = Vec::new();
+            let components = param["components"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .map(|c| (c["name"].as_str().unwrap_or_default().to_string(), ParamType::from_abi(c)))
+                .collect();
+            return ParamType::Tuple(components);
+        }
+
+        // tuples spelled out inline carry no component names, e.g. `tuple(uint256[],address)`
+        if let Option::Some(inner) = typ.strip_prefix("tuple").unwrap_or(typ).strip_prefix('(') {
+            if let Option::Some(inner) = inner.strip_suffix(')') {
+                let components = split_components(inner)
+                    .into_iter()
+                    .map(|c| (String::new(), ParamType::from_type(c, &Value::Null)))
+                    .collect();
+                return ParamType::Tuple(components);
+            }
+        }
+
+        ParamType::Elementary(typ.to_string())
+    }
+
+    // the form used in event signatures, e.g. `(uint256,address)[]`
+    fn canonical(&self) -> String {
+        match self {
+            ParamType::Elementary(typ) => match typ.as_str() {
+                // aliases are hashed under their explicit width
+                "uint" | "int" => format!("{}256", typ),
+                "fixed" | "ufixed" => format!("{}128x18", typ),
+                _ => typ.clone(),
+            },
+            ParamType::Array(elem, Option::None) => format!("{}[]", elem.canonical()),
+            ParamType::Array(elem, Option::Some(k)) => format!("{}[{}]", elem.canonical(), k),
+            ParamType::Tuple(components) => {
+                let types: Vec<String> = components.iter().map(|(_, c)| c.canonical()).collect();
+                format!("({})", types.join(","))
+            }
+        }
+    }
+
+    // whether the type is encoded in the tail with its head slot holding an offset
+    fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Elementary(typ) => typ == "string" || typ == "bytes",
+            ParamType::Array(_, Option::None) => true,
+            ParamType::Array(elem, Option::Some(_)) => elem.is_dynamic(),
+            ParamType::Tuple(components) => components.iter().any(|(_, c)| c.is_dynamic()),
+        }
+    }
+
+    // number of bytes the type occupies in the head
+    fn head_size(&self) -> usize {
+        if self.is_dynamic() {
+            return 32;
+        }
+        match self {
+            ParamType::Array(elem, Option::Some(k)) => k * elem.head_size(),
+            ParamType::Tuple(components) => components.iter().map(|(_, c)| c.head_size()).sum(),
+            _ => 32,
+        }
+    }
+}
+
+// decode the value of type `param` whose head slot is at `offset`
+// offsets held in dynamic heads are relative to the start of `data`
+fn decode_value(param: &ParamType, data: &[u8], offset: usize, params: &Parameters) -> Result<Value, ModuleError> {
+    match param {
+        ParamType::Array(elem, len) => {
+            let (region, base, count) = match len {
+                // dynamic array, the tail holds the element count followed by the encoded elements
+                Option::None => {
+                    let start = read_usize(data, offset)?;
+                    (tail(data, start.saturating_add(32))?, 0, read_usize(data, start)?)
                 }
+                // fixed array of dynamic elements, the tail holds the k element heads
+                Option::Some(k) if elem.is_dynamic() => (tail(data, read_usize(data, offset)?)?, 0, *k),
+                // fixed array of static elements, the k elements are encoded inline
+                Option::Some(k) => (data, offset, *k),
+            };
+            let size = elem.head_size();
+            let values = (0..count)
+                .map(|i| decode_value(elem, region, base + i * size, params))
+                .collect::<Result<Vec<Value>, ModuleError>>()?;
+            Ok(Value::Array(values))
+        }
+        ParamType::Tuple(components) => {
+            // a dynamic tuple is encoded in the tail and its members' offsets are relative to it,
+            // a static tuple is encoded inline
+            let (region, mut cursor) = match param.is_dynamic() {
+                true => (tail(data, read_usize(data, offset)?)?, 0),
+                false => (data, offset),
+            };
+            let mut values = serde_json::Map::new();
+            for (i, (name, component)) in components.iter().enumerate() {
+                values.insert(component_key(i, name), decode_value(component, region, cursor, params)?);
+                cursor += component.head_size();
+            }
+            Ok(Value::Object(values))
+        }
+        ParamType::Elementary(typ) => match typ.as_str() {
+            "string" => Ok(Value::String(decode_string(data, offset)?)),
+            "bytes" => Ok(Value::String(decode_bytes(data, offset)?)),
+            _ => decode_word(typ, &hex::encode(read_word(data, offset)?), params),
+        },
+    }
+}
 
-                input.insert("arguments".to_string(), serde_json::Value::Array(arguments));
-                break;
+// the key of a decoded tuple member, its name from the abi `components` or `field<position>` for unnamed members
+fn component_key(index: usize, name: &str) -> String {
+    match name.is_empty() {
+        true => format!("field{}", index),
+        false => name.to_string(),
+    }
+}
+
+// split the inside of an inline tuple on its top-level commas
+// e.g. `uint256,(address,bool)[]` -> [`uint256`, `(address,bool)[]`]
+fn split_components(inner: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(inner[start..i].trim());
+                start = i + 1;
             }
+            _ => {}
         }
     }
+    if !inner[start..].trim().is_empty() {
+        parts.push(inner[start..].trim());
+    }
+    parts
+}
 
-    // input.insert("data".to_string(), serde_json::Value::String(topic0.to_string()));
+// split an array type into its element type and length, `None` for dynamic arrays
+// e.g. `uint256[3][]` -> (`uint256[3]`, None)
+fn parse_array(typ: &str) -> Option<(&str, Option<usize>)> {
+    let body = typ.strip_suffix(']')?;
+    let open = body.rfind('[')?;
+    let len = &body[open + 1..];
+    if len.is_empty() {
+        return Option::Some((&body[..open], Option::None));
+    }
+    len.parse().ok().map(|k| (&body[..open], Option::Some(k)))
+}
 
-    let result_json = serde_json::to_vec(&input.clone())?;
-    lens_sdk::free_transport_buffer(ptr)?;
-    Ok(Some(result_json))
+// read the 32-byte slot at `offset`
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8], ModuleError> {
+    offset
+        .checked_add(32)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(ModuleError::DecodeError {
+            reason: format!("the slot at byte {} is out of bounds of {} bytes of data", offset, data.len()),
+        })
+}
+
+// the data from `start` onwards
+fn tail(data: &[u8], start: usize) -> Result<&[u8], ModuleError> {
+    data.get(start..).ok_or(ModuleError::DecodeError {
+        reason: format!("the offset {} is out of bounds of {} bytes of data", start, data.len()),
+    })
+}
+
+// read a 32-byte slot as an offset or length, which must fit the address space
+fn read_usize(data: &[u8], offset: usize) -> Result<usize, ModuleError> {
+    let word = read_word(data, offset)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&word[24..32]);
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(buf)).map_err(|e| ModuleError::DecodeError { reason: e.to_string() }),
+        false => Err(ModuleError::DecodeError { reason: format!("the slot at byte {} is too large for an offset or length", offset) }),
+    }
+}
+
+// read the payload of a dynamic `string` or `bytes` whose head slot is at `offset`
+// the head holds the tail offset, the tail holds the length followed by the payload
+fn read_dynamic(data: &[u8], offset: usize) -> Result<&[u8], ModuleError> {
+    let start = read_usize(data, offset)?;
+    let len = read_usize(data, start)?;
+    let begin = start.saturating_add(32);
+    begin
+        .checked_add(len)
+        .and_then(|end| data.get(begin..end))
+        .ok_or(ModuleError::DecodeError {
+            reason: format!("the {} byte payload at byte {} is out of bounds of {} bytes of data", len, begin, data.len()),
+        })
 }
 
-fn decode_param(typ: &str, hex_data: &str) -> String {
+fn decode_string(data: &[u8], offset: usize) -> Result<String, ModuleError> {
+    Ok(String::from_utf8_lossy(read_dynamic(data, offset)?).to_string())
+}
+
+fn decode_bytes(data: &[u8], offset: usize) -> Result<String, ModuleError> {
+    Ok(format!("0x{}", hex::encode(read_dynamic(data, offset)?)))
+}
+
+// decode a static elementary type from its 32-byte slot
+fn decode_word(typ: &str, hex_data: &str, params: &Parameters) -> Result<Value, ModuleError> {
     let clean = hex_data.trim_start_matches("0x");
+    if clean.len() != 64 || !clean.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ModuleError::DecodeError { reason: format!("`{}` is not a 32-byte hex word", hex_data) });
+    }
 
     match typ {
-        "uint256" => {
-            u128::from_str_radix(clean, 16)
-                .map(|v| v.to_string())
-                .unwrap_or_else(|_| "0".to_string())
+        "address" => Ok(Value::String(format_address(&decode_param(typ, hex_data)?, params))),
+        // an external function is a 20-byte address followed by a 4-byte selector, left-aligned
+        "function" => Ok(serde_json::json!({
+            "address": format_address(&format!("0x{}", &clean[..40]), params),
+            "selector": format!("0x{}", &clean[40..48]),
+        })),
+        _ => Ok(Value::String(decode_param(typ, hex_data)?)),
+    }
+}
+
+// render a lowercase 0x-prefixed address in the configured format
+fn format_address(address: &str, params: &Parameters) -> String {
+    match params.address_format {
+        AddressFormat::Lowercase => address.to_string(),
+        AddressFormat::Checksum => to_checksum_address(address),
+    }
+}
+
+// EIP-55: uppercase each hex letter whose nibble in keccak(lowercase address) is 8 or above
+fn to_checksum_address(address: &str) -> String {
+    let lower = address.trim_start_matches("0x").to_lowercase();
+    let mut hasher = Keccak256::new();
+    hasher.update(lower.as_bytes());
+    let hash = hex::encode(hasher.finalize());
+
+    let checksummed: String = lower
+        .chars()
+        .zip(hash.chars())
+        .map(|(c, h)| match h.to_digit(16).unwrap_or(0) >= 8 {
+            true => c.to_ascii_uppercase(),
+            false => c,
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+// decode a static elementary type other than `function` from a 32-byte hex word
+fn decode_param(typ: &str, hex_data: &str) -> Result<String, ModuleError> {
+    let clean = hex_data.trim_start_matches("0x");
+    let invalid = |e: hex::FromHexError| ModuleError::DecodeError { reason: e.to_string() };
+
+    match typ {
+        _ if int_bits(typ, "uint").is_some() => {
+            // uintN is right-aligned in the slot, keep only the low N bits
+            let bits = int_bits(typ, "uint").unwrap_or(256);
+            let low = &clean[clean.len().saturating_sub(bits / 4)..];
+            Ok(U256::from_be_slice(&hex::decode(low).map_err(invalid)?).to_string())
+        }
+        _ if int_bits(typ, "int").is_some() => {
+            // intN is sign-extended to the slot, the low N bits hold the two's complement value
+            let bits = int_bits(typ, "int").unwrap_or(256);
+            let low = &clean[clean.len().saturating_sub(bits / 4)..];
+            Ok(decode_signed(&hex::decode(low).map_err(invalid)?))
         }
-        "address" => {
+        _ if parse_fixed(typ).is_some() => {
+            // fixedMxN holds the intM/uintM value v and represents v / 10^N
+            let (signed, bits, decimals) = parse_fixed(typ).unwrap_or((true, 128, 18));
+            let integer = match signed {
+                true => decode_param(&format!("int{}", bits), hex_data)?,
+                false => decode_param(&format!("uint{}", bits), hex_data)?,
+            };
+            Ok(scale_decimal(&integer, decimals))
+        }
+        "address" if clean.len() >= 64 => {
             let addr = &clean[24..64]; // last 20 bytes (40 hex chars)
-            format!("0x{}", addr)
+            Ok(format!("0x{}", addr))
         }
         "bool" => {
             let b = clean.ends_with("1");
-            b.to_string()
+            Ok(b.to_string())
+        }
+        _ if bytes_width(typ).is_some() => {
+            // bytesN is left-aligned in the slot, keep only the first N bytes
+            let width = bytes_width(typ).unwrap_or(32);
+            Ok(format!("0x{}", &clean[..(width * 2).min(clean.len())]))
+        }
+        _ => Err(ModuleError::DecodeError { reason: format!("unsupported type: {}", typ) }),
+    }
+}
+
+// width of an `uintN`/`intN` type, 8 to 256 in steps of 8, a bare `uint`/`int` is 256 bits
+fn int_bits(typ: &str, prefix: &str) -> Option<usize> {
+    let width = typ.strip_prefix(prefix)?;
+    if width.is_empty() {
+        return Option::Some(256);
+    }
+    match width.parse::<usize>() {
+        Ok(bits) if (8..=256).contains(&bits) && bits.is_multiple_of(8) => Option::Some(bits),
+        _ => Option::None,
+    }
+}
+
+// signedness, width and decimals of a `fixedMxN`/`ufixedMxN` type
+// M is 8 to 256 in steps of 8 and N is 1 to 80, a bare `fixed`/`ufixed` is `fixed128x18`
+fn parse_fixed(typ: &str) -> Option<(bool, usize, usize)> {
+    let (signed, rest) = match typ.strip_prefix("ufixed") {
+        Option::Some(rest) => (false, rest),
+        Option::None => (true, typ.strip_prefix("fixed")?),
+    };
+    if rest.is_empty() {
+        return Option::Some((signed, 128, 18));
+    }
+
+    let (bits, decimals) = rest.split_once('x')?;
+    let bits: usize = bits.parse().ok()?;
+    let decimals: usize = decimals.parse().ok()?;
+    match (8..=256).contains(&bits) && bits.is_multiple_of(8) && (1..=80).contains(&decimals) {
+        true => Option::Some((signed, bits, decimals)),
+        false => Option::None,
+    }
+}
+
+// shift the decimal point of an integer string `decimals` places to the left,
+// trailing fractional zeros are dropped, e.g. ("-1500", 3) -> "-1.5"
+fn scale_decimal(integer: &str, decimals: usize) -> String {
+    let (sign, digits) = match integer.strip_prefix('-') {
+        Option::Some(digits) => ("-", digits),
+        Option::None => ("", integer),
+    };
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => format!("{}{}", sign, whole),
+        false => format!("{}{}.{}", sign, whole, fraction),
+    }
+}
+
+// width of a fixed `bytesN` type, 1 to 32
+fn bytes_width(typ: &str) -> Option<usize> {
+    match typ.strip_prefix("bytes")?.parse::<usize>() {
+        Ok(width) if (1..=32).contains(&width) => Option::Some(width),
+        _ => Option::None,
+    }
+}
+
+// decode big-endian two's complement bytes into a signed decimal string
+fn decode_signed(bytes: &[u8]) -> String {
+    let negative = bytes.first().map(|b| b & 0x80 != 0).unwrap_or(false);
+    if !negative {
+        return U256::from_be_slice(bytes).to_string();
+    }
+
+    // sign-extend to 256 bits, the magnitude is then the two's complement negation
+    let mut extended = vec![0xffu8; 32usize.saturating_sub(bytes.len())];
+    extended.extend_from_slice(bytes);
+    format!("-{}", U256::from_be_slice(&extended).wrapping_neg())
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // two's complement negation modulo 2^256
+    fn wrapping_neg(self) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut carry = true;
+        for (i, l) in self.0.iter().enumerate() {
+            let (v, overflow) = (!l).overflowing_add(carry as u64);
+            limbs[i] = v;
+            carry = overflow;
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            let mut carry = c.to_digit(10)? as u128;
+            for limb in n.0.iter_mut() {
+                let acc = *limb as u128 * 10 + carry;
+                *limb = acc as u64;
+                carry = acc >> 64;
+            }
+            if carry != 0 {
+                return Option::None;
+            }
+        }
+        Option::Some(n)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
         }
-        "bytes32" => {
-            format!("0x{}", clean)
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
+
+impl fmt::LowerHex for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the most significant non-zero limb is written without padding, the rest as 16 digits each
+        let top = self.0.iter().rposition(|l| *l != 0).unwrap_or(0);
+        let mut out = format!("{:x}", self.0[top]);
+        for limb in self.0[..top].iter().rev() {
+            out.push_str(&format!("{:016x}", limb));
+        }
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> Parameters {
+        serde_json::from_value(serde_json::json!({ "abi": [] })).unwrap()
+    }
+
+    fn word(n: u64) -> Vec<u8> {
+        [vec![0u8; 24], n.to_be_bytes().to_vec()].concat()
+    }
+
+    #[test]
+    fn decode_value_follows_head_offsets_into_the_tail() {
+        // (uint256 7, string "hi", uint256[] [1, 2])
+        let mut padded = b"hi".to_vec();
+        padded.resize(32, 0);
+        let data = [word(7), word(0x60), word(0xa0), word(2), padded, word(2), word(1), word(2)].concat();
+
+        let value = |typ: &str, offset: usize| decode_value(&ParamType::from_type(typ, &Value::Null), &data, offset, &params());
+        assert_eq!(value("uint256", 0).unwrap(), Value::from("7"));
+        assert_eq!(value("string", 32).unwrap(), Value::from("hi"));
+        assert_eq!(value("uint256[]", 64).unwrap(), serde_json::json!(["1", "2"]));
+    }
+
+    #[test]
+    fn decode_arguments_reads_the_static_parameters_after_a_string() {
+        let event = Event::from_abi(&serde_json::json!({ "type": "event", "name": "Note", "inputs": [
+            { "name": "who", "type": "address", "indexed": true },
+            { "name": "a", "type": "uint256", "indexed": false },
+            { "name": "memo", "type": "string", "indexed": false },
+            { "name": "b", "type": "uint256", "indexed": false },
+        ] }));
+        // (uint256 1, string "memo", uint256 3), the string's head slot points past the heads of all three
+        let mut padded = b"memo".to_vec();
+        padded.resize(32, 0);
+        let data = [word(1), word(0x60), word(3), word(4), padded].concat();
+        let topics = vec![signature_hash("Note(address,uint256,string,uint256)"), "0x".to_string() + &"0".repeat(64)];
+
+        let (arguments, failure) = decode_arguments(&event, &topics, &format!("0x{}", hex::encode(data)), "", &params());
+        assert!(failure.is_none());
+        let values: Vec<&Value> = arguments.iter().map(|argument| &argument["value"]).collect();
+        assert_eq!(values[1..], [&Value::from("1"), &Value::from("memo"), &Value::from("3")]);
+    }
+
+    #[test]
+    fn decode_value_reads_static_arrays_and_tuples_inline() {
+        let data = [word(3), word(2), word(1)].concat();
+
+        let array = ParamType::from_type("uint256[2]", &Value::Null);
+        assert_eq!(decode_value(&array, &data, 0, &params()).unwrap(), serde_json::json!(["3", "2"]));
+        let tuple = ParamType::from_type("(uint8,bool)", &Value::Null);
+        assert_eq!(decode_value(&tuple, &data, 32, &params()).unwrap(), serde_json::json!({ "field0": "2", "field1": "true" }));
+    }
+
+    #[test]
+    fn decode_value_fails_on_offsets_out_of_bounds() {
+        let data = [word(0x1000), word(0)].concat();
+
+        assert!(decode_value(&ParamType::from_type("string", &Value::Null), &data, 0, &params()).is_err());
+        assert!(decode_value(&ParamType::from_type("uint256[]", &Value::Null), &data, 0, &params()).is_err());
+        assert!(decode_value(&ParamType::from_type("uint256", &Value::Null), &data, 64, &params()).is_err());
+    }
+
+    #[test]
+    fn to_checksum_address_matches_the_eip55_vectors() {
+        for address in ["0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB", "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb"] {
+            assert_eq!(to_checksum_address(&address.to_lowercase()), address);
         }
-        _ => format!("unsupported type: {}", typ),
+    }
+
+    #[test]
+    fn quantity_reads_numbers_and_hex_or_decimal_strings() {
+        assert_eq!(quantity(&Value::from(26)), Option::Some(26));
+        assert_eq!(quantity(&Value::from("0x1a")), Option::Some(26));
+        assert_eq!(quantity(&Value::from(" 26 ")), Option::Some(26));
+        assert_eq!(quantity(&Value::from("0xzz")), Option::None);
+        assert_eq!(quantity(&Value::Bool(true)), Option::None);
+    }
+
+    #[test]
+    fn find_event_prefers_a_topic0_match_over_a_contract_anonymous_event() {
+        let transfer = serde_json::json!([{ "type": "event", "name": "Transfer", "inputs": [
+            { "name": "from", "type": "address", "indexed": true },
+            { "name": "to", "type": "address", "indexed": true },
+            { "name": "value", "type": "uint256", "indexed": false },
+        ] }]);
+        let ping = serde_json::json!([{ "type": "event", "name": "Ping", "anonymous": true, "inputs": [
+            { "name": "a", "type": "uint256", "indexed": true },
+            { "name": "b", "type": "uint256", "indexed": true },
+            { "name": "c", "type": "uint256", "indexed": true },
+        ] }]);
+        let (global, contract) = (EventTable::from_abi(&transfer), EventTable::from_abi(&ping));
+        let mut params = params();
+        params.anonymous_address = Option::Some("0xc0".to_string());
+
+        let word = "0x".to_string() + &"0".repeat(64);
+        let topics = vec![signature_hash("Transfer(address,address,uint256)"), word.clone(), word.clone()];
+        let (event, anonymous) = find_event(&[&contract, &global], &topics, "0xc0", &params).unwrap();
+        assert_eq!((event.item["name"].as_str(), anonymous), (Option::Some("Transfer"), false));
+
+        let topics = vec![word.clone(), word.clone(), word];
+        let (event, anonymous) = find_event(&[&contract, &global], &topics, "0xc0", &params).unwrap();
+        assert_eq!((event.item["name"].as_str(), anonymous), (Option::Some("Ping"), true));
+        assert!(find_event(&[&contract, &global], &topics, "0xd0", &params).is_none());
+        params.anonymous_address = Option::None;
+        assert!(find_event(&[&contract, &global], &topics, "0xc0", &params).is_none());
+    }
+
+    #[test]
+    fn log_position_fails_without_panicking_on_a_missing_field() {
+        let log = |fields: Value| serde_json::from_value::<HashMap<String, Value>>(fields).unwrap();
+
+        let position = log_position(&log(serde_json::json!({ "transactionHash": "0xab", "blockNumber": "0x10" })));
+        assert_eq!(position.unwrap(), ("0xab".to_string(), "16".to_string()));
+        assert!(log_position(&log(serde_json::json!({ "blockNumber": 16 }))).is_err());
+        assert!(log_position(&log(serde_json::json!({ "transactionHash": "0xab" }))).is_err());
+        assert!(log_position(&log(serde_json::json!({ "transactionHash": 1, "blockNumber": 16 }))).is_err());
     }
 }
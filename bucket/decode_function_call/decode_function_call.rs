@@ -0,0 +1,789 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+// the variants keep the `Error` suffix that ParametersNotSetError gives the errors of every lens
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    AbiFormatError{reason: String},
+    DecodeError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::AbiFormatError { reason } =>
+                write!(f, "The abi parameter could not be read. Reason: {}", reason),
+            ModuleError::DecodeError { reason } =>
+                write!(f, "The transaction input could not be decoded. Reason: {}", reason),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // a json abi document, or that document encoded in a string
+    pub abi: Value,
+    // the abi functions keyed by their 4-byte selector, built by set_param
+    #[serde(skip)]
+    pub functions: HashMap<String, Value>,
+}
+
+// shared so that reading the parameters for each record doesn't copy the abi
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    parameter.abi = normalize_abi(&parameter.abi)?;
+    let empty: Vec<Value> = Vec::new();
+    parameter.functions = parameter.abi
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter(|item| item["type"] == "function")
+        .map(|item| (selector(&function_signature(item)), item.clone()))
+        .collect();
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(Arc::new(parameter));
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    // the calldata is `input` on rpc transactions and `data` on signed transaction requests
+    let calldata = input
+        .get("input")
+        .or(input.get("data"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    let calldata = calldata.strip_prefix("0x").unwrap_or(&calldata);
+
+    // plain value transfers have no selector and calls of unknown functions are passed through as they are
+    let function = calldata.get(..8).and_then(|sel| params.functions.get(&format!("0x{}", sel)));
+    if let Option::Some(item) = function {
+        input.insert("functionName".to_string(), item["name"].clone());
+        input.insert("signature".to_string(), Value::String(function_signature(item)));
+        input.insert("selector".to_string(), Value::String(format!("0x{}", &calldata[..8])));
+
+        // the arguments are abi encoded after the selector, like a tuple of the function inputs
+        let (arguments, failure) = match hex::decode(&calldata[8..]) {
+            Ok(args) => decode_arguments(item, &args),
+            Err(e) => (Vec::new(), Option::Some(ModuleError::DecodeError {
+                reason: format!("the calldata is not hex: {}", e),
+            })),
+        };
+        if let Option::Some(e) = failure {
+            input.insert("decodeError".to_string(), Value::String(e.to_string()));
+        }
+        input.insert("arguments".to_string(), Value::Array(arguments));
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// build the signature string of an abi function, e.g. "transfer(address,uint256)"
+fn function_signature(item: &Value) -> String {
+    let empty: Vec<Value> = Vec::new();
+    let types: Vec<String> = item["inputs"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .map(|input| ParamType::from_abi(input).canonical())
+        .collect();
+
+    format!("{}({})", item["name"].as_str().unwrap_or_default(), types.join(","))
+}
+
+// the first 4 bytes of the hashed signature, which prefix the calldata of the function
+fn selector(sig: &str) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(sig.as_bytes());
+    format!("0x{}", hex::encode(&hasher.finalize()[..4]))
+}
+
+// decode the arguments of an abi function from the calldata following the selector
+// arguments that failed to decode are emitted as null, the first failure is returned alongside them
+fn decode_arguments(item: &Value, args: &[u8]) -> (Vec<Value>, Option<ModuleError>) {
+    let sig = function_signature(item);
+    let mut failure: Option<ModuleError> = Option::None;
+    let mut arguments = Vec::new();
+    let mut offset = 0;
+
+    let empty: Vec<Value> = Vec::new();
+    for input_item in item["inputs"].as_array().unwrap_or(&empty).iter() {
+        let name = input_item["name"].as_str().unwrap_or_default();
+        let param = ParamType::from_abi(input_item);
+
+        let value = match decode_value(&param, args, offset) {
+            Ok(value) => value,
+            Err(e) => {
+                let reason = match e {
+                    ModuleError::DecodeError { reason } => reason,
+                    e => e.to_string(),
+                };
+                failure.get_or_insert(ModuleError::DecodeError {
+                    reason: format!("argument `{}` of {}: {}", name, sig, reason),
+                });
+                Value::Null
+            }
+        };
+
+        let mut argument = serde_json::json!({
+            "name": name,
+            "type": input_item["type"].as_str().unwrap_or_default(),
+            "value": value,
+        });
+        if let Option::Some(internal_type) = input_item.get("internalType").filter(|t| t.is_string()) {
+            argument["internalType"] = internal_type.clone();
+        }
+        arguments.push(argument);
+
+        offset += param.head_size();
+    }
+
+    (arguments, failure)
+}
+
+// normalize the accepted abi formats to a single json abi array
+fn normalize_abi(abi: &Value) -> Result<Value, ModuleError> {
+    match abi {
+        // a document encoded in a string, e.g. the raw json abi or a copy-pasted artifact
+        Value::String(document) => match serde_json::from_str::<Value>(document) {
+            Ok(abi) => normalize_abi(&abi),
+            Err(e) => Err(ModuleError::AbiFormatError { reason: e.to_string() }),
+        },
+        // hardhat and foundry artifacts carry the abi next to the bytecode and metadata
+        Value::Object(artifact) if artifact.contains_key("abi") => normalize_abi(&artifact["abi"]),
+        // the etherscan getabi response holds the abi as a json encoded string in `result`,
+        // or an error message in its place when the status is not "1"
+        Value::Object(response) if response.contains_key("status") && response.contains_key("result") => {
+            let result = response["result"].as_str().unwrap_or_default();
+            match (response["status"].as_str(), serde_json::from_str::<Value>(result)) {
+                (Option::Some("1"), Ok(abi)) => normalize_abi(&abi),
+                _ => Err(ModuleError::AbiFormatError { reason: format!("etherscan returned: {}", result) }),
+            }
+        }
+        // an abi, or a list of abi documents to merge into one table, the entries may be abi items,
+        // human-readable declarations, or whole documents in any of the accepted formats
+        Value::Array(entries) => {
+            let mut items: Vec<Value> = Vec::new();
+            for entry in entries.iter() {
+                let normalized = match entry {
+                    Value::String(s) if !s.trim_start().starts_with(['[', '{']) => {
+                        parse_declaration(s).into_iter().collect()
+                    }
+                    Value::Object(item) if !item.contains_key("abi") && !item.contains_key("result") => {
+                        vec![entry.clone()]
+                    }
+                    _ => normalize_abi(entry)?.as_array().cloned().unwrap_or_default(),
+                };
+
+                // the same item is commonly declared by several of the merged contracts
+                for item in normalized {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            Ok(Value::Array(items))
+        }
+        _ => Err(ModuleError::AbiFormatError { reason: "expected a json array or object".to_string() }),
+    }
+}
+
+// parse a human-readable declaration into its json abi form, e.g.
+// "function transfer(address to, uint256 amount) external returns (bool)"
+// events, functions, errors and constructors are understood, other declarations are skipped
+fn parse_declaration(declaration: &str) -> Option<Value> {
+    let declaration = declaration.trim();
+    let (kind, rest) = ["event", "function", "error", "constructor"]
+        .iter()
+        .find_map(|kind| declaration.strip_prefix(kind).map(|rest| (*kind, rest.trim())))?;
+    let open = rest.find('(')?;
+    let close = matching_paren(rest, open)?;
+
+    let inputs: Vec<Value> = split_components(&rest[open + 1..close])
+        .into_iter()
+        .map(parse_declared_param)
+        .collect();
+    let modifiers = &rest[close + 1..];
+
+    let mut item = serde_json::json!({
+        "type": kind,
+        "inputs": inputs,
+    });
+    if kind != "constructor" {
+        item["name"] = Value::String(rest[..open].trim().to_string());
+    }
+    match kind {
+        "event" => item["anonymous"] = Value::Bool(modifiers.trim() == "anonymous"),
+        "function" | "constructor" => {
+            let outputs: Vec<Value> = modifiers
+                .find("returns")
+                .and_then(|at| {
+                    let open = at + modifiers[at..].find('(')?;
+                    let close = matching_paren(modifiers, open)?;
+                    Option::Some(split_components(&modifiers[open + 1..close]))
+                })
+                .unwrap_or_default()
+                .into_iter()
+                .map(parse_declared_param)
+                .collect();
+            let mutability = modifiers
+                .split_whitespace()
+                .find(|w| ["view", "pure", "payable"].contains(w))
+                .unwrap_or("nonpayable");
+            if kind == "function" {
+                item["outputs"] = Value::Array(outputs);
+            }
+            item["stateMutability"] = Value::String(mutability.to_string());
+        }
+        _ => {}
+    }
+    Option::Some(item)
+}
+
+// parse a declared parameter such as `address indexed from` or `(uint256 id, address to)[] memory orders`
+fn parse_declared_param(param: &str) -> Value {
+    let param = param.trim();
+    let (typ, components, rest) = match param.strip_prefix("tuple").unwrap_or(param).starts_with('(') {
+        true => {
+            let open = param.find('(').unwrap_or(0);
+            let close = matching_paren(param, open).unwrap_or(param.len() - 1);
+            let components: Vec<Value> = split_components(&param[open + 1..close])
+                .into_iter()
+                .map(parse_declared_param)
+                .collect();
+
+            // the array suffix directly follows the closing parenthesis
+            let after = &param[close + 1..];
+            let suffix_len = after.find(|c: char| c.is_whitespace()).unwrap_or(after.len());
+            (format!("tuple{}", &after[..suffix_len]), Option::Some(components), &after[suffix_len..])
+        }
+        false => {
+            let typ_len = param.find(|c: char| c.is_whitespace()).unwrap_or(param.len());
+            (param[..typ_len].to_string(), Option::None, &param[typ_len..])
+        }
+    };
+
+    // the name is the word that isn't a modifier or data location
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let indexed = words.contains(&"indexed");
+    let name = words
+        .iter()
+        .find(|w| !["indexed", "memory", "calldata", "storage", "payable"].contains(*w))
+        .copied()
+        .unwrap_or_default();
+
+    let mut value = serde_json::json!({
+        "type": typ,
+        "name": name,
+        "indexed": indexed,
+    });
+    if let Option::Some(components) = components {
+        value["components"] = Value::Array(components);
+    }
+    value
+}
+
+// position of the parenthesis closing the one at `open`
+fn matching_paren(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices().skip_while(|(i, _)| *i < open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Option::Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    Option::None
+}
+
+// recursive model of an abi type, built from an abi input or tuple component
+#[derive(Clone, Debug)]
+enum ParamType {
+    Elementary(String),
+    Array(Box<ParamType>, Option<usize>),
+    Tuple(Vec<(String, ParamType)>),
+}
+
+impl ParamType {
+    fn from_abi(param: &Value) -> ParamType {
+        let typ = param["type"].as_str().unwrap_or_default();
+        ParamType::from_type(typ, param)
+    }
+
+    // `param` carries the `components` used when the innermost type is a tuple
+    fn from_type(typ: &str, param: &Value) -> ParamType {
+        if let Option::Some((elem, len)) = parse_array(typ) {
+            return ParamType::Array(Box::new(ParamType::from_type(elem, param)), len);
+        }
+
+        if typ == "tuple" {
+            let empty: Vec<Value> = Vec::new();
+            let components = param["components"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .map(|c| (c["name"].as_str().unwrap_or_default().to_string(), ParamType::from_abi(c)))
+                .collect();
+            return ParamType::Tuple(components);
+        }
+
+        // tuples spelled out inline carry no component names, e.g. `tuple(uint256[],address)`
+        if let Option::Some(inner) = typ.strip_prefix("tuple").unwrap_or(typ).strip_prefix('(') {
+            if let Option::Some(inner) = inner.strip_suffix(')') {
+                let components = split_components(inner)
+                    .into_iter()
+                    .map(|c| (String::new(), ParamType::from_type(c, &Value::Null)))
+                    .collect();
+                return ParamType::Tuple(components);
+            }
+        }
+
+        ParamType::Elementary(typ.to_string())
+    }
+
+    // the form used in signatures, e.g. `(uint256,address)[]`
+    fn canonical(&self) -> String {
+        match self {
+            ParamType::Elementary(typ) => match typ.as_str() {
+                // aliases are hashed under their explicit width
+                "uint" | "int" => format!("{}256", typ),
+                "fixed" | "ufixed" => format!("{}128x18", typ),
+                _ => typ.clone(),
+            },
+            ParamType::Array(elem, Option::None) => format!("{}[]", elem.canonical()),
+            ParamType::Array(elem, Option::Some(k)) => format!("{}[{}]", elem.canonical(), k),
+            ParamType::Tuple(components) => {
+                let types: Vec<String> = components.iter().map(|(_, c)| c.canonical()).collect();
+                format!("({})", types.join(","))
+            }
+        }
+    }
+
+    // whether the type is encoded in the tail with its head slot holding an offset
+    fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Elementary(typ) => typ == "string" || typ == "bytes",
+            ParamType::Array(_, Option::None) => true,
+            ParamType::Array(elem, Option::Some(_)) => elem.is_dynamic(),
+            ParamType::Tuple(components) => components.iter().any(|(_, c)| c.is_dynamic()),
+        }
+    }
+
+    // number of bytes the type occupies in the head
+    fn head_size(&self) -> usize {
+        if self.is_dynamic() {
+            return 32;
+        }
+        match self {
+            ParamType::Array(elem, Option::Some(k)) => k * elem.head_size(),
+            ParamType::Tuple(components) => components.iter().map(|(_, c)| c.head_size()).sum(),
+            _ => 32,
+        }
+    }
+}
+
+// decode the value of type `param` whose head slot is at `offset`
+// offsets held in dynamic heads are relative to the start of `data`
+fn decode_value(param: &ParamType, data: &[u8], offset: usize) -> Result<Value, ModuleError> {
+    match param {
+        ParamType::Array(elem, len) => {
+            let (region, base, count) = match len {
+                // dynamic array, the tail holds the element count followed by the encoded elements
+                Option::None => {
+                    let start = read_usize(data, offset)?;
+                    (tail(data, start.saturating_add(32))?, 0, read_usize(data, start)?)
+                }
+                // fixed array of dynamic elements, the tail holds the k element heads
+                Option::Some(k) if elem.is_dynamic() => (tail(data, read_usize(data, offset)?)?, 0, *k),
+                // fixed array of static elements, the k elements are encoded inline
+                Option::Some(k) => (data, offset, *k),
+            };
+            let size = elem.head_size();
+            let values = (0..count)
+                .map(|i| decode_value(elem, region, base + i * size))
+                .collect::<Result<Vec<Value>, ModuleError>>()?;
+            Ok(Value::Array(values))
+        }
+        ParamType::Tuple(components) => {
+            // a dynamic tuple is encoded in the tail and its members' offsets are relative to it,
+            // a static tuple is encoded inline
+            let (region, mut cursor) = match param.is_dynamic() {
+                true => (tail(data, read_usize(data, offset)?)?, 0),
+                false => (data, offset),
+            };
+            let mut values = serde_json::Map::new();
+            for (i, (name, component)) in components.iter().enumerate() {
+                values.insert(component_key(i, name), decode_value(component, region, cursor)?);
+                cursor += component.head_size();
+            }
+            Ok(Value::Object(values))
+        }
+        ParamType::Elementary(typ) => match typ.as_str() {
+            "string" => Ok(Value::String(decode_string(data, offset)?)),
+            "bytes" => Ok(Value::String(decode_bytes(data, offset)?)),
+            _ => decode_word(typ, &hex::encode(read_word(data, offset)?)),
+        },
+    }
+}
+
+// the key of a decoded tuple member, its name from the abi `components` or `field<position>` for unnamed members
+fn component_key(index: usize, name: &str) -> String {
+    match name.is_empty() {
+        true => format!("field{}", index),
+        false => name.to_string(),
+    }
+}
+
+// split the inside of an inline tuple on its top-level commas
+// e.g. `uint256,(address,bool)[]` -> [`uint256`, `(address,bool)[]`]
+fn split_components(inner: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !inner[start..].trim().is_empty() {
+        parts.push(inner[start..].trim());
+    }
+    parts
+}
+
+// split an array type into its element type and length, `None` for dynamic arrays
+// e.g. `uint256[3][]` -> (`uint256[3]`, None)
+fn parse_array(typ: &str) -> Option<(&str, Option<usize>)> {
+    let body = typ.strip_suffix(']')?;
+    let open = body.rfind('[')?;
+    let len = &body[open + 1..];
+    if len.is_empty() {
+        return Option::Some((&body[..open], Option::None));
+    }
+    len.parse().ok().map(|k| (&body[..open], Option::Some(k)))
+}
+
+// read the 32-byte slot at `offset`
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8], ModuleError> {
+    offset
+        .checked_add(32)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(ModuleError::DecodeError {
+            reason: format!("the slot at byte {} is out of bounds of {} bytes of data", offset, data.len()),
+        })
+}
+
+// the data from `start` onwards
+fn tail(data: &[u8], start: usize) -> Result<&[u8], ModuleError> {
+    data.get(start..).ok_or(ModuleError::DecodeError {
+        reason: format!("the offset {} is out of bounds of {} bytes of data", start, data.len()),
+    })
+}
+
+// read a 32-byte slot as an offset or length, which must fit the address space
+fn read_usize(data: &[u8], offset: usize) -> Result<usize, ModuleError> {
+    let word = read_word(data, offset)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(&word[24..32]);
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(buf)).map_err(|e| ModuleError::DecodeError { reason: e.to_string() }),
+        false => Err(ModuleError::DecodeError { reason: format!("the slot at byte {} is too large for an offset or length", offset) }),
+    }
+}
+
+// read the payload of a dynamic `string` or `bytes` whose head slot is at `offset`
+// the head holds the tail offset, the tail holds the length followed by the payload
+fn read_dynamic(data: &[u8], offset: usize) -> Result<&[u8], ModuleError> {
+    let start = read_usize(data, offset)?;
+    let len = read_usize(data, start)?;
+    let begin = start.saturating_add(32);
+    begin
+        .checked_add(len)
+        .and_then(|end| data.get(begin..end))
+        .ok_or(ModuleError::DecodeError {
+            reason: format!("the {} byte payload at byte {} is out of bounds of {} bytes of data", len, begin, data.len()),
+        })
+}
+
+fn decode_string(data: &[u8], offset: usize) -> Result<String, ModuleError> {
+    Ok(String::from_utf8_lossy(read_dynamic(data, offset)?).to_string())
+}
+
+fn decode_bytes(data: &[u8], offset: usize) -> Result<String, ModuleError> {
+    Ok(format!("0x{}", hex::encode(read_dynamic(data, offset)?)))
+}
+
+// decode a static elementary type from its 32-byte slot
+fn decode_word(typ: &str, hex_data: &str) -> Result<Value, ModuleError> {
+    let clean = hex_data.trim_start_matches("0x");
+    if clean.len() != 64 || !clean.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ModuleError::DecodeError { reason: format!("`{}` is not a 32-byte hex word", hex_data) });
+    }
+
+    match typ {
+        // an external function is a 20-byte address followed by a 4-byte selector, left-aligned
+        "function" => Ok(serde_json::json!({
+            "address": format!("0x{}", &clean[..40]),
+            "selector": format!("0x{}", &clean[40..48]),
+        })),
+        _ => Ok(Value::String(decode_param(typ, hex_data)?)),
+    }
+}
+
+// decode a static elementary type other than `function` from a 32-byte hex word
+fn decode_param(typ: &str, hex_data: &str) -> Result<String, ModuleError> {
+    let clean = hex_data.trim_start_matches("0x");
+    let invalid = |e: hex::FromHexError| ModuleError::DecodeError { reason: e.to_string() };
+
+    match typ {
+        _ if int_bits(typ, "uint").is_some() => {
+            // uintN is right-aligned in the slot, keep only the low N bits
+            let bits = int_bits(typ, "uint").unwrap_or(256);
+            let low = &clean[clean.len().saturating_sub(bits / 4)..];
+            Ok(U256::from_be_slice(&hex::decode(low).map_err(invalid)?).to_string())
+        }
+        _ if int_bits(typ, "int").is_some() => {
+            // intN is sign-extended to the slot, the low N bits hold the two's complement value
+            let bits = int_bits(typ, "int").unwrap_or(256);
+            let low = &clean[clean.len().saturating_sub(bits / 4)..];
+            Ok(decode_signed(&hex::decode(low).map_err(invalid)?))
+        }
+        _ if parse_fixed(typ).is_some() => {
+            // fixedMxN holds the intM/uintM value v and represents v / 10^N
+            let (signed, bits, decimals) = parse_fixed(typ).unwrap_or((true, 128, 18));
+            let integer = match signed {
+                true => decode_param(&format!("int{}", bits), hex_data)?,
+                false => decode_param(&format!("uint{}", bits), hex_data)?,
+            };
+            Ok(scale_decimal(&integer, decimals))
+        }
+        "address" if clean.len() >= 64 => {
+            let addr = &clean[24..64]; // last 20 bytes (40 hex chars)
+            Ok(format!("0x{}", addr))
+        }
+        "bool" => {
+            let b = clean.ends_with("1");
+            Ok(b.to_string())
+        }
+        _ if bytes_width(typ).is_some() => {
+            // bytesN is left-aligned in the slot, keep only the first N bytes
+            let width = bytes_width(typ).unwrap_or(32);
+            Ok(format!("0x{}", &clean[..(width * 2).min(clean.len())]))
+        }
+        _ => Err(ModuleError::DecodeError { reason: format!("unsupported type: {}", typ) }),
+    }
+}
+
+// width of an `uintN`/`intN` type, 8 to 256 in steps of 8, a bare `uint`/`int` is 256 bits
+fn int_bits(typ: &str, prefix: &str) -> Option<usize> {
+    let width = typ.strip_prefix(prefix)?;
+    if width.is_empty() {
+        return Option::Some(256);
+    }
+    match width.parse::<usize>() {
+        Ok(bits) if (8..=256).contains(&bits) && bits.is_multiple_of(8) => Option::Some(bits),
+        _ => Option::None,
+    }
+}
+
+// signedness, width and decimals of a `fixedMxN`/`ufixedMxN` type
+// M is 8 to 256 in steps of 8 and N is 1 to 80, a bare `fixed`/`ufixed` is `fixed128x18`
+fn parse_fixed(typ: &str) -> Option<(bool, usize, usize)> {
+    let (signed, rest) = match typ.strip_prefix("ufixed") {
+        Option::Some(rest) => (false, rest),
+        Option::None => (true, typ.strip_prefix("fixed")?),
+    };
+    if rest.is_empty() {
+        return Option::Some((signed, 128, 18));
+    }
+
+    let (bits, decimals) = rest.split_once('x')?;
+    let bits: usize = bits.parse().ok()?;
+    let decimals: usize = decimals.parse().ok()?;
+    match (8..=256).contains(&bits) && bits.is_multiple_of(8) && (1..=80).contains(&decimals) {
+        true => Option::Some((signed, bits, decimals)),
+        false => Option::None,
+    }
+}
+
+// shift the decimal point of an integer string `decimals` places to the left,
+// trailing fractional zeros are dropped, e.g. ("-1500", 3) -> "-1.5"
+fn scale_decimal(integer: &str, decimals: usize) -> String {
+    let (sign, digits) = match integer.strip_prefix('-') {
+        Option::Some(digits) => ("-", digits),
+        Option::None => ("", integer),
+    };
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => format!("{}{}", sign, whole),
+        false => format!("{}{}.{}", sign, whole, fraction),
+    }
+}
+
+// width of a fixed `bytesN` type, 1 to 32
+fn bytes_width(typ: &str) -> Option<usize> {
+    match typ.strip_prefix("bytes")?.parse::<usize>() {
+        Ok(width) if (1..=32).contains(&width) => Option::Some(width),
+        _ => Option::None,
+    }
+}
+
+// decode big-endian two's complement bytes into a signed decimal string
+fn decode_signed(bytes: &[u8]) -> String {
+    let negative = bytes.first().map(|b| b & 0x80 != 0).unwrap_or(false);
+    if !negative {
+        return U256::from_be_slice(bytes).to_string();
+    }
+
+    // sign-extend to 256 bits, the magnitude is then the two's complement negation
+    let mut extended = vec![0xffu8; 32usize.saturating_sub(bytes.len())];
+    extended.extend_from_slice(bytes);
+    format!("-{}", U256::from_be_slice(&extended).wrapping_neg())
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // two's complement negation modulo 2^256
+    fn wrapping_neg(self) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut carry = true;
+        for (i, l) in self.0.iter().enumerate() {
+            let (v, overflow) = (!l).overflowing_add(carry as u64);
+            limbs[i] = v;
+            carry = overflow;
+        }
+        U256(limbs)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
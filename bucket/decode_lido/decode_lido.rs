@@ -0,0 +1,488 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    ShareRateFormatError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::ShareRateFormatError { reason } =>
+                write!(f, "The share rate parameters could not be read. Reason: {}", reason),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't lido events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // the token contracts whose transfers are decoded, those of mainnet by default
+    #[serde(default = "steth_default")]
+    pub steth: String,
+    #[serde(default = "wsteth_default")]
+    pub wsteth: String,
+    // the pooled ether and total shares, as decimal strings, to convert with until the stream reaches
+    // its first TokenRebased, amounts are left unconverted before it when they are not given
+    #[serde(default)]
+    pub total_pooled_ether: Option<String>,
+    #[serde(default)]
+    pub total_shares: Option<String>,
+}
+
+fn steth_default() -> String {
+    "0xae7ab96520de3a18e5e111b5eaab095312d7fe84".to_string()
+}
+
+fn wsteth_default() -> String {
+    "0x7f39c581f595b53c5cb19bd0b3f8da6c935e2ca0".to_string()
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            keep_unmatched: false,
+            steth: steth_default(),
+            wsteth: wsteth_default(),
+            total_pooled_ether: Option::None,
+            total_shares: Option::None,
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// the pooled ether and total shares of the latest oracle report, stETH balances are shares valued at
+// their ratio so it changes with every rebase
+static SHARE_RATE: RwLock<Option<(U256, U256)>> = RwLock::new(Option::None);
+
+// keccak256 of the lido events
+// "Submitted(address,uint256,address)", a deposit of ether for stETH
+const SUBMITTED_TOPIC: &str = "0x96a25c8ce0baabc1fdefd93e9ed25d8e092a3332f3aa9a41722b5697231d1d1a";
+// "Transfer(address,address,uint256)" of the stETH and wstETH tokens
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+// "TokenRebased(uint256,uint256,uint256,uint256,uint256,uint256,uint256)", an oracle report
+const TOKEN_REBASED_TOPIC: &str = "0xff08c3ef606d198e316ef5b822193c489965899eb4e3c248cea1a4626c3eda50";
+// "WithdrawalRequested(uint256,address,address,uint256,uint256)" and
+// "WithdrawalClaimed(uint256,address,address,uint256)" of the withdrawal queue
+const WITHDRAWAL_REQUESTED_TOPIC: &str = "0xf0cb471f23fb74ea44b8252eb1881a2dca546288d9f6e90d1a0e82fe0ed342ab";
+const WITHDRAWAL_CLAIMED_TOPIC: &str = "0x6ad26c5e238e7d002799f9a5db07e81ef14e37386ae03496d7a7ef04713e145b";
+
+// the digits the share rate is given with, those of ether
+const SHARE_RATE_PRECISION: usize = 18;
+
+// the fields of the staking schema, those that an event doesn't carry are null
+const FIELDS: &[&str] = &[
+    "eventName", "token", "from", "to", "sender", "referral", "requestId", "requestor", "owner", "receiver",
+    "ethAmount", "stEthAmount", "shares", "shareRate", "reportTimestamp", "totalPooledEther", "totalShares",
+    "sharesMintedAsFees", "contract",
+];
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // the addresses are matched against the lowercased address of the log
+    parameter.steth = parameter.steth.trim().to_lowercase();
+    parameter.wsteth = parameter.wsteth.trim().to_lowercase();
+
+    let rate = match (&parameter.total_pooled_ether, &parameter.total_shares) {
+        (Option::Some(ether), Option::Some(shares)) => {
+            let parse = |n: &str| U256::from_dec_str(n.trim()).ok_or(ModuleError::ShareRateFormatError {
+                reason: format!("`{}` is not a decimal amount", n),
+            });
+            Option::Some((parse(ether)?, parse(shares)?))
+        }
+        (Option::None, Option::None) => Option::None,
+        _ => return Err(ModuleError::ShareRateFormatError {
+            reason: "totalPooledEther and totalShares must be given together".to_string(),
+        }.into()),
+    };
+    *SHARE_RATE.write()? = rate;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input, &params)? {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the staking schema document of a lido event, amounts are given in stETH, which rebases, and in
+// shares, which don't and are what wstETH counts, transfers are converted between the two at the
+// share rate of the latest TokenRebased, whose document updates it, and are left unconverted while
+// the rate is unknown, logs of other events and transfers of other tokens give None
+fn decode_log(input: &HashMap<String, Value>, params: &Parameters) -> Result<Option<HashMap<String, Value>>, Box<dyn Error>> {
+    let topics: Option<Vec<Vec<u8>>> = input
+        .get("topics")
+        .and_then(|t| t.as_array())
+        .map(|t| t.iter().map(|t| t.as_str().map(|t| t.trim().to_lowercase())))
+        .and_then(|t| t.map(|t| hex::decode(t?.strip_prefix("0x")?).ok().filter(|w| w.len() == 32)).collect());
+    let data = input.get("data").and_then(|d| d.as_str()).unwrap_or_default().trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok();
+    let (topics, data) = match topics.zip(data) {
+        Option::Some((topics, data)) if !topics.is_empty() => (topics, data),
+        _ => return Ok(Option::None),
+    };
+    let words: Vec<&[u8]> = data.chunks(32).collect();
+    let contract = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+
+    let mut output: HashMap<String, Value> = FIELDS.iter().map(|key| (key.to_string(), Value::Null)).collect();
+    let mut set = |key: &str, value: Value| output.insert(key.to_string(), value);
+    let rate = *SHARE_RATE.read()?;
+
+    let topic0 = format!("0x{}", hex::encode(&topics[0]));
+    let event_name = match (topic0.as_str(), topics.len(), words.len(), data.len() % 32) {
+        (SUBMITTED_TOPIC, 2, 2, 0) => {
+            let amount = U256::from_be_slice(words[0]);
+            set("token", Value::String("stETH".to_string()));
+            set("sender", address(&topics[1]));
+            set("referral", address(words[1]));
+            set("ethAmount", Value::String(amount.to_string()));
+            set("stEthAmount", Value::String(amount.to_string()));
+            set("shares", amount_json(rate.and_then(|(ether, shares)| convert(amount, shares, ether))));
+            "Submitted"
+        }
+        (TRANSFER_TOPIC, 3, 1, 0) if contract.as_ref() == Option::Some(&params.steth) => {
+            let amount = U256::from_be_slice(words[0]);
+            set("token", Value::String("stETH".to_string()));
+            set("from", address(&topics[1]));
+            set("to", address(&topics[2]));
+            set("stEthAmount", Value::String(amount.to_string()));
+            set("shares", amount_json(rate.and_then(|(ether, shares)| convert(amount, shares, ether))));
+            "Transfer"
+        }
+        (TRANSFER_TOPIC, 3, 1, 0) if contract.as_ref() == Option::Some(&params.wsteth) => {
+            let amount = U256::from_be_slice(words[0]);
+            set("token", Value::String("wstETH".to_string()));
+            set("from", address(&topics[1]));
+            set("to", address(&topics[2]));
+            set("stEthAmount", amount_json(rate.and_then(|(ether, shares)| convert(amount, ether, shares))));
+            set("shares", Value::String(amount.to_string()));
+            "Transfer"
+        }
+        (TOKEN_REBASED_TOPIC, 2, 6, 0) => {
+            let total_shares = U256::from_be_slice(words[3]);
+            let total_ether = U256::from_be_slice(words[4]);
+            set("token", Value::String("stETH".to_string()));
+            set("reportTimestamp", uint(&topics[1]));
+            set("totalShares", Value::String(total_shares.to_string()));
+            set("totalPooledEther", Value::String(total_ether.to_string()));
+            set("sharesMintedAsFees", uint(words[5]));
+            if !total_shares.is_zero() {
+                *SHARE_RATE.write()? = Option::Some((total_ether, total_shares));
+            }
+            "TokenRebased"
+        }
+        // requests lock the stETH of their shares at the rate they were made at
+        (WITHDRAWAL_REQUESTED_TOPIC, 4, 2, 0) => {
+            set("token", Value::String("stETH".to_string()));
+            set("requestId", uint(&topics[1]));
+            set("requestor", address(&topics[2]));
+            set("owner", address(&topics[3]));
+            set("stEthAmount", uint(words[0]));
+            set("shares", uint(words[1]));
+            "WithdrawalRequested"
+        }
+        (WITHDRAWAL_CLAIMED_TOPIC, 4, 1, 0) => {
+            set("requestId", uint(&topics[1]));
+            set("owner", address(&topics[2]));
+            set("receiver", address(&topics[3]));
+            set("ethAmount", uint(words[0]));
+            "WithdrawalClaimed"
+        }
+        _ => return Ok(Option::None),
+    };
+
+    // the share rate in force after the event, in ether per share
+    let rate = *SHARE_RATE.read()?;
+    output.insert("shareRate".to_string(), serde_json::json!(rate.and_then(|(ether, shares)| ratio(ether, shares, SHARE_RATE_PRECISION))));
+    output.insert("eventName".to_string(), Value::String(event_name.to_string()));
+    output.insert("contract".to_string(), serde_json::json!(contract));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Ok(Option::Some(output))
+}
+
+// convert an amount by the ratio `numerator / denominator` rounding down as lido does, from stETH to
+// shares with the total shares over the pooled ether and back with its inverse, None when the ratio
+// is undefined or the product overflows 256 bits
+fn convert(amount: U256, numerator: U256, denominator: U256) -> Option<U256> {
+    match denominator.is_zero() {
+        true => Option::None,
+        false => Option::Some(amount.checked_mul(numerator)?.div_rem_u256(denominator).0),
+    }
+}
+
+// an optional amount as a decimal string or null
+fn amount_json(amount: Option<U256>) -> Value {
+    serde_json::json!(amount.map(|a| a.to_string()))
+}
+
+// the ratio of two amounts as a decimal string truncated to `precision` digits, e.g. "1.18", None when
+// the denominator is zero
+fn ratio(numerator: U256, denominator: U256, precision: usize) -> Option<String> {
+    if denominator.is_zero() {
+        return Option::None;
+    }
+    let (whole, mut remainder) = numerator.div_rem_u256(denominator);
+    // the fractional digits are long division by the denominator one decimal digit at a time
+    let mut fraction = String::new();
+    for _ in 0..precision {
+        if remainder.is_zero() {
+            break;
+        }
+        let (digit, rest) = remainder.checked_mul_u64(10)?.div_rem_u256(denominator);
+        fraction.push_str(&digit.to_string());
+        remainder = rest;
+    }
+    match fraction.trim_end_matches('0') {
+        "" => Option::Some(whole.to_string()),
+        fraction => Option::Some(format!("{}.{}", whole, fraction)),
+    }
+}
+
+// the address in the low 20 bytes of a word
+fn address(word: &[u8]) -> Value {
+    Value::String(format!("0x{}", hex::encode(&word[12..])))
+}
+
+// an unsigned integer word as a decimal string
+fn uint(word: &[u8]) -> Value {
+    Value::String(U256::from_be_slice(word).to_string())
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            n = n.checked_mul_u64(10)?.checked_add_u64(c.to_digit(10)? as u64)?;
+        }
+        Option::Some(n)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    fn checked_mul_u64(self, factor: u64) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let acc = self.0[i] as u128 * factor as u128 + carry;
+            *limb = acc as u64;
+            carry = acc >> 64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_add_u64(self, term: u64) -> Option<U256> {
+        let mut limbs = self.0;
+        let mut carry = term;
+        for limb in limbs.iter_mut() {
+            let (v, overflow) = limb.overflowing_add(carry);
+            *limb = v;
+            carry = overflow as u64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    // schoolbook multiplication, None when the product doesn't fit 256 bits
+    fn checked_mul(self, other: U256) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let acc = self.0[i] as u128 * other.0[j] as u128 + carry + if i + j < 4 { limbs[i + j] as u128 } else { 0 };
+                match i + j < 4 {
+                    true => limbs[i + j] = acc as u64,
+                    false if acc as u64 != 0 => return Option::None,
+                    false => {}
+                }
+                carry = acc >> 64;
+            }
+            if carry != 0 {
+                return Option::None;
+            }
+        }
+        Option::Some(U256(limbs))
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    // binary long division by a nonzero divisor, the remainder is shifted in one bit at a time and
+    // the divisor taken from it whenever it fits, a bit shifted out of the remainder means it does
+    fn div_rem_u256(self, divisor: U256) -> (U256, U256) {
+        let mut quotient = U256::default();
+        let mut remainder = U256::default();
+        for i in (0..256).rev() {
+            let overflow = remainder.0[3] >> 63 == 1;
+            let mut limbs = [0u64; 4];
+            for (j, limb) in limbs.iter_mut().enumerate() {
+                *limb = remainder.0[j] << 1 | if j > 0 { remainder.0[j - 1] >> 63 } else { self.bit(i) as u64 };
+            }
+            remainder = U256(limbs);
+            if overflow || remainder.ge(&divisor) {
+                remainder = remainder.wrapping_sub(divisor);
+                quotient.0[i / 64] |= 1 << (i % 64);
+            }
+        }
+        (quotient, remainder)
+    }
+
+    fn ge(&self, other: &U256) -> bool {
+        for i in (0..4).rev() {
+            if self.0[i] != other.0[i] {
+                return self.0[i] > other.0[i];
+            }
+        }
+        true
+    }
+
+    fn wrapping_sub(self, other: U256) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_sub(other.0[i]);
+            let (v, o2) = v.overflowing_sub(borrow as u64);
+            *limb = v;
+            borrow = o1 || o2;
+        }
+        U256(limbs)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,520 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // only decode the deposit events of this OptimismPortal, each op stack chain has its own
+    #[serde(default)]
+    pub portal: Option<String>,
+    // pass records that are neither deposit events nor deposit transactions through unchanged instead of
+    // dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// TransactionDeposited(address,address,uint256,bytes)
+const TRANSACTION_DEPOSITED: &str = "0xb3813568d9991fc951961fcb4c784893574240a28925604d09fc577c55bb7c32";
+
+// the EIP-2718 type of deposit transactions
+const DEPOSIT_TX_TYPE: u8 = 0x7e;
+
+// the account sending the l1 attributes transaction opening each l2 block, and the L1Block predeploy it calls
+const DEPOSITOR_ACCOUNT: &str = "0xdeaddeaddeaddeaddeaddeaddeaddeaddead0001";
+const L1_BLOCK: &str = "0x4200000000000000000000000000000000000015";
+
+// setL1BlockValues(uint64,uint64,uint256,bytes32,uint64,bytes32,uint256,uint256) until ecotone
+const SET_L1_BLOCK_VALUES: &str = "015d8eb9";
+// setL1BlockValuesEcotone() and setL1BlockValuesIsthmus(), which read their arguments tightly packed
+const SET_L1_BLOCK_VALUES_ECOTONE: &str = "440a5e20";
+const SET_L1_BLOCK_VALUES_ISTHMUS: &str = "098999be";
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    parameter.portal = parameter.portal.map(|p| p.trim().to_lowercase());
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // logs are told apart from transactions by their topics
+    let decoded = match input.contains_key("topics") {
+        true => decode_deposit_event(&input, params.portal.as_deref()),
+        false => decode_deposit_transaction(&input),
+    };
+    let output = match decoded {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fields of a deposit, in the document of the l1 event as in that of the l2 transaction it becomes,
+// so both join on `sourceHash` and `l2TransactionHash`
+const DEPOSIT_FIELDS: &[&str] = &[
+    "from", "to", "mint", "value", "gasLimit", "isCreation", "data", "isSystemTransaction", "sourceHash",
+    "l2TransactionHash", "version", "portal", "l1TransactionHash", "l1BlockHash", "l1BlockNumber", "l1LogIndex",
+];
+
+// the fields of the l1 attributes transaction, those a hardfork doesn't set are null
+const L1_ATTRIBUTES_FIELDS: &[&str] = &[
+    "hardfork", "l1BlockNumber", "l1Timestamp", "l1BaseFee", "l1BlobBaseFee", "l1BlockHash", "sequenceNumber",
+    "batcherHash", "l1FeeOverhead", "l1FeeScalar", "baseFeeScalar", "blobBaseFeeScalar", "operatorFeeScalar",
+    "operatorFeeConstant", "l2TransactionHash", "l2BlockHash", "l2BlockNumber",
+];
+
+// a TransactionDeposited log of the OptimismPortal, None for other logs and those whose opaque data
+// isn't of the version 0 layout
+fn decode_deposit_event(input: &HashMap<String, Value>, portal: Option<&str>) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    if topics.len() != 4 || topics[0] != TRANSACTION_DEPOSITED {
+        return Option::None;
+    }
+    let address = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    if portal.is_some() && address.as_deref() != portal {
+        return Option::None;
+    }
+    let word = |topic: &str| hex::decode(topic.strip_prefix("0x").unwrap_or(topic)).ok().filter(|w| w.len() == 32);
+    let (from, to, version) = (word(&topics[1])?, word(&topics[2])?, word(&topics[3])?);
+    if version.iter().any(|b| *b != 0) {
+        return Option::None;
+    }
+
+    // the data is the abi encoded opaque data, packed as the mint, the value, the gas limit, whether the
+    // deposit creates a contract and the calldata
+    let data = input.get("data")?.as_str()?.trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+    let start = read_usize(&data, 0)?;
+    let length = read_usize(&data, start)?;
+    let opaque = data.get(start.checked_add(32)?..)?.get(..length)?;
+    if opaque.len() < 73 {
+        return Option::None;
+    }
+    let deposit = Deposit {
+        from: from[12..].to_vec(),
+        to: to[12..].to_vec(),
+        mint: opaque[..32].to_vec(),
+        value: opaque[32..64].to_vec(),
+        gas_limit: u64::from_be_bytes(opaque[64..72].try_into().ok()?),
+        is_creation: opaque[72] != 0,
+        data: opaque[73..].to_vec(),
+    };
+
+    let mut output: HashMap<String, Value> = DEPOSIT_FIELDS.iter().map(|key| (key.to_string(), Value::Null)).collect();
+    deposit.insert_into(&mut output);
+    output.insert("isSystemTransaction".to_string(), Value::Bool(false));
+    output.insert("version".to_string(), Value::from(0));
+    output.insert("portal".to_string(), serde_json::json!(address));
+
+    // the l2 transaction hash follows from the source hash, which identifies the deposit by the l1 block
+    // it is in and the index of its log there
+    let block_hash = input.get("blockHash").and_then(|h| h.as_str()).and_then(hash_bytes);
+    let log_index = input.get("logIndex").and_then(quantity);
+    if let (Option::Some(block_hash), Option::Some(log_index)) = (block_hash, log_index) {
+        let source_hash = user_deposit_source_hash(&block_hash, log_index);
+        output.insert("sourceHash".to_string(), Value::String(format!("0x{}", hex::encode(source_hash))));
+        output.insert("l2TransactionHash".to_string(), Value::String(format!("0x{}", hex::encode(deposit.hash(&source_hash)))));
+    }
+    output.insert("l1TransactionHash".to_string(), input.get("transactionHash").cloned().unwrap_or(Value::Null));
+    output.insert("l1BlockHash".to_string(), input.get("blockHash").cloned().unwrap_or(Value::Null));
+    output.insert("l1BlockNumber".to_string(), serde_json::json!(input.get("blockNumber").and_then(quantity)));
+    output.insert("l1LogIndex".to_string(), serde_json::json!(log_index));
+    output.insert("kind".to_string(), Value::String("l1Deposit".to_string()));
+    Option::Some(output)
+}
+
+// a deposit transaction of an l2 block, the l1 attributes transaction opening each block is decoded
+// into the l1 block it was derived from, None for transactions of other types
+fn decode_deposit_transaction(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    if input.get("type").and_then(quantity) != Option::Some(DEPOSIT_TX_TYPE as u64) {
+        return Option::None;
+    }
+    let address = |key: &str| input.get(key).and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    let calldata = input.get("input").or(input.get("data")).and_then(|d| d.as_str()).unwrap_or_default().trim().to_lowercase();
+    let calldata = hex::decode(calldata.strip_prefix("0x").unwrap_or(&calldata)).ok()?;
+
+    if address("from").as_deref() == Option::Some(DEPOSITOR_ACCOUNT) && address("to").as_deref() == Option::Some(L1_BLOCK) {
+        if let Option::Some(mut output) = decode_l1_attributes(&calldata) {
+            output.insert("l2TransactionHash".to_string(), input.get("hash").cloned().unwrap_or(Value::Null));
+            output.insert("l2BlockHash".to_string(), input.get("blockHash").cloned().unwrap_or(Value::Null));
+            output.insert("l2BlockNumber".to_string(), serde_json::json!(input.get("blockNumber").and_then(quantity)));
+            output.insert("kind".to_string(), Value::String("l1Attributes".to_string()));
+            return Option::Some(output);
+        }
+    }
+
+    // deposits carry no l1 context of their own but the source hash linking them to their l1 event
+    let mut output: HashMap<String, Value> = DEPOSIT_FIELDS.iter().map(|key| (key.to_string(), Value::Null)).collect();
+    let to = address("to");
+    output.insert("from".to_string(), serde_json::json!(address("from")));
+    output.insert("to".to_string(), serde_json::json!(to));
+    output.insert("isCreation".to_string(), Value::Bool(to.is_none()));
+    for (key, field) in [("mint", "mint"), ("value", "value")] {
+        let amount = input.get(key).and_then(|v| v.as_str()).and_then(quantity_bytes).unwrap_or_default();
+        output.insert(field.to_string(), Value::String(U256::from_be_slice(&amount).to_string()));
+    }
+    output.insert("gasLimit".to_string(), serde_json::json!(input.get("gas").and_then(quantity)));
+    output.insert("data".to_string(), Value::String(format!("0x{}", hex::encode(&calldata))));
+    output.insert("isSystemTransaction".to_string(), Value::Bool(input.get("isSystemTx").and_then(|v| v.as_bool()).unwrap_or(false)));
+    output.insert("sourceHash".to_string(), input.get("sourceHash").cloned().unwrap_or(Value::Null));
+    output.insert("l2TransactionHash".to_string(), input.get("hash").cloned().unwrap_or(Value::Null));
+    output.insert("kind".to_string(), Value::String("l2Deposit".to_string()));
+    Option::Some(output)
+}
+
+// the calldata of the l1 attributes transaction, abi encoded until ecotone and packed from ecotone on,
+// isthmus appends the operator fee
+fn decode_l1_attributes(calldata: &[u8]) -> Option<HashMap<String, Value>> {
+    let mut output: HashMap<String, Value> = L1_ATTRIBUTES_FIELDS.iter().map(|key| (key.to_string(), Value::Null)).collect();
+    let selector = hex::encode(calldata.get(..4)?);
+    let args = &calldata[4..];
+    let uint = |bytes: &[u8]| Value::String(U256::from_be_slice(bytes).to_string());
+    let number = |bytes: &[u8]| Value::from(U256::from_be_slice(bytes).low_u64());
+    let hash = |bytes: &[u8]| Value::String(format!("0x{}", hex::encode(bytes)));
+
+    match selector.as_str() {
+        SET_L1_BLOCK_VALUES => {
+            let word = |i: usize| args.get(i * 32..i * 32 + 32);
+            output.insert("hardfork".to_string(), Value::String("bedrock".to_string()));
+            output.insert("l1BlockNumber".to_string(), number(word(0)?));
+            output.insert("l1Timestamp".to_string(), number(word(1)?));
+            output.insert("l1BaseFee".to_string(), uint(word(2)?));
+            output.insert("l1BlockHash".to_string(), hash(word(3)?));
+            output.insert("sequenceNumber".to_string(), number(word(4)?));
+            output.insert("batcherHash".to_string(), hash(word(5)?));
+            output.insert("l1FeeOverhead".to_string(), uint(word(6)?));
+            output.insert("l1FeeScalar".to_string(), uint(word(7)?));
+        }
+        SET_L1_BLOCK_VALUES_ECOTONE | SET_L1_BLOCK_VALUES_ISTHMUS => {
+            let isthmus = selector == SET_L1_BLOCK_VALUES_ISTHMUS;
+            if args.len() < if isthmus { 172 } else { 160 } {
+                return Option::None;
+            }
+            output.insert("hardfork".to_string(), Value::String(if isthmus { "isthmus" } else { "ecotone" }.to_string()));
+            output.insert("baseFeeScalar".to_string(), number(&args[0..4]));
+            output.insert("blobBaseFeeScalar".to_string(), number(&args[4..8]));
+            output.insert("sequenceNumber".to_string(), number(&args[8..16]));
+            output.insert("l1Timestamp".to_string(), number(&args[16..24]));
+            output.insert("l1BlockNumber".to_string(), number(&args[24..32]));
+            output.insert("l1BaseFee".to_string(), uint(&args[32..64]));
+            output.insert("l1BlobBaseFee".to_string(), uint(&args[64..96]));
+            output.insert("l1BlockHash".to_string(), hash(&args[96..128]));
+            output.insert("batcherHash".to_string(), hash(&args[128..160]));
+            if isthmus {
+                output.insert("operatorFeeScalar".to_string(), number(&args[160..164]));
+                output.insert("operatorFeeConstant".to_string(), uint(&args[164..172]));
+            }
+        }
+        _ => return Option::None,
+    }
+    Option::Some(output)
+}
+
+// a user deposit as the portal emits it, amounts are 32-byte big-endian words
+struct Deposit {
+    from: Vec<u8>,
+    to: Vec<u8>,
+    mint: Vec<u8>,
+    value: Vec<u8>,
+    gas_limit: u64,
+    is_creation: bool,
+    data: Vec<u8>,
+}
+
+impl Deposit {
+    fn insert_into(&self, output: &mut HashMap<String, Value>) {
+        output.insert("from".to_string(), Value::String(format!("0x{}", hex::encode(&self.from))));
+        // contract creations emit the zero address as their recipient
+        output.insert("to".to_string(), match self.is_creation {
+            true => Value::Null,
+            false => Value::String(format!("0x{}", hex::encode(&self.to))),
+        });
+        output.insert("mint".to_string(), Value::String(U256::from_be_slice(&self.mint).to_string()));
+        output.insert("value".to_string(), Value::String(U256::from_be_slice(&self.value).to_string()));
+        output.insert("gasLimit".to_string(), Value::from(self.gas_limit));
+        output.insert("isCreation".to_string(), Value::Bool(self.is_creation));
+        output.insert("data".to_string(), Value::String(format!("0x{}", hex::encode(&self.data))));
+    }
+
+    // the hash of the deposit transaction the deposit becomes on l2, keccak256(0x7e || rlp([sourceHash,
+    // from, to, mint, value, gas, isSystemTx, data])) where creations have an empty recipient
+    fn hash(&self, source_hash: &[u8]) -> [u8; 32] {
+        let to: &[u8] = match self.is_creation {
+            true => &[],
+            false => &self.to,
+        };
+        let fields = [
+            rlp_bytes(source_hash),
+            rlp_bytes(&self.from),
+            rlp_bytes(to),
+            rlp_bytes(strip_zeros(&self.mint)),
+            rlp_bytes(strip_zeros(&self.value)),
+            rlp_bytes(strip_zeros(&self.gas_limit.to_be_bytes())),
+            rlp_bytes(&[]),
+            rlp_bytes(&self.data),
+        ];
+        let mut encoded = vec![DEPOSIT_TX_TYPE];
+        encoded.extend(rlp_list(&fields.concat()));
+        Keccak256::digest(&encoded).into()
+    }
+}
+
+// the source hash of a user deposit, keccak256(bytes32(0) || keccak256(l1BlockHash || bytes32(logIndex)))
+fn user_deposit_source_hash(block_hash: &[u8], log_index: u64) -> [u8; 32] {
+    let mut deposit_id = block_hash.to_vec();
+    deposit_id.extend([0u8; 24]);
+    deposit_id.extend(log_index.to_be_bytes());
+    let mut preimage = vec![0u8; 32];
+    preimage.extend(Keccak256::digest(&deposit_id));
+    Keccak256::digest(&preimage).into()
+}
+
+// the rlp encoding of a byte string, integers are their big-endian bytes without leading zeros
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    match bytes {
+        [b] if *b < 0x80 => vec![*b],
+        _ => {
+            let mut encoded = rlp_length(bytes.len(), 0x80);
+            encoded.extend(bytes);
+            encoded
+        }
+    }
+}
+
+// the rlp encoding of a list of already encoded items
+fn rlp_list(items: &[u8]) -> Vec<u8> {
+    let mut encoded = rlp_length(items.len(), 0xc0);
+    encoded.extend(items);
+    encoded
+}
+
+// the rlp prefix of a payload, short payloads add their length to the offset, longer ones are
+// followed by the big-endian bytes of their length
+fn rlp_length(length: usize, offset: u8) -> Vec<u8> {
+    match length < 56 {
+        true => vec![offset + length as u8],
+        false => {
+            let bytes = (length as u64).to_be_bytes();
+            let bytes = strip_zeros(&bytes);
+            let mut encoded = vec![offset + 55 + bytes.len() as u8];
+            encoded.extend(bytes);
+            encoded
+        }
+    }
+}
+
+fn strip_zeros(bytes: &[u8]) -> &[u8] {
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    &bytes[zeros..]
+}
+
+// a 0x-prefixed 32-byte hash
+fn hash_bytes(hash: &str) -> Option<Vec<u8>> {
+    let hash = hash.trim().to_lowercase();
+    hex::decode(hash.strip_prefix("0x").unwrap_or(&hash)).ok().filter(|h| h.len() == 32)
+}
+
+// the big-endian bytes of a 0x-prefixed hex quantity
+fn quantity_bytes(value: &str) -> Option<Vec<u8>> {
+    let digits = value.trim().strip_prefix("0x")?;
+    // quantities are encoded without leading zeros so their digit count may be odd
+    let padded = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+    hex::decode(padded).ok().filter(|b| b.len() <= 32)
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => u64::from_str_radix(digits, 16).ok(),
+            Option::None => s.trim().parse::<u64>().ok(),
+        },
+        _ => Option::None,
+    }
+}
+
+// a word of the data read as an offset or length, None when it is out of bounds or too large
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = data.get(offset..offset.checked_add(32)?)?;
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(word[24..].try_into().ok()?)).ok(),
+        false => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rlp_encodes_the_spec_examples() {
+        assert_eq!(rlp_bytes(b"dog"), b"\x83dog");
+        assert_eq!(rlp_bytes(&[]), [0x80]);
+        assert_eq!(rlp_bytes(&[0x0f]), [0x0f]);
+        assert_eq!(rlp_bytes(&[0x80]), [0x81, 0x80]);
+        assert_eq!(rlp_list(&[rlp_bytes(b"cat"), rlp_bytes(b"dog")].concat()), b"\xc8\x83cat\x83dog");
+        assert_eq!(rlp_list(&[]), [0xc0]);
+
+        let lorem = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        assert_eq!(rlp_bytes(lorem), [&[0xb8, 0x38][..], &lorem[..]].concat());
+        assert_eq!(rlp_length(1024, 0x80), [0xb9, 0x04, 0x00]);
+    }
+
+    #[test]
+    fn quantities_are_read_from_numbers_and_hex_or_decimal_strings() {
+        assert_eq!(quantity_bytes("0x1"), Option::Some(vec![0x01]));
+        assert_eq!(quantity_bytes("0x100"), Option::Some(vec![0x01, 0x00]));
+        assert_eq!(quantity_bytes("100"), Option::None);
+        assert_eq!(quantity_bytes(&format!("0x1{}", "0".repeat(64))), Option::None);
+        assert_eq!(quantity(&Value::from("0x1a")), Option::Some(26));
+        assert_eq!(quantity(&Value::from("26")), Option::Some(26));
+        assert_eq!(quantity(&Value::from(26)), Option::Some(26));
+    }
+}
@@ -0,0 +1,297 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // emit the receipt's logs after it, one record per log, and drop them from the receipt
+    #[serde(default)]
+    pub explode_logs: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    let gas_used = input.get("gasUsed").and_then(quantity);
+    let effective_gas_price = input.get("effectiveGasPrice").and_then(quantity);
+    let base_fee = input.get("baseFeePerGas").and_then(quantity);
+
+    // status is 1 for success and 0 for failure since byzantium, earlier receipts carry a state root instead
+    let status = input.get("status").and_then(quantity).map(|s| s.low_u64());
+    input.insert("status".to_string(), serde_json::json!(status));
+    input.insert("success".to_string(), serde_json::json!(status.map(|s| s == 1)));
+
+    for key in ["blockNumber", "transactionIndex", "type", "gasUsed", "cumulativeGasUsed", "blobGasUsed"] {
+        if let Option::Some(n) = input.get(key).and_then(quantity) {
+            input.insert(key.to_string(), number(n));
+        }
+    }
+    for key in ["effectiveGasPrice", "blobGasPrice", "baseFeePerGas"] {
+        if let Option::Some(n) = input.get(key).and_then(quantity) {
+            input.insert(key.to_string(), Value::String(n.to_string()));
+        }
+    }
+
+    // the fee paid is the gas used at the effective price, since london the base fee part of it is
+    // burnt and the rest is the priority fee paid to the block producer, receipts don't carry the
+    // base fee so it is only split when the record has been joined with its block's `baseFeePerGas`
+    let fee = gas_used.zip(effective_gas_price).and_then(|(gas, price)| price.checked_mul_u64(gas.low_u64()));
+    let burnt_fee = gas_used.zip(base_fee).and_then(|(gas, base)| base.checked_mul_u64(gas.low_u64()));
+    let priority_fee = fee.zip(burnt_fee).and_then(|(fee, burnt)| fee.checked_sub(burnt));
+    input.insert("fee".to_string(), serde_json::json!(fee.map(|n| n.to_string())));
+    input.insert("burntFee".to_string(), serde_json::json!(burnt_fee.map(|n| n.to_string())));
+    input.insert("priorityFee".to_string(), serde_json::json!(priority_fee.map(|n| n.to_string())));
+
+    // the blob gas of EIP-4844 transactions is paid on top and burnt in full
+    let blob_fee = input.get("blobGasUsed").and_then(quantity)
+        .zip(input.get("blobGasPrice").and_then(quantity))
+        .and_then(|(gas, price)| price.checked_mul_u64(gas.low_u64()));
+    if let Option::Some(blob_fee) = blob_fee {
+        input.insert("blobFee".to_string(), Value::String(blob_fee.to_string()));
+    }
+
+    // the decoded receipt is emitted first so its status and fees aren't lost, its logs follow it
+    if params.explode_logs {
+        let logs = match input.remove("logs") {
+            Option::Some(Value::Array(logs)) => logs,
+            _ => Vec::new(),
+        };
+        let logs = logs
+            .iter()
+            .map(serde_json::to_vec)
+            .collect::<Result<VecDeque<Vec<u8>>, serde_json::Error>>()?;
+        PENDING.write()?.extend(logs);
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// a quantity small enough to be a json number, e.g. gas used, larger ones are decimal strings
+fn number(n: U256) -> Value {
+    match n.0[1..].iter().all(|l| *l == 0) {
+        true => Value::from(n.low_u64()),
+        false => Value::String(n.to_string()),
+    }
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from_u64),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => {
+                // quantities are encoded without leading zeros so their digit count may be odd
+                let padded = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+                match padded.len() <= 64 {
+                    true => hex::decode(padded).ok().map(|bytes| U256::from_be_slice(&bytes)),
+                    false => Option::None,
+                }
+            }
+            Option::None => U256::from_dec_str(s.trim()),
+        },
+        _ => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    fn from_u64(n: u64) -> U256 {
+        U256([n, 0, 0, 0])
+    }
+
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            n = n.checked_mul_u64(10)?.checked_add_u64(c.to_digit(10)? as u64)?;
+        }
+        Option::Some(n)
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    fn checked_mul_u64(self, factor: u64) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let acc = self.0[i] as u128 * factor as u128 + carry;
+            *limb = acc as u64;
+            carry = acc >> 64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_add_u64(self, term: u64) -> Option<U256> {
+        let mut limbs = self.0;
+        let mut carry = term;
+        for limb in limbs.iter_mut() {
+            let (v, overflow) = limb.overflowing_add(carry);
+            *limb = v;
+            carry = overflow as u64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_sub(self, other: U256) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_sub(other.0[i]);
+            let (v, o2) = v.overflowing_sub(borrow as u64);
+            *limb = v;
+            borrow = o1 || o2;
+        }
+        match borrow {
+            false => Option::Some(U256(limbs)),
+            true => Option::None,
+        }
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
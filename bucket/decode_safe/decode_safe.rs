@@ -0,0 +1,314 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't safe events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// the kinds of argument the safe events carry
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    Address,
+    Bytes32,
+    Uint,
+    AddressArray,
+}
+
+// the names and kinds of an event's arguments
+type Arguments = &'static [(&'static str, Kind)];
+
+// the safe events by topic with their arguments in declaration order, safe versions differ in which
+// arguments they index but always index the leading ones, so the first arguments are read from as many
+// topics as the log has and the rest from its data
+const EVENTS: &[(&str, &str, Arguments)] = &[
+    // ExecutionSuccess(bytes32,uint256)
+    ("0x442e715f626346e8c54381002da614f62bee8d27386535b2521ec8540898556e", "ExecutionSuccess",
+        &[("txHash", Kind::Bytes32), ("payment", Kind::Uint)]),
+    // ExecutionFailure(bytes32,uint256)
+    ("0x23428b18acfb3ea64b08dc0c1d296ea9c09702c09083ca5272e64d115b687d23", "ExecutionFailure",
+        &[("txHash", Kind::Bytes32), ("payment", Kind::Uint)]),
+    // SafeSetup(address,address[],uint256,address,address)
+    ("0x141df868a6331af528e38c83b7aa03edc19be66e37ae67f9285bf4f8e3c6a1a8", "SafeSetup",
+        &[("initiator", Kind::Address), ("owners", Kind::AddressArray), ("threshold", Kind::Uint),
+          ("initializer", Kind::Address), ("fallbackHandler", Kind::Address)]),
+    // AddedOwner(address)
+    ("0x9465fa0c962cc76958e6373a993326400c1c94f8be2fe3a952adfa7f60b2ea26", "AddedOwner",
+        &[("owner", Kind::Address)]),
+    // RemovedOwner(address)
+    ("0xf8d49fc529812e9a7c5c50e69c20f0dccc0db8fa95c98bc58cc9a4f1c1299eaf", "RemovedOwner",
+        &[("owner", Kind::Address)]),
+    // ChangedThreshold(uint256)
+    ("0x610f7ff2b304ae8903c3de74c60c6ab1f7d6226b3f52c5161905bb5ad4039c93", "ChangedThreshold",
+        &[("threshold", Kind::Uint)]),
+    // EnabledModule(address)
+    ("0xecdf3a3effea5783a3c4c2140e677577666428d44ed9d474a0b3a4c9943f8440", "EnabledModule",
+        &[("module", Kind::Address)]),
+    // DisabledModule(address)
+    ("0xaab4fa2b463f581b2b32cb3b7e3b704b9ce37cc209b5fb4d77e593ace4054276", "DisabledModule",
+        &[("module", Kind::Address)]),
+    // ExecutionFromModuleSuccess(address)
+    ("0x6895c13664aa4f67288b25d7a21d7aaa34916e355fb9b6fae0a139a9085becb8", "ExecutionFromModuleSuccess",
+        &[("module", Kind::Address)]),
+    // ExecutionFromModuleFailure(address)
+    ("0xacd2c8702804128fdb0db2bb49f6d127dd0181c13fd45dbfe16de0930e2bd375", "ExecutionFromModuleFailure",
+        &[("module", Kind::Address)]),
+    // ChangedGuard(address)
+    ("0x1151116914515bc0891ff9047a6cb32cf902546f83066499bcf8ba33d2353fa2", "ChangedGuard",
+        &[("guard", Kind::Address)]),
+    // ChangedModuleGuard(address)
+    ("0xcd1966d6be16bc0c030cc741a06c6e0efaf8d00de2c8b6a9e11827e125de8bb8", "ChangedModuleGuard",
+        &[("guard", Kind::Address)]),
+    // ChangedFallbackHandler(address)
+    ("0x5ac6c46c93c8d0e53714ba3b53db3e7c046da994313d7ed0d192028bc7c228b0", "ChangedFallbackHandler",
+        &[("handler", Kind::Address)]),
+    // SafeReceived(address,uint256)
+    ("0x3d0ce9bfc3ed7d6862dbb28b2dea94561fe714a1b4d019aa8af39730d1ad7c3d", "SafeReceived",
+        &[("sender", Kind::Address), ("value", Kind::Uint)]),
+    // ApproveHash(bytes32,address)
+    ("0xf2a0eb156472d1440255b0d7c1e19cc07115d1051fe605b0dce69acfec884d9c", "ApproveHash",
+        &[("approvedHash", Kind::Bytes32), ("owner", Kind::Address)]),
+    // SignMsg(bytes32)
+    ("0xe7f4675038f4f6034dfcbbb24c4dc08e4ebf10eb9d257d3d02c0f38d122ac6e4", "SignMsg",
+        &[("msgHash", Kind::Bytes32)]),
+];
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fixed schema document of a safe event, `success` tells executions that succeeded from those
+// that failed, fields an event doesn't carry are null, logs of other events and those whose topics and
+// data don't add up to the event's arguments give None
+fn decode_log(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    let data = input.get("data")?.as_str()?.trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+
+    let (_, event_name, args) = EVENTS
+        .iter()
+        .find(|(topic, _, _)| Option::Some(*topic) == topics.first().map(|t| t.as_str()))?;
+    // indexed arrays are hashed into their topic and can't be read back
+    let indexed = topics.len() - 1;
+    if indexed > args.len() || args[..indexed].iter().any(|(_, kind)| *kind == Kind::AddressArray) {
+        return Option::None;
+    }
+
+    let mut output: HashMap<String, Value> = [
+        "txHash", "payment", "initiator", "owners", "threshold", "initializer", "fallbackHandler", "owner",
+        "module", "guard", "handler", "sender", "value", "approvedHash", "msgHash", "success",
+    ]
+    .iter()
+    .map(|key| (key.to_string(), Value::Null))
+    .collect();
+
+    let mut head = 0;
+    for (i, (key, kind)) in args.iter().enumerate() {
+        let value = match i < indexed {
+            true => decode_word(*kind, &hex::decode(topics[i + 1].strip_prefix("0x")?).ok()?)?,
+            false => {
+                let value = match kind {
+                    Kind::AddressArray => Value::Array(read_address_array(&data, head)?),
+                    _ => decode_word(*kind, data.get(head..head + 32)?)?,
+                };
+                head += 32;
+                value
+            }
+        };
+        output.insert(key.to_string(), value);
+    }
+
+    if event_name.starts_with("Execution") {
+        output.insert("success".to_string(), Value::Bool(event_name.ends_with("Success")));
+    }
+    output.insert("eventName".to_string(), Value::String(event_name.to_string()));
+    let safe = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("safe".to_string(), serde_json::json!(safe));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the value of a single-word argument
+fn decode_word(kind: Kind, word: &[u8]) -> Option<Value> {
+    if word.len() != 32 {
+        return Option::None;
+    }
+    Option::Some(Value::String(match kind {
+        Kind::Address => format!("0x{}", hex::encode(&word[12..])),
+        Kind::Bytes32 => format!("0x{}", hex::encode(word)),
+        Kind::Uint => U256::from_be_slice(word).to_string(),
+        Kind::AddressArray => return Option::None,
+    }))
+}
+
+// the address[] whose head slot is at `offset`
+fn read_address_array(data: &[u8], offset: usize) -> Option<Vec<Value>> {
+    let start = read_usize(data, offset)?;
+    let len = read_usize(data, start)?;
+    let elements = data.get(start.checked_add(32)?..)?.get(..len.checked_mul(32)?)?;
+    elements.chunks(32).map(|word| decode_word(Kind::Address, word)).collect()
+}
+
+// a word of the data read as an offset or length, None when it is out of bounds or too large
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = data.get(offset..offset.checked_add(32)?)?;
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(word[24..].try_into().ok()?)).ok(),
+        false => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,372 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't seaport fulfillments through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// keccak256("OrderFulfilled(bytes32,address,address,address,(uint8,address,uint256,uint256)[],
+// (uint8,address,uint256,uint256,address)[])"), the same for every seaport version
+const ORDER_FULFILLED_TOPIC: &str = "0x9d9af8e38d66c62e2c12f0225249fd9d721c54b83f48d9352c97c6cacdcb6f31";
+
+// the seaport item types by their index, the criteria types are fulfilled with a concrete identifier
+const ITEM_TYPES: [&str; 6] = ["native", "erc20", "erc721", "erc1155", "erc721WithCriteria", "erc1155WithCriteria"];
+
+// the fields of the sale schema, those that don't apply to an order are null
+const FIELDS: &[&str] = &[
+    "eventName", "orderHash", "offerer", "zone", "recipient", "offer", "consideration", "saleType", "buyer",
+    "seller", "nfts", "nftContract", "tokenId", "paymentToken", "price", "fees", "feeTotal", "sellerProceeds",
+    "contract",
+];
+
+// an offer or consideration item, offered items have no recipient
+struct Item {
+    item_type: u8,
+    token: String,
+    identifier: U256,
+    amount: U256,
+    recipient: Option<String>,
+}
+
+impl Item {
+    fn is_nft(&self) -> bool {
+        self.item_type >= 2
+    }
+
+    fn to_value(&self) -> Value {
+        let mut item = serde_json::json!({
+            "itemType": self.item_type,
+            "itemTypeName": ITEM_TYPES.get(self.item_type as usize),
+            "token": self.token,
+            "identifier": self.identifier.to_string(),
+            "amount": self.amount.to_string(),
+        });
+        if let Option::Some(recipient) = &self.recipient {
+            item["recipient"] = Value::String(recipient.clone());
+        }
+        item
+    }
+}
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the sale schema document of an OrderFulfilled log, the offerer of a listing sells the nfts it offers
+// for the payment items of the consideration, most of which go to the offerer and the rest are fees,
+// the offerer of an accepted bid buys the nfts of the consideration for the payment it offers, the fees
+// among the consideration are taken from it, orders trading only nfts or only payment have no sale
+// fields, logs of other events give None
+fn decode_log(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    if topics.len() != 3 || topics[0] != ORDER_FULFILLED_TOPIC {
+        return Option::None;
+    }
+    let data = input.get("data").and_then(|d| d.as_str()).unwrap_or_default().trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+
+    // the data holds the order hash, the recipient and the offsets of the offer and consideration,
+    // arrays of static tuples laid out in place after their length
+    let order_hash = data.get(..32)?;
+    let recipient = word_address(data.get(32..64)?);
+    let offer = read_items(&data, read_usize(&data, 64)?, false)?;
+    let consideration = read_items(&data, read_usize(&data, 96)?, true)?;
+    let offerer = topic_address(&topics[1])?;
+
+    let mut output: HashMap<String, Value> = FIELDS.iter().map(|key| (key.to_string(), Value::Null)).collect();
+    output.insert("eventName".to_string(), Value::String("OrderFulfilled".to_string()));
+    output.insert("orderHash".to_string(), Value::String(format!("0x{}", hex::encode(order_hash))));
+    output.insert("offerer".to_string(), Value::String(offerer.clone()));
+    output.insert("zone".to_string(), Value::String(topic_address(&topics[2])?));
+    output.insert("recipient".to_string(), Value::String(recipient.clone()));
+    output.insert("offer".to_string(), Value::Array(offer.iter().map(Item::to_value).collect()));
+    output.insert("consideration".to_string(), Value::Array(consideration.iter().map(Item::to_value).collect()));
+    let contract = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("contract".to_string(), serde_json::json!(contract));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+
+    let listing = offer.iter().any(Item::is_nft) && offer.iter().all(Item::is_nft);
+    let bid = !offer.is_empty() && !offer.iter().any(Item::is_nft) && consideration.iter().any(Item::is_nft);
+    let (nfts, payments, fees, seller, buyer): (Vec<&Item>, Vec<&Item>, Vec<&Item>, String, String) = match (listing, bid) {
+        (true, _) => (
+            offer.iter().collect(),
+            consideration.iter().filter(|i| !i.is_nft()).collect(),
+            consideration.iter().filter(|i| !i.is_nft() && i.recipient.as_ref() != Option::Some(&offerer)).collect(),
+            offerer.clone(),
+            recipient,
+        ),
+        (_, true) => (
+            consideration.iter().filter(|i| i.is_nft()).collect(),
+            offer.iter().collect(),
+            consideration.iter().filter(|i| !i.is_nft()).collect(),
+            recipient,
+            offerer.clone(),
+        ),
+        _ => return Option::Some(output),
+    };
+
+    output.insert("saleType".to_string(), Value::String(if listing { "listing" } else { "bid" }.to_string()));
+    output.insert("seller".to_string(), Value::String(seller.clone()));
+    output.insert("buyer".to_string(), Value::String(buyer));
+    output.insert("nfts".to_string(), Value::Array(nfts.iter().map(|i| i.to_value()).collect()));
+    if let Option::Some(nft) = nfts.first() {
+        output.insert("nftContract".to_string(), Value::String(nft.token.clone()));
+        output.insert("tokenId".to_string(), Value::String(nft.identifier.to_string()));
+    }
+    output.insert("fees".to_string(), Value::Array(fees.iter().map(|i| serde_json::json!({
+        "recipient": i.recipient,
+        "token": i.token,
+        "amount": i.amount.to_string(),
+    })).collect()));
+
+    // an order paid in several tokens has no single price, native payment is the zero address
+    let token = payments.first().map(|i| i.token.clone());
+    if token.is_none() || payments.iter().chain(fees.iter()).any(|i| Option::Some(&i.token) != token.as_ref()) {
+        return Option::Some(output);
+    }
+    let sum = |items: &[&Item]| items.iter().try_fold(U256::default(), |acc, i| acc.checked_add(i.amount));
+    let price = sum(&payments);
+    let fee_total = sum(&fees);
+    output.insert("paymentToken".to_string(), serde_json::json!(token));
+    output.insert("price".to_string(), serde_json::json!(price.map(|p| p.to_string())));
+    output.insert("feeTotal".to_string(), serde_json::json!(fee_total.map(|f| f.to_string())));
+    let proceeds = price.zip(fee_total).and_then(|(price, fees)| price.checked_sub(fees));
+    output.insert("sellerProceeds".to_string(), serde_json::json!(proceeds.map(|p| p.to_string())));
+    Option::Some(output)
+}
+
+// the items of the array whose length is at `offset`, spent items are 4 words and received items add
+// their recipient as a fifth
+fn read_items(data: &[u8], offset: usize, received: bool) -> Option<Vec<Item>> {
+    let length = read_usize(data, offset)?;
+    let size = if received { 160 } else { 128 };
+    let items = data.get(offset.checked_add(32)?..)?.get(..length.checked_mul(size)?)?;
+    items
+        .chunks(size)
+        .map(|item| {
+            let item_type = item[31];
+            if item[..31].iter().any(|b| *b != 0) || item_type as usize >= ITEM_TYPES.len() {
+                return Option::None;
+            }
+            Option::Some(Item {
+                item_type,
+                token: word_address(&item[32..64]),
+                identifier: U256::from_be_slice(&item[64..96]),
+                amount: U256::from_be_slice(&item[96..128]),
+                recipient: match received {
+                    true => Option::Some(word_address(&item[128..160])),
+                    false => Option::None,
+                },
+            })
+        })
+        .collect()
+}
+
+// the address in the low 20 bytes of a data word
+fn word_address(word: &[u8]) -> String {
+    format!("0x{}", hex::encode(&word[12..]))
+}
+
+// a word of the data read as an offset or length, None when it is out of bounds or too large
+fn read_usize(data: &[u8], offset: usize) -> Option<usize> {
+    let word = data.get(offset..offset.checked_add(32)?)?;
+    match word[..24].iter().all(|b| *b == 0) {
+        true => usize::try_from(u64::from_be_bytes(word[24..].try_into().ok()?)).ok(),
+        false => Option::None,
+    }
+}
+
+// the address in the low 20 bytes of an indexed topic
+fn topic_address(topic: &str) -> Option<String> {
+    let word = topic.strip_prefix("0x").unwrap_or(topic);
+    match word.len() == 64 && word.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Option::Some(format!("0x{}", &word[24..])),
+        false => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn checked_add(self, other: U256) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_add(other.0[i]);
+            let (v, o2) = v.overflowing_add(carry as u64);
+            *limb = v;
+            carry = o1 || o2;
+        }
+        match carry {
+            false => Option::Some(U256(limbs)),
+            true => Option::None,
+        }
+    }
+
+    fn checked_sub(self, other: U256) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_sub(other.0[i]);
+            let (v, o2) = v.overflowing_sub(borrow as u64);
+            *limb = v;
+            borrow = o1 || o2;
+        }
+        match borrow {
+            false => Option::Some(U256(limbs)),
+            true => Option::None,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
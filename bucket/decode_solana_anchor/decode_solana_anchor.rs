@@ -0,0 +1,603 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+// the variants keep the `Error` suffix that ParametersNotSetError gives the errors of every lens
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    IdlFormatError{reason: String},
+    DecodeError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::IdlFormatError { reason } =>
+                write!(f, "The idl parameter could not be read. Reason: {}", reason),
+            ModuleError::DecodeError { reason } =>
+                write!(f, "The event could not be decoded. Reason: {}", reason),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // an anchor idl, or that idl encoded in a string, in the format of anchor 0.30 or of earlier versions
+    pub idl: Value,
+    // the program whose events are decoded, the `address` of the idl when left out
+    #[serde(default)]
+    pub program_id: Option<String>,
+    // pass transactions without events of the program through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // the events keyed by their hex discriminator and the types they are made of, built by set_param
+    #[serde(skip)]
+    pub events: HashMap<String, (String, Value)>,
+    #[serde(skip)]
+    pub types: HashMap<String, Value>,
+}
+
+// shared so that reading the parameters for each record doesn't copy the idl
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+// the little-endian sha256("anchor:event")[..8] prefixing the data of the self-cpi emit_cpi! makes
+const EVENT_IX_TAG_LE: [u8; 8] = [0xe4, 0x45, 0xa5, 0x2e, 0x51, 0xcb, 0x9a, 0x1d];
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+// the borsh encoding nested types are read to at most, deeper values are taken for malformed data
+const MAX_DEPTH: usize = 32;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    if let Value::String(document) = &parameter.idl {
+        parameter.idl = serde_json::from_str::<Value>(document)
+            .map_err(|e| ModuleError::IdlFormatError { reason: e.to_string() })?;
+    }
+    let idl = parameter.idl.clone();
+    if !idl.is_object() {
+        return Err(ModuleError::IdlFormatError { reason: "expected a json object".to_string() }.into());
+    }
+
+    let empty: Vec<Value> = Vec::new();
+    parameter.types = idl["types"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|t| Option::Some((t["name"].as_str()?.to_string(), t["type"].clone())))
+        .collect();
+
+    // anchor 0.30 idls list each event's discriminator and declare its fields among the types, earlier
+    // idls declare the fields with the event and leave the discriminator to sha256("event:<name>")
+    for event in idl["events"].as_array().unwrap_or(&empty).iter() {
+        let name = event["name"].as_str().unwrap_or_default().to_string();
+        let discriminator = match event["discriminator"].as_array() {
+            Option::Some(bytes) => bytes.iter().filter_map(|b| b.as_u64().map(|b| b as u8)).collect(),
+            Option::None => sha256(format!("event:{}", name).as_bytes())[..8].to_vec(),
+        };
+        if discriminator.len() != 8 {
+            return Err(ModuleError::IdlFormatError { reason: format!("the discriminator of {} is not 8 bytes", name) }.into());
+        }
+        let definition = match event.get("fields") {
+            Option::Some(fields) => serde_json::json!({ "kind": "struct", "fields": fields }),
+            Option::None => parameter.types.get(&name).cloned().ok_or(ModuleError::IdlFormatError {
+                reason: format!("the event {} has no fields nor a type of its name", name),
+            })?,
+        };
+        parameter.events.insert(hex::encode(discriminator), (name, definition));
+    }
+
+    parameter.program_id = parameter.program_id
+        .clone()
+        .or(idl["address"].as_str().map(|a| a.to_string()))
+        .or(idl["metadata"]["address"].as_str().map(|a| a.to_string()));
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(Arc::new(parameter));
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    let meta = input.get("meta").unwrap_or(&Value::Null);
+    let mut payloads = log_events(meta, params.program_id.as_deref());
+    payloads.extend(cpi_events(&input, meta, params.program_id.as_deref()));
+
+    let mut documents = VecDeque::new();
+    for (source, program_id, data) in payloads.iter() {
+        // data of events the idl doesn't declare, e.g. of other programs emitting through the same
+        // instruction, is skipped
+        let (name, definition) = match data.get(..8).and_then(|d| params.events.get(&hex::encode(d))) {
+            Option::Some(event) => event,
+            Option::None => continue,
+        };
+
+        let mut document = serde_json::Map::new();
+        document.insert("eventName".to_string(), Value::String(name.clone()));
+        document.insert("eventIndex".to_string(), Value::from(documents.len()));
+        document.insert("source".to_string(), Value::String(source.to_string()));
+        document.insert("programId".to_string(), serde_json::json!(program_id));
+        let mut pos = 8;
+        match decode_type(&params, definition, data, &mut pos, 0) {
+            Ok(fields) => {
+                document.insert("args".to_string(), fields);
+            }
+            Err(e) => {
+                document.insert("args".to_string(), Value::Null);
+                document.insert("decodeError".to_string(), Value::String(e.to_string()));
+            }
+        }
+        let signature = input.get("transaction").and_then(|t| t["signatures"].get(0)).cloned();
+        document.insert("signature".to_string(), signature.unwrap_or(Value::Null));
+        document.insert("slot".to_string(), input.get("slot").cloned().unwrap_or(Value::Null));
+        document.insert("blockTime".to_string(), input.get("blockTime").cloned().unwrap_or(Value::Null));
+        documents.push_back(serde_json::to_vec(&document)?);
+    }
+
+    if documents.is_empty() && params.keep_unmatched {
+        documents.push_back(serde_json::to_vec(&input)?);
+    }
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    let first = documents.pop_front();
+    PENDING.write()?.extend(documents);
+    match first {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
+
+// the events emit! logs as base64 `Program data:` lines, attributed to the program the invocation
+// stack of the logs is in at that point
+fn log_events(meta: &Value, program_id: Option<&str>) -> Vec<(&'static str, Option<String>, Vec<u8>)> {
+    let mut stack: Vec<String> = Vec::new();
+    let mut events = Vec::new();
+    let empty: Vec<Value> = Vec::new();
+    for line in meta["logMessages"].as_array().unwrap_or(&empty).iter().filter_map(|l| l.as_str()) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["Program", id, "invoke", ..] => stack.push(id.to_string()),
+            ["Program", id, "success"] | ["Program", id, "failed:", ..] if stack.last().map(|s| s.as_str()) == Option::Some(*id) => {
+                stack.pop();
+            }
+            ["Program", "data:", data @ ..] => {
+                let emitter = stack.last().cloned();
+                if program_id.is_some() && emitter.as_deref() != program_id {
+                    continue;
+                }
+                // the data of one call is logged as several base64 strings, the event is the first
+                if let Option::Some(bytes) = data.first().and_then(|d| base64_decode(d)) {
+                    events.push(("log", emitter, bytes));
+                }
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+// the events emit_cpi! makes the program send to itself as inner instructions, base58 encoded
+fn cpi_events(input: &HashMap<String, Value>, meta: &Value, program_id: Option<&str>) -> Vec<(&'static str, Option<String>, Vec<u8>)> {
+    // the program of an instruction is an index into the account keys, followed by the addresses
+    // v0 transactions load from lookup tables, jsonParsed transactions name the program instead
+    let message = input.get("transaction").map(|t| &t["message"]).unwrap_or(&Value::Null);
+    let empty: Vec<Value> = Vec::new();
+    let mut keys: Vec<String> = message["accountKeys"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|k| k.as_str().or(k["pubkey"].as_str()).map(|k| k.to_string()))
+        .collect();
+    for loaded in ["writable", "readonly"] {
+        keys.extend(meta["loadedAddresses"][loaded].as_array().unwrap_or(&empty).iter().filter_map(|k| k.as_str().map(|k| k.to_string())));
+    }
+
+    let mut events = Vec::new();
+    for group in meta["innerInstructions"].as_array().unwrap_or(&empty).iter() {
+        for instruction in group["instructions"].as_array().unwrap_or(&empty).iter() {
+            let program = instruction["programId"]
+                .as_str()
+                .map(|p| p.to_string())
+                .or(instruction["programIdIndex"].as_u64().and_then(|i| keys.get(i as usize).cloned()));
+            if program_id.is_some() && program.as_deref() != program_id {
+                continue;
+            }
+            let data = instruction["data"].as_str().and_then(base58_decode).unwrap_or_default();
+            if let Option::Some(event) = data.strip_prefix(&EVENT_IX_TAG_LE[..]) {
+                events.push(("cpi", program, event.to_vec()));
+            }
+        }
+    }
+    events
+}
+
+// decode a borsh encoded value of an idl type, starting at `pos` which is moved past it
+fn decode_type(params: &Parameters, typ: &Value, data: &[u8], pos: &mut usize, depth: usize) -> Result<Value, ModuleError> {
+    if depth > MAX_DEPTH {
+        return Err(ModuleError::DecodeError { reason: "the value is nested too deeply".to_string() });
+    }
+    let mut take = |n: usize| -> Result<&[u8], ModuleError> {
+        let bytes = data.get(*pos..*pos + n).ok_or(ModuleError::DecodeError {
+            reason: format!("expected {} more bytes at offset {}, the data is {} bytes", n, *pos, data.len()),
+        })?;
+        *pos += n;
+        Ok(bytes)
+    };
+
+    match typ {
+        Value::String(name) => match name.as_str() {
+            "bool" => Ok(Value::Bool(take(1)?[0] != 0)),
+            "u8" => Ok(Value::from(take(1)?[0])),
+            "i8" => Ok(Value::from(take(1)?[0] as i8)),
+            "u16" => Ok(Value::from(u16::from_le_bytes(array(take(2)?)))),
+            "i16" => Ok(Value::from(i16::from_le_bytes(array(take(2)?)))),
+            "u32" => Ok(Value::from(u32::from_le_bytes(array(take(4)?)))),
+            "i32" => Ok(Value::from(i32::from_le_bytes(array(take(4)?)))),
+            "f32" => Ok(serde_json::json!(f32::from_le_bytes(array(take(4)?)))),
+            "f64" => Ok(serde_json::json!(f64::from_le_bytes(array(take(8)?)))),
+            // 64 and 128-bit integers exceed what json numbers hold exactly and are decimal strings
+            "u64" => Ok(Value::String(u64::from_le_bytes(array(take(8)?)).to_string())),
+            "i64" => Ok(Value::String(i64::from_le_bytes(array(take(8)?)).to_string())),
+            "u128" => Ok(Value::String(u128::from_le_bytes(array(take(16)?)).to_string())),
+            "i128" => Ok(Value::String(i128::from_le_bytes(array(take(16)?)).to_string())),
+            "publicKey" | "pubkey" => Ok(Value::String(base58(take(32)?))),
+            "string" => {
+                let length = u32::from_le_bytes(array(take(4)?)) as usize;
+                Ok(Value::String(String::from_utf8_lossy(take(length)?).into_owned()))
+            }
+            "bytes" => {
+                let length = u32::from_le_bytes(array(take(4)?)) as usize;
+                Ok(Value::String(format!("0x{}", hex::encode(take(length)?))))
+            }
+            other => Err(ModuleError::DecodeError { reason: format!("the type {} is not supported", other) }),
+        },
+        Value::Object(compound) => {
+            if let Option::Some(inner) = compound.get("vec") {
+                let length = u32::from_le_bytes(array(take(4)?)) as usize;
+                return (0..length).map(|_| decode_type(params, inner, data, pos, depth + 1)).collect::<Result<Vec<Value>, ModuleError>>().map(Value::Array);
+            }
+            if let Option::Some(inner) = compound.get("option").or(compound.get("coption")) {
+                // borsh tags an option with a byte while the solana COption tag is a u32
+                let size = match compound.contains_key("coption") {
+                    true => 4,
+                    false => 1,
+                };
+                return match take(size)?.iter().any(|b| *b != 0) {
+                    true => decode_type(params, inner, data, pos, depth + 1),
+                    false => Ok(Value::Null),
+                };
+            }
+            if let Option::Some(Value::Array(spec)) = compound.get("array") {
+                let length = spec.get(1).and_then(|n| n.as_u64()).unwrap_or(0) as usize;
+                let inner = spec.first().unwrap_or(&Value::Null);
+                return (0..length).map(|_| decode_type(params, inner, data, pos, depth + 1)).collect::<Result<Vec<Value>, ModuleError>>().map(Value::Array);
+            }
+            if let Option::Some(defined) = compound.get("defined") {
+                // anchor 0.30 names the defined type in an object, earlier versions directly
+                let name = defined.as_str().or(defined["name"].as_str()).unwrap_or_default();
+                let definition = params.types.get(name).ok_or(ModuleError::DecodeError {
+                    reason: format!("the type {} is not defined by the idl", name),
+                })?;
+                return decode_type(params, definition, data, pos, depth + 1);
+            }
+            match compound.get("kind").and_then(|k| k.as_str()) {
+                Option::Some("struct") => decode_fields(params, &compound["fields"], data, pos, depth),
+                // an enum is its variant index as a byte followed by the fields of that variant,
+                // variants without fields are their name
+                Option::Some("enum") => {
+                    let index = take(1)?[0] as usize;
+                    let variant = compound["variants"].get(index).ok_or(ModuleError::DecodeError {
+                        reason: format!("the enum has no variant {}", index),
+                    })?;
+                    let name = variant["name"].clone();
+                    match variant.get("fields") {
+                        Option::Some(fields) => Ok(serde_json::json!({
+                            "variant": name,
+                            "fields": decode_fields(params, fields, data, pos, depth)?,
+                        })),
+                        Option::None => Ok(name),
+                    }
+                }
+                // a type alias names the type it stands for
+                Option::Some("type") => decode_type(params, &compound["alias"], data, pos, depth + 1),
+                _ => Err(ModuleError::DecodeError { reason: format!("the type {} is not supported", typ) }),
+            }
+        }
+        _ => Err(ModuleError::DecodeError { reason: format!("the type {} is not supported", typ) }),
+    }
+}
+
+// decode the fields of a struct or enum variant, named fields make an object and the unnamed fields
+// of tuple structs an array
+fn decode_fields(params: &Parameters, fields: &Value, data: &[u8], pos: &mut usize, depth: usize) -> Result<Value, ModuleError> {
+    let empty: Vec<Value> = Vec::new();
+    let fields = fields.as_array().unwrap_or(&empty);
+    match fields.iter().all(|f| f.get("name").is_some()) && !fields.is_empty() {
+        true => {
+            let mut object = serde_json::Map::new();
+            for field in fields.iter() {
+                let value = decode_type(params, &field["type"], data, pos, depth + 1)?;
+                object.insert(field["name"].as_str().unwrap_or_default().to_string(), value);
+            }
+            Ok(Value::Object(object))
+        }
+        false => fields
+            .iter()
+            .map(|typ| decode_type(params, typ.get("type").unwrap_or(typ), data, pos, depth + 1))
+            .collect::<Result<Vec<Value>, ModuleError>>()
+            .map(Value::Array),
+    }
+}
+
+// copy a slice of the expected length into an array for the from_le_bytes constructors
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut out = [0u8; N];
+    out.copy_from_slice(bytes);
+    out
+}
+
+// decode standard base64 with optional padding, none if the input holds another character
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim().trim_end_matches('=');
+    let mut bytes = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for c in encoded.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Option::None,
+        };
+        acc = acc << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    // a single leftover character can't hold a whole byte
+    match encoded.len() % 4 {
+        1 => Option::None,
+        _ => Option::Some(bytes),
+    }
+}
+
+// base58 with the bitcoin alphabet, each leading zero byte is kept as a leading '1'
+fn base58(bytes: &[u8]) -> String {
+    let mut digits: Vec<u8> = Vec::new();
+    for b in bytes {
+        let mut carry = *b as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    let mut out = "1".repeat(zeros);
+    out.extend(digits.iter().rev().map(|d| BASE58_ALPHABET[*d as usize] as char));
+    out
+}
+
+// decode base58 with the bitcoin alphabet, each leading '1' is a leading zero byte
+fn base58_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in encoded.trim().bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|a| *a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = encoded.trim().bytes().take_while(|c| *c == b'1').count();
+    let mut out = vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Option::Some(out)
+}
+
+// sha256 as specified by FIPS 180-4, anchor derives the discriminators of older idls with it
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+    ];
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+
+    // the message is padded with a one bit, zeros and its bit length to whole 64-byte blocks
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend(((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([block[4 * i], block[4 * i + 1], block[4 * i + 2], block[4 * i + 3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 32];
+    for (i, s) in state.iter().enumerate() {
+        digest[4 * i..4 * i + 4].copy_from_slice(&s.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(types: Value) -> Parameters {
+        let types = types.as_object().unwrap().iter().map(|(name, typ)| (name.clone(), typ.clone())).collect();
+        Parameters { idl: Value::Null, program_id: Option::None, keep_unmatched: false, events: HashMap::new(), types }
+    }
+
+    #[test]
+    fn sha256_matches_the_fips_180_vectors() {
+        let digest = |data: &[u8]| hex::encode(sha256(data));
+        assert_eq!(digest(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(digest(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        // 56 bytes leave no room for the length in the first block so the padding takes a second one
+        assert_eq!(
+            digest(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        );
+    }
+
+    #[test]
+    fn discriminators_are_the_leading_bytes_of_the_hashed_names() {
+        assert_eq!(hex::encode(&sha256(b"event:Swap")[..8]), "516ce3becdd00ac4");
+        // the self-cpi tag is anchor's u64 EVENT_IX_TAG, the hash's leading bytes read big-endian
+        let mut tag = sha256(b"anchor:event")[..8].to_vec();
+        tag.reverse();
+        assert_eq!(tag, EVENT_IX_TAG_LE);
+    }
+
+    #[test]
+    fn decode_type_reads_borsh_structs_options_vecs_and_enums() {
+        let params = params(serde_json::json!({ "Side": { "kind": "enum", "variants": [{ "name": "Buy" }, { "name": "Sell" }] } }));
+        let event = serde_json::json!({ "kind": "struct", "fields": [
+            { "name": "amount", "type": "u64" },
+            { "name": "owner", "type": "pubkey" },
+            { "name": "memo", "type": "string" },
+            { "name": "fee", "type": { "option": "u8" } },
+            { "name": "path", "type": { "vec": "u16" } },
+            { "name": "side", "type": { "defined": { "name": "Side" } } },
+        ] });
+        let data = [
+            1_000_000u64.to_le_bytes().to_vec(),
+            vec![0u8; 32],
+            vec![2, 0, 0, 0, b'h', b'i'],
+            vec![1, 5],
+            vec![2, 0, 0, 0, 1, 0, 1, 2],
+            vec![1],
+        ].concat();
+
+        let mut pos = 0;
+        assert_eq!(decode_type(&params, &event, &data, &mut pos, 0).unwrap(), serde_json::json!({
+            "amount": "1000000",
+            "owner": "11111111111111111111111111111111",
+            "memo": "hi",
+            "fee": 5,
+            "path": [1, 513],
+            "side": "Sell",
+        }));
+        assert_eq!(pos, data.len());
+
+        let mut pos = 0;
+        assert!(decode_type(&params, &event, &data[..data.len() - 1], &mut pos, 0).is_err());
+    }
+}
@@ -0,0 +1,726 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::cmp::Ordering;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // derive `from` from the signature when the transaction doesn't carry it
+    #[serde(default = "recover_sender_default")]
+    pub recover_sender: bool,
+}
+
+fn recover_sender_default() -> bool {
+    true
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    let tx_type = transaction_type(&input);
+    let to = input.get("to").and_then(|v| v.as_str()).filter(|to| !to.is_empty()).map(|to| to.to_lowercase());
+
+    // the sender is only derived when the source didn't deliver it, recovering it is the costly part
+    let delivered = input.get("from").and_then(|v| v.as_str()).map(|from| from.to_lowercase());
+    let recovered = match (&delivered, params.recover_sender) {
+        (Option::None, true) => recover_sender(&input, tx_type),
+        _ => Option::None,
+    };
+
+    // type 0 and 1 transactions pay `gasPrice`, type 2 and later bid `maxFeePerGas` and `maxPriorityFeePerGas`
+    // and the `gasPrice` reported for them once mined is the effective price
+    let (gas_price, effective_gas_price) = match tx_type {
+        0 | 1 => (amount(&input, "gasPrice"), Value::Null),
+        _ => (Value::Null, amount(&input, "gasPrice")),
+    };
+
+    let mut output: HashMap<String, Value> = HashMap::new();
+    output.insert("hash".to_string(), input.get("hash").cloned().unwrap_or_default());
+    output.insert("blockHash".to_string(), input.get("blockHash").cloned().unwrap_or_default());
+    output.insert("blockNumber".to_string(), number(&input, "blockNumber"));
+    output.insert("transactionIndex".to_string(), number(&input, "transactionIndex"));
+    output.insert("type".to_string(), Value::from(tx_type));
+    output.insert("chainId".to_string(), chain_id(&input, tx_type));
+    output.insert("nonce".to_string(), number(&input, "nonce"));
+    output.insert("from".to_string(), serde_json::json!(delivered.or(recovered.clone())));
+    output.insert("senderRecovered".to_string(), Value::Bool(recovered.is_some()));
+    output.insert("to".to_string(), serde_json::json!(to));
+    output.insert("contractCreation".to_string(), Value::Bool(to.is_none()));
+    output.insert("value".to_string(), amount(&input, "value"));
+    output.insert("gas".to_string(), number(&input, "gas"));
+    output.insert("gasPrice".to_string(), gas_price);
+    output.insert("effectiveGasPrice".to_string(), effective_gas_price);
+    output.insert("maxFeePerGas".to_string(), amount(&input, "maxFeePerGas"));
+    output.insert("maxPriorityFeePerGas".to_string(), amount(&input, "maxPriorityFeePerGas"));
+    output.insert("maxFeePerBlobGas".to_string(), amount(&input, "maxFeePerBlobGas"));
+    output.insert("blobVersionedHashes".to_string(), input.get("blobVersionedHashes").cloned().unwrap_or_default());
+    output.insert("accessList".to_string(), input.get("accessList").cloned().unwrap_or_default());
+    output.insert("input".to_string(), input.get("input").or(input.get("data")).cloned().unwrap_or_default());
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the EIP-2718 type of a transaction, inferred from its fee and blob fields when it has no `type`
+fn transaction_type(input: &HashMap<String, Value>) -> u64 {
+    if let Option::Some(tx_type) = input.get("type").and_then(quantity) {
+        return tx_type.low_u64();
+    }
+    match () {
+        _ if input.contains_key("maxFeePerBlobGas") || input.contains_key("blobVersionedHashes") => 3,
+        _ if input.contains_key("maxFeePerGas") => 2,
+        _ if input.contains_key("accessList") => 1,
+        _ => 0,
+    }
+}
+
+// a quantity small enough to be a json number, e.g. a nonce or gas limit, larger ones are decimal strings
+fn number(input: &HashMap<String, Value>, key: &str) -> Value {
+    match input.get(key).and_then(quantity) {
+        Option::Some(n) if n.bits() <= 64 => Value::from(n.low_u64()),
+        Option::Some(n) => Value::String(n.to_string()),
+        Option::None => Value::Null,
+    }
+}
+
+// the chain a transaction is replay protected on, a legacy transaction signed after EIP-155 carries no
+// `chainId` and has it in `v` = 35 + 2 * chainId + parity instead, one signed before it has none
+fn chain_id(input: &HashMap<String, Value>, tx_type: u64) -> Value {
+    match number(input, "chainId") {
+        Value::Null if tx_type == 0 => match input.get("v").and_then(quantity) {
+            Option::Some(v) if v.bits() <= 64 && v.low_u64() >= 35 => Value::from((v.low_u64() - 35) / 2),
+            _ => Value::Null,
+        },
+        chain_id => chain_id,
+    }
+}
+
+// a wei amount as a decimal string
+fn amount(input: &HashMap<String, Value>, key: &str) -> Value {
+    match input.get(key).and_then(quantity) {
+        Option::Some(n) => Value::String(n.to_string()),
+        Option::None => Value::Null,
+    }
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from_u64),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => {
+                // quantities are encoded without leading zeros so their digit count may be odd
+                let padded = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+                match padded.len() <= 64 {
+                    true => hex::decode(padded).ok().map(|bytes| U256::from_be_slice(&bytes)),
+                    false => Option::None,
+                }
+            }
+            Option::None => U256::from_dec_str(s.trim()),
+        },
+        _ => Option::None,
+    }
+}
+
+// read a 0x-prefixed hex string as bytes
+fn hex_bytes(value: Option<&Value>) -> Option<Vec<u8>> {
+    let s = value?.as_str()?.trim();
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).ok()
+}
+
+// recover the sender of a signed transaction from its signature over the signing hash
+fn recover_sender(input: &HashMap<String, Value>, tx_type: u64) -> Option<String> {
+    let r = input.get("r").and_then(quantity)?;
+    let s = input.get("s").and_then(quantity)?;
+    let v = input.get("yParity").or(input.get("v")).and_then(quantity)?.low_u64();
+
+    let field = |key: &str| input.get(key).and_then(quantity).map(|n| rlp_bytes(&n.to_be_trimmed()));
+    let to = rlp_bytes(&hex_bytes(input.get("to")).unwrap_or_default());
+    let data = rlp_bytes(&hex_bytes(input.get("input").or(input.get("data")))?);
+    let access_list = rlp_access_list(input.get("accessList"))?;
+
+    let (payload, recovery_id) = match tx_type {
+        0 => {
+            let mut items = vec![field("nonce")?, field("gasPrice")?, field("gas")?, to, field("value")?, data];
+            // EIP-155 signatures commit to the chain id, `v` is then 35 + 2 * chainId + parity
+            let recovery_id = match v {
+                27 | 28 => v - 27,
+                _ if v >= 35 => {
+                    items.push(rlp_bytes(&U256::from_u64((v - 35) / 2).to_be_trimmed()));
+                    items.push(rlp_bytes(&[]));
+                    items.push(rlp_bytes(&[]));
+                    (v - 35) % 2
+                }
+                _ => return Option::None,
+            };
+            (rlp_list(&items), recovery_id)
+        }
+        1 => {
+            let items = vec![
+                field("chainId")?, field("nonce")?, field("gasPrice")?, field("gas")?, to, field("value")?, data,
+                access_list,
+            ];
+            ([vec![0x01], rlp_list(&items)].concat(), v)
+        }
+        2 => {
+            let items = vec![
+                field("chainId")?, field("nonce")?, field("maxPriorityFeePerGas")?, field("maxFeePerGas")?,
+                field("gas")?, to, field("value")?, data, access_list,
+            ];
+            ([vec![0x02], rlp_list(&items)].concat(), v)
+        }
+        3 => {
+            let empty: Vec<Value> = Vec::new();
+            let blob_hashes = input.get("blobVersionedHashes").and_then(|v| v.as_array()).unwrap_or(&empty)
+                .iter()
+                .map(|hash| hex_bytes(Option::Some(hash)).map(|hash| rlp_bytes(&hash)))
+                .collect::<Option<Vec<Vec<u8>>>>()?;
+            let items = vec![
+                field("chainId")?, field("nonce")?, field("maxPriorityFeePerGas")?, field("maxFeePerGas")?,
+                field("gas")?, to, field("value")?, data, access_list, field("maxFeePerBlobGas")?,
+                rlp_list(&blob_hashes),
+            ];
+            ([vec![0x03], rlp_list(&items)].concat(), v)
+        }
+        // later types sign over fields this lens doesn't model
+        _ => return Option::None,
+    };
+
+    let mut hasher = Keccak256::new();
+    hasher.update(&payload);
+    let hash = U256::from_be_slice(&hasher.finalize());
+
+    let public_key = recover_public_key(hash, r, s, recovery_id)?;
+    let mut hasher = Keccak256::new();
+    hasher.update(public_key);
+    Option::Some(format!("0x{}", hex::encode(&hasher.finalize()[12..])))
+}
+
+// rlp encoding of an access list, `[[address, [storageKey, ...]], ...]`
+fn rlp_access_list(access_list: Option<&Value>) -> Option<Vec<u8>> {
+    let empty: Vec<Value> = Vec::new();
+    let entries = access_list.and_then(|v| v.as_array()).unwrap_or(&empty);
+    let items = entries
+        .iter()
+        .map(|entry| {
+            let address = rlp_bytes(&hex_bytes(entry.get("address"))?);
+            let keys = entry["storageKeys"].as_array().unwrap_or(&empty)
+                .iter()
+                .map(|key| hex_bytes(Option::Some(key)).map(|key| rlp_bytes(&key)))
+                .collect::<Option<Vec<Vec<u8>>>>()?;
+            Option::Some(rlp_list(&[address, rlp_list(&keys)]))
+        })
+        .collect::<Option<Vec<Vec<u8>>>>()?;
+    Option::Some(rlp_list(&items))
+}
+
+// rlp encoding of a byte string, a single byte below 0x80 is its own encoding
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    match bytes {
+        [b] if *b < 0x80 => vec![*b],
+        _ => [rlp_length(bytes.len(), 0x80), bytes.to_vec()].concat(),
+    }
+}
+
+// rlp encoding of a list of already encoded items
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    [rlp_length(payload.len(), 0xc0), payload].concat()
+}
+
+// the rlp length prefix, short payloads add their length to `offset` and longer ones are
+// prefixed by the big-endian length bytes
+fn rlp_length(len: usize, offset: u8) -> Vec<u8> {
+    if len <= 55 {
+        return vec![offset + len as u8];
+    }
+    let bytes = len.to_be_bytes();
+    let trimmed: Vec<u8> = bytes.iter().skip_while(|b| **b == 0).copied().collect();
+    [vec![offset + 55 + trimmed.len() as u8], trimmed].concat()
+}
+
+// secp256k1, y^2 = x^3 + 7 over the field of the prime P with the generator G of order N
+const P: Modulus = Modulus {
+    m: U256([0xfffffffefffffc2f, 0xffffffffffffffff, 0xffffffffffffffff, 0xffffffffffffffff]),
+    c: U256([0x1000003d1, 0, 0, 0]),
+};
+const N: Modulus = Modulus {
+    m: U256([0xbfd25e8cd0364141, 0xbaaedce6af48a03b, 0xfffffffffffffffe, 0xffffffffffffffff]),
+    c: U256([0x402da1732fc9bebf, 0x4551231950b75fc4, 0x1, 0]),
+};
+const G: Point = Point {
+    x: U256([0x59f2815b16f81798, 0x029bfcdb2dce28d9, 0x55a06295ce870b07, 0x79be667ef9dcbbac]),
+    y: U256([0x9c47d08ffb10d4b8, 0xfd17b448a6855419, 0x5da4fbfc0e1108a8, 0x483ada7726a3c465]),
+    z: U256([1, 0, 0, 0]),
+};
+// (P + 1) / 4, as P = 3 mod 4 a square root of a is a^((P + 1) / 4)
+const SQRT_EXPONENT: U256 = U256([0xffffffffbfffff0c, 0xffffffffffffffff, 0xffffffffffffffff, 0x3fffffffffffffff]);
+
+// recover the 64-byte uncompressed public key (without its 0x04 prefix) that signed `hash`,
+// Q = r^-1 (sR - eG) where R is the curve point with x = r and the parity given by the recovery id
+fn recover_public_key(hash: U256, r: U256, s: U256, recovery_id: u64) -> Option<[u8; 64]> {
+    if r.is_zero() || s.is_zero() || r >= N.m || s >= N.m {
+        return Option::None;
+    }
+
+    let alpha = P.add(P.mul(P.mul(r, r), r), U256::from_u64(7));
+    let beta = P.pow(alpha, SQRT_EXPONENT);
+    if P.mul(beta, beta) != alpha {
+        return Option::None;
+    }
+    let y = match (beta.0[0] & 1) == (recovery_id & 1) {
+        true => beta,
+        false => P.sub(U256::ZERO, beta),
+    };
+    let point_r = Point { x: r, y, z: U256::from_u64(1) };
+
+    let e = N.reduce_word(hash);
+    let r_inv = N.inv(r);
+    let u1 = N.sub(U256::ZERO, N.mul(e, r_inv));
+    let u2 = N.mul(s, r_inv);
+    let (x, y) = G.mul(u1).add(&point_r.mul(u2)).to_affine()?;
+
+    let mut public_key = [0u8; 64];
+    public_key[..32].copy_from_slice(&x.to_be_bytes());
+    public_key[32..].copy_from_slice(&y.to_be_bytes());
+    Option::Some(public_key)
+}
+
+// arithmetic modulo m = 2^256 - c, which holds for both the secp256k1 field prime and group order
+#[derive(Clone, Copy, Debug)]
+struct Modulus {
+    m: U256,
+    c: U256,
+}
+
+impl Modulus {
+    fn add(&self, a: U256, b: U256) -> U256 {
+        let (sum, carry) = a.overflowing_add(b);
+        match carry || sum >= self.m {
+            true => sum.overflowing_sub(self.m).0,
+            false => sum,
+        }
+    }
+
+    fn sub(&self, a: U256, b: U256) -> U256 {
+        let (difference, borrow) = a.overflowing_sub(b);
+        match borrow {
+            true => difference.overflowing_add(self.m).0,
+            false => difference,
+        }
+    }
+
+    fn mul(&self, a: U256, b: U256) -> U256 {
+        self.reduce(a.widening_mul(b))
+    }
+
+    // as 2^256 = c (mod m) the high half of a product folds into the low half as high * c
+    fn reduce(&self, wide: [u64; 8]) -> U256 {
+        let mut wide = wide;
+        while wide[4..].iter().any(|l| *l != 0) {
+            let high = U256([wide[4], wide[5], wide[6], wide[7]]);
+            let folded = high.widening_mul(self.c);
+            let mut carry = 0u128;
+            for i in 0..8 {
+                let low = if i < 4 { wide[i] as u128 } else { 0 };
+                let acc = low + folded[i] as u128 + carry;
+                wide[i] = acc as u64;
+                carry = acc >> 64;
+            }
+        }
+        self.reduce_word(U256([wide[0], wide[1], wide[2], wide[3]]))
+    }
+
+    // m exceeds 2^255, so a single subtraction reduces any 256-bit value
+    fn reduce_word(&self, a: U256) -> U256 {
+        match a >= self.m {
+            true => a.overflowing_sub(self.m).0,
+            false => a,
+        }
+    }
+
+    fn pow(&self, base: U256, exponent: U256) -> U256 {
+        let mut result = U256::from_u64(1);
+        for i in (0..256).rev() {
+            result = self.mul(result, result);
+            if exponent.bit(i) {
+                result = self.mul(result, base);
+            }
+        }
+        result
+    }
+
+    // by Fermat's little theorem, as m is prime
+    fn inv(&self, a: U256) -> U256 {
+        self.pow(a, self.m.overflowing_sub(U256::from_u64(2)).0)
+    }
+}
+
+// a curve point in jacobian coordinates (X / Z^2, Y / Z^3), Z = 0 is the point at infinity
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+}
+
+impl Point {
+    const INFINITY: Point = Point { x: U256::ZERO, y: U256::ZERO, z: U256::ZERO };
+
+    fn double(&self) -> Point {
+        if self.z.is_zero() || self.y.is_zero() {
+            return Point::INFINITY;
+        }
+        let a = P.mul(self.x, self.x);
+        let b = P.mul(self.y, self.y);
+        let c = P.mul(b, b);
+        let xb = P.add(self.x, b);
+        let d = P.sub(P.sub(P.mul(xb, xb), a), c);
+        let d = P.add(d, d);
+        let e = P.add(P.add(a, a), a);
+        let f = P.mul(e, e);
+        let x = P.sub(f, P.add(d, d));
+        let c2 = P.add(c, c);
+        let c4 = P.add(c2, c2);
+        let y = P.sub(P.mul(e, P.sub(d, x)), P.add(c4, c4));
+        let yz = P.mul(self.y, self.z);
+        Point { x, y, z: P.add(yz, yz) }
+    }
+
+    fn add(&self, other: &Point) -> Point {
+        if self.z.is_zero() {
+            return *other;
+        }
+        if other.z.is_zero() {
+            return *self;
+        }
+        let z1z1 = P.mul(self.z, self.z);
+        let z2z2 = P.mul(other.z, other.z);
+        let u1 = P.mul(self.x, z2z2);
+        let u2 = P.mul(other.x, z1z1);
+        let s1 = P.mul(P.mul(self.y, other.z), z2z2);
+        let s2 = P.mul(P.mul(other.y, self.z), z1z1);
+        if u1 == u2 {
+            return match s1 == s2 {
+                true => self.double(),
+                false => Point::INFINITY,
+            };
+        }
+        let h = P.sub(u2, u1);
+        let h2 = P.add(h, h);
+        let i = P.mul(h2, h2);
+        let j = P.mul(h, i);
+        let r = P.sub(s2, s1);
+        let r = P.add(r, r);
+        let v = P.mul(u1, i);
+        let x = P.sub(P.sub(P.mul(r, r), j), P.add(v, v));
+        let s1j = P.mul(s1, j);
+        let y = P.sub(P.mul(r, P.sub(v, x)), P.add(s1j, s1j));
+        let z12 = P.add(self.z, other.z);
+        let z = P.mul(P.sub(P.sub(P.mul(z12, z12), z1z1), z2z2), h);
+        Point { x, y, z }
+    }
+
+    // double-and-add from the most significant bit of the scalar
+    fn mul(&self, scalar: U256) -> Point {
+        let mut result = Point::INFINITY;
+        for i in (0..256).rev() {
+            result = result.double();
+            if scalar.bit(i) {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+
+    fn to_affine(self) -> Option<(U256, U256)> {
+        if self.z.is_zero() {
+            return Option::None;
+        }
+        let z_inv = P.inv(self.z);
+        let z_inv2 = P.mul(z_inv, z_inv);
+        Option::Some((P.mul(self.x, z_inv2), P.mul(self.y, P.mul(z_inv2, z_inv))))
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    const ZERO: U256 = U256([0; 4]);
+
+    fn from_u64(n: u64) -> U256 {
+        U256([n, 0, 0, 0])
+    }
+
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            let mut carry = c.to_digit(10)? as u128;
+            for limb in n.0.iter_mut() {
+                let acc = *limb as u128 * 10 + carry;
+                *limb = acc as u64;
+                carry = acc >> 64;
+            }
+            if carry != 0 {
+                return Option::None;
+            }
+        }
+        Option::Some(n)
+    }
+
+    fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().enumerate() {
+            bytes[24 - i * 8..32 - i * 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+
+    // big-endian bytes without leading zeros, as rlp encodes integers, zero is empty
+    fn to_be_trimmed(self) -> Vec<u8> {
+        self.to_be_bytes().iter().skip_while(|b| **b == 0).copied().collect()
+    }
+
+    fn low_u64(&self) -> u64 {
+        self.0[0]
+    }
+
+    fn bits(&self) -> usize {
+        match self.0.iter().rposition(|l| *l != 0) {
+            Option::Some(top) => top * 64 + 64 - self.0[top].leading_zeros() as usize,
+            Option::None => 0,
+        }
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        (self.0[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    fn overflowing_add(self, other: U256) -> (U256, bool) {
+        let mut limbs = [0u64; 4];
+        let mut carry = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_add(other.0[i]);
+            let (v, o2) = v.overflowing_add(carry as u64);
+            *limb = v;
+            carry = o1 || o2;
+        }
+        (U256(limbs), carry)
+    }
+
+    fn overflowing_sub(self, other: U256) -> (U256, bool) {
+        let mut limbs = [0u64; 4];
+        let mut borrow = false;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let (v, o1) = self.0[i].overflowing_sub(other.0[i]);
+            let (v, o2) = v.overflowing_sub(borrow as u64);
+            *limb = v;
+            borrow = o1 || o2;
+        }
+        (U256(limbs), borrow)
+    }
+
+    // the full 512-bit product, least significant limb first
+    fn widening_mul(self, other: U256) -> [u64; 8] {
+        let mut wide = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let acc = wide[i + j] as u128 + self.0[i] as u128 * other.0[j] as u128 + carry;
+                wide[i + j] = acc as u64;
+                carry = acc >> 64;
+            }
+            wide[i + 4] = carry as u64;
+        }
+        wide
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl Ord for U256 {
+    // limbs are least significant first, so compare from the last
+    fn cmp(&self, other: &U256) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for U256 {
+    fn partial_cmp(&self, other: &U256) -> Option<Ordering> {
+        Option::Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(fields: Value) -> HashMap<String, Value> {
+        serde_json::from_value(fields).unwrap()
+    }
+
+    #[test]
+    fn rlp_encodes_the_spec_examples() {
+        assert_eq!(rlp_bytes(b"dog"), b"\x83dog");
+        assert_eq!(rlp_bytes(&[]), [0x80]);
+        assert_eq!(rlp_bytes(&[0x0f]), [0x0f]);
+        assert_eq!(rlp_bytes(&[0x80]), [0x81, 0x80]);
+        assert_eq!(rlp_list(&[rlp_bytes(b"cat"), rlp_bytes(b"dog")]), b"\xc8\x83cat\x83dog");
+        assert_eq!(rlp_list(&[]), [0xc0]);
+
+        let lorem = b"Lorem ipsum dolor sit amet, consectetur adipisicing elit";
+        assert_eq!(rlp_bytes(lorem), [&[0xb8, 0x38][..], &lorem[..]].concat());
+        assert_eq!(rlp_length(1024, 0x80), [0xb9, 0x04, 0x00]);
+    }
+
+    #[test]
+    fn recover_sender_recovers_the_eip155_example() {
+        let input = transaction(serde_json::json!({
+            "nonce": "0x9", "gasPrice": "0x4a817c800", "gas": "0x5208", "to": "0x3535353535353535353535353535353535353535",
+            "value": "0xde0b6b3a7640000", "input": "0x", "v": "0x25",
+            "r": "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276",
+            "s": "0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        }));
+        assert_eq!(recover_sender(&input, 0).as_deref(), Option::Some("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f"));
+    }
+
+    #[test]
+    fn chain_id_is_derived_from_v_for_eip155_legacy_transactions() {
+        assert_eq!(chain_id(&transaction(serde_json::json!({ "v": "0x25" })), 0), Value::from(1));
+        assert_eq!(chain_id(&transaction(serde_json::json!({ "v": 310 })), 0), Value::from(137));
+        assert_eq!(chain_id(&transaction(serde_json::json!({ "v": "0x1b" })), 0), Value::Null);
+        assert_eq!(chain_id(&transaction(serde_json::json!({ "v": "0x1", "chainId": "0xa" })), 2), Value::from(10));
+        assert_eq!(chain_id(&transaction(serde_json::json!({ "v": "0x1" })), 2), Value::Null);
+    }
+}
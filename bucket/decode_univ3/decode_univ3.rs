@@ -0,0 +1,398 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass logs that aren't pool events through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+    // the decimals of the pool's tokens, the price is of token0 in token1 and in their smallest
+    // units when they are left out
+    #[serde(default)]
+    pub decimals0: u32,
+    #[serde(default)]
+    pub decimals1: u32,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// keccak256 of the uniswap v3 pool events "Swap(address,address,int256,int256,uint160,uint128,int24)",
+// "Mint(address,address,int24,int24,uint128,uint256,uint256)", "Burn(address,int24,int24,uint128,uint256,uint256)",
+// "Collect(address,address,int24,int24,uint128,uint128)" and "Flash(address,address,uint256,uint256,uint256,uint256)"
+const SWAP_TOPIC: &str = "0xc42079f94a6350d7e6235f29174924f928cc2ac818eb64fed8004e115fbcca67";
+const MINT_TOPIC: &str = "0x7a53080ba414158be7ec69b987b5fb7d07dee101fe85488f0853ae16239d0bde";
+const BURN_TOPIC: &str = "0x0c396cd989a39f4459b5fa1aed6a9a8dcdbc45908acfd67e028cd568da98982c";
+const COLLECT_TOPIC: &str = "0x70935338e69775456a85ddef226c395fb668b63fa0115f5f20610b388e6ca9c0";
+const FLASH_TOPIC: &str = "0xbdbdb71d7860376ba52b25a5028beea23581364a40522f6bcfb86bb1f2dca633";
+
+// significant digits kept in the computed price
+const PRICE_DIGITS: usize = 18;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match decode_log(&input, &params) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fixed schema document of a pool event, swap amounts are signed and positive for the tokens paid
+// into the pool, fields an event doesn't carry are null, logs of other events give None
+fn decode_log(input: &HashMap<String, Value>, params: &Parameters) -> Option<HashMap<String, Value>> {
+    let topics: Vec<String> = input
+        .get("topics")?
+        .as_array()?
+        .iter()
+        .map(|t| t.as_str().map(|t| t.trim().to_lowercase()))
+        .collect::<Option<Vec<String>>>()?;
+    let data = input.get("data")?.as_str()?.trim().to_lowercase();
+    let data = hex::decode(data.strip_prefix("0x").unwrap_or(&data)).ok()?;
+    let words: Vec<&[u8]> = data.chunks(32).collect();
+    let indexed: Vec<Vec<u8>> = topics
+        .iter()
+        .map(|t| hex::decode(t.strip_prefix("0x").unwrap_or(t)).ok().filter(|w| w.len() == 32))
+        .collect::<Option<Vec<Vec<u8>>>>()?;
+
+    let mut output: HashMap<String, Value> = [
+        "sender", "recipient", "owner", "amount0", "amount1", "sqrtPriceX96", "liquidity", "tick", "price",
+        "tickLower", "tickUpper", "amount", "paid0", "paid1",
+    ]
+    .iter()
+    .map(|key| (key.to_string(), Value::Null))
+    .collect();
+    let mut set = |key: &str, value: Value| output.insert(key.to_string(), value);
+
+    let event_name = match (topics.first().map(|t| t.as_str()), topics.len(), data.len()) {
+        (Option::Some(SWAP_TOPIC), 3, 160) => {
+            set("sender", word_address(&indexed[1]));
+            set("recipient", word_address(&indexed[2]));
+            set("amount0", Value::String(signed(words[0])));
+            set("amount1", Value::String(signed(words[1])));
+            set("sqrtPriceX96", Value::String(U256::from_be_slice(words[2]).to_string()));
+            set("liquidity", Value::String(U256::from_be_slice(words[3]).to_string()));
+            set("tick", Value::from(small_signed(words[4])));
+            set("price", Value::String(price(U256::from_be_slice(words[2]), params)));
+            "Swap"
+        }
+        (Option::Some(MINT_TOPIC), 4, 128) => {
+            set("sender", word_address(words[0]));
+            set("owner", word_address(&indexed[1]));
+            set("tickLower", Value::from(small_signed(&indexed[2])));
+            set("tickUpper", Value::from(small_signed(&indexed[3])));
+            set("amount", Value::String(U256::from_be_slice(words[1]).to_string()));
+            set("amount0", Value::String(U256::from_be_slice(words[2]).to_string()));
+            set("amount1", Value::String(U256::from_be_slice(words[3]).to_string()));
+            "Mint"
+        }
+        (Option::Some(BURN_TOPIC), 4, 96) => {
+            set("owner", word_address(&indexed[1]));
+            set("tickLower", Value::from(small_signed(&indexed[2])));
+            set("tickUpper", Value::from(small_signed(&indexed[3])));
+            set("amount", Value::String(U256::from_be_slice(words[0]).to_string()));
+            set("amount0", Value::String(U256::from_be_slice(words[1]).to_string()));
+            set("amount1", Value::String(U256::from_be_slice(words[2]).to_string()));
+            "Burn"
+        }
+        (Option::Some(COLLECT_TOPIC), 4, 96) => {
+            set("owner", word_address(&indexed[1]));
+            set("recipient", word_address(words[0]));
+            set("tickLower", Value::from(small_signed(&indexed[2])));
+            set("tickUpper", Value::from(small_signed(&indexed[3])));
+            set("amount0", Value::String(U256::from_be_slice(words[1]).to_string()));
+            set("amount1", Value::String(U256::from_be_slice(words[2]).to_string()));
+            "Collect"
+        }
+        (Option::Some(FLASH_TOPIC), 3, 128) => {
+            set("sender", word_address(&indexed[1]));
+            set("recipient", word_address(&indexed[2]));
+            set("amount0", Value::String(U256::from_be_slice(words[0]).to_string()));
+            set("amount1", Value::String(U256::from_be_slice(words[1]).to_string()));
+            set("paid0", Value::String(U256::from_be_slice(words[2]).to_string()));
+            set("paid1", Value::String(U256::from_be_slice(words[3]).to_string()));
+            "Flash"
+        }
+        _ => return Option::None,
+    };
+
+    output.insert("eventName".to_string(), Value::String(event_name.to_string()));
+    let pool = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("pool".to_string(), serde_json::json!(pool));
+    for key in ["transactionHash", "transactionIndex", "blockHash", "blockNumber", "logIndex"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the address in the low 20 bytes of a word
+fn word_address(word: &[u8]) -> Value {
+    Value::String(format!("0x{}", hex::encode(&word[12..])))
+}
+
+// a two's complement int256 word as a decimal string
+fn signed(word: &[u8]) -> String {
+    let n = U256::from_be_slice(word);
+    match word[0] & 0x80 != 0 {
+        true => format!("-{}", n.wrapping_neg()),
+        false => n.to_string(),
+    }
+}
+
+// a sign-extended word holding a small signed integer such as an int24 tick
+fn small_signed(word: &[u8]) -> i64 {
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&word[24..]);
+    i64::from_be_bytes(low)
+}
+
+// the price of token0 in token1 implied by the pool's sqrt price, (sqrtPriceX96 / 2^96)^2, scaled by
+// the tokens' decimals and rounded down to PRICE_DIGITS significant digits
+fn price(sqrt_price: U256, params: &Parameters) -> String {
+    // the square of a uint160 fits 320 bits, its integer part is above the 192 fractional bits
+    let square = sqrt_price.widening_mul(sqrt_price);
+    let integer = (square[3] as u128) | ((square[4] as u128) << 64);
+    let mut fraction = [square[0], square[1], square[2], 0];
+
+    let mut digits = match integer {
+        0 => String::new(),
+        _ => integer.to_string(),
+    };
+    let integer_len = digits.len();
+    // the fraction of a price below one starts with zeros that aren't significant
+    while fraction.iter().any(|l| *l != 0) && digits.trim_start_matches('0').len() < PRICE_DIGITS {
+        // multiplying the 192-bit fraction by ten carries the next decimal digit into the top limb
+        let mut carry = 0u128;
+        for limb in fraction.iter_mut() {
+            let acc = *limb as u128 * 10 + carry;
+            *limb = acc as u64;
+            carry = acc >> 64;
+        }
+        digits.push(char::from(b'0' + fraction[3] as u8));
+        fraction[3] = 0;
+    }
+
+    // place the decimal point, moved right by token0's decimals and left by token1's
+    let point = integer_len as i64 + params.decimals0 as i64 - params.decimals1 as i64;
+    let (whole, decimals) = match point {
+        p if p <= 0 => ("0".to_string(), format!("{}{}", "0".repeat((-p) as usize), digits)),
+        p if p as usize >= digits.len() => (format!("{}{}", digits, "0".repeat(p as usize - digits.len())), String::new()),
+        p => (digits[..p as usize].to_string(), digits[p as usize..].to_string()),
+    };
+    let whole = match whole.trim_start_matches('0') {
+        "" => "0",
+        w => w,
+    };
+    match decimals.trim_end_matches('0') {
+        "" => whole.to_string(),
+        d => format!("{}.{}", whole, d),
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // two's complement negation modulo 2^256
+    fn wrapping_neg(self) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut carry = true;
+        for (i, l) in self.0.iter().enumerate() {
+            let (v, overflow) = (!l).overflowing_add(carry as u64);
+            limbs[i] = v;
+            carry = overflow;
+        }
+        U256(limbs)
+    }
+
+    // the full 512-bit product, limbs least significant first
+    fn widening_mul(self, other: U256) -> [u64; 8] {
+        let mut limbs = [0u64; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let acc = self.0[i] as u128 * other.0[j] as u128 + limbs[i + j] as u128 + carry;
+                limbs[i + j] = acc as u64;
+                carry = acc >> 64;
+            }
+            limbs[i + 4] = carry as u64;
+        }
+        limbs
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sqrt_price(n: u128) -> U256 {
+        U256::from_be_slice(&n.to_be_bytes())
+    }
+
+    fn decimals(decimals0: u32, decimals1: u32) -> Parameters {
+        Parameters { decimals0, decimals1, ..Parameters::default() }
+    }
+
+    #[test]
+    fn price_squares_the_sqrt_price() {
+        assert_eq!(price(sqrt_price(1 << 96), &decimals(0, 0)), "1");
+        assert_eq!(price(sqrt_price(1 << 97), &decimals(0, 0)), "4");
+        assert_eq!(price(sqrt_price(1 << 95), &decimals(0, 0)), "0.25");
+        assert_eq!(price(sqrt_price(3 << 95), &decimals(0, 0)), "2.25");
+        assert_eq!(price(sqrt_price(0), &decimals(0, 0)), "0");
+    }
+
+    #[test]
+    fn price_moves_the_point_by_the_token_decimals() {
+        assert_eq!(price(sqrt_price(1 << 96), &decimals(18, 6)), "1000000000000");
+        assert_eq!(price(sqrt_price(1 << 96), &decimals(6, 18)), "0.000000000001");
+        assert_eq!(price(sqrt_price(1 << 95), &decimals(1, 0)), "2.5");
+    }
+
+    #[test]
+    fn price_keeps_the_significant_digits_of_a_small_price() {
+        // (2^96 / 3)^2 / 2^192 is 1/9 less the truncation of the sqrt price
+        assert_eq!(price(sqrt_price((1 << 96) / 3), &decimals(0, 0)), "0.111111111111111111");
+    }
+
+    #[test]
+    fn signed_reads_twos_complement_words() {
+        assert_eq!(signed(&[0xff; 32]), "-1");
+        assert_eq!(signed(&[[0u8; 31].as_slice(), &[7]].concat()), "7");
+        let tick = [[0xff; 24].as_slice(), &(-887272i64).to_be_bytes()].concat();
+        assert_eq!(small_signed(&tick), -887272);
+    }
+}
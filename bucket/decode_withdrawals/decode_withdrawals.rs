@@ -0,0 +1,167 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass blocks without withdrawals, e.g. those before shanghai, through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+const WEI_PER_GWEI: u128 = 1_000_000_000;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let empty: Vec<Value> = Vec::new();
+    let withdrawals = input.get("withdrawals").and_then(|v| v.as_array()).unwrap_or(&empty);
+    if withdrawals.is_empty() {
+        let result_json = match params.keep_unmatched {
+            true => Option::Some(serde_json::to_vec(&input)?),
+            false => Option::None,
+        };
+        lens_sdk::free_transport_buffer(ptr)?;
+        return match result_json {
+            Option::Some(result_json) => Ok(Some(result_json)),
+            Option::None => try_transform(),
+        };
+    }
+
+    let block_number = input.get("number").and_then(quantity);
+    let timestamp = input.get("timestamp").and_then(quantity);
+
+    let mut documents = VecDeque::new();
+    for (i, withdrawal) in withdrawals.iter().enumerate() {
+        // the consensus layer accounts in gwei, the amount is credited to the address in wei
+        let amount = withdrawal.get("amount").and_then(quantity);
+        let address = withdrawal["address"].as_str().map(|a| a.trim().to_lowercase());
+
+        let mut document = serde_json::Map::new();
+        document.insert("withdrawalIndex".to_string(), serde_json::json!(withdrawal.get("index").and_then(quantity)));
+        document.insert("validatorIndex".to_string(), serde_json::json!(withdrawal.get("validatorIndex").and_then(quantity)));
+        document.insert("address".to_string(), serde_json::json!(address));
+        document.insert("amountGwei".to_string(), serde_json::json!(amount));
+        document.insert("amountWei".to_string(), serde_json::json!(amount.map(|a| (a as u128 * WEI_PER_GWEI).to_string())));
+        document.insert("amountEth".to_string(), serde_json::json!(amount.map(eth)));
+        document.insert("position".to_string(), Value::from(i));
+        document.insert("blockNumber".to_string(), serde_json::json!(block_number));
+        document.insert("blockHash".to_string(), input.get("hash").cloned().unwrap_or(Value::Null));
+        document.insert("timestamp".to_string(), serde_json::json!(timestamp));
+        documents.push_back(serde_json::to_vec(&document)?);
+    }
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    let first = documents.pop_front();
+    PENDING.write()?.extend(documents);
+    match first {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
+
+// an amount of gwei as an exact decimal amount of ether, e.g. "0.018312345"
+fn eth(gwei: u64) -> String {
+    let (whole, fraction) = (gwei / WEI_PER_GWEI as u64, gwei % WEI_PER_GWEI as u64);
+    match fraction {
+        0 => whole.to_string(),
+        _ => format!("{}.{}", whole, format!("{:09}", fraction).trim_end_matches('0')),
+    }
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => u64::from_str_radix(digits, 16).ok(),
+            Option::None => s.trim().parse::<u64>().ok(),
+        },
+        _ => Option::None,
+    }
+}
@@ -0,0 +1,227 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the fields that together identify a record, a log by default
+    #[serde(default = "fields_default")]
+    pub fields: Vec<String>,
+    // how many of the most recently seen keys are remembered, a duplicate delivered after that many
+    // other records is passed again
+    #[serde(default = "capacity_default")]
+    pub capacity: usize,
+}
+
+fn fields_default() -> Vec<String> {
+    vec!["transactionHash".to_string(), "logIndex".to_string()]
+}
+
+fn capacity_default() -> usize {
+    10000
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            fields: fields_default(),
+            capacity: capacity_default(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// the keys seen in the stream, each with the stamp of its latest sighting, and the sightings in order,
+// a sighting whose stamp is no longer its key's latest is stale and skipped when the oldest key is evicted
+struct Seen {
+    latest: Option<HashMap<String, u64>>,
+    order: VecDeque<(String, u64)>,
+    stamp: u64,
+}
+
+static SEEN: RwLock<Seen> = RwLock::new(Seen::empty());
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // new parameters start a new stream, keys seen under the previous ones are forgotten
+    *SEEN.write()? = Seen::empty();
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        // a stream that follows starts with no keys seen, as it does under new parameters
+        EndOfStream => {
+            *SEEN.write()? = Seen::empty();
+            return Ok(EndOfStream);
+        }
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // records lacking a key field can't be identified and are always passed, each part is prefixed by
+    // its length so that no two part lists, e.g. ["a|b", "c"] and ["a", "b|c"], share a key
+    let key = params.fields
+        .iter()
+        .map(|field| {
+            let part = key_part(input.get(field).filter(|v| !v.is_null())?);
+            Option::Some(format!("{}:{}", part.len(), part))
+        })
+        .collect::<Option<String>>();
+
+    // dropped records are skipped by moving on to the next input
+    if let Option::Some(key) = key {
+        if SEEN.write()?.sighting(key, params.capacity) {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+impl Seen {
+    const fn empty() -> Seen {
+        Seen { latest: Option::None, order: VecDeque::new(), stamp: 0 }
+    }
+
+    // record a sighting of `key`, returning whether it was seen before, and evict the least recently
+    // seen keys beyond `capacity`
+    fn sighting(&mut self, key: String, capacity: usize) -> bool {
+        self.stamp += 1;
+        let latest = self.latest.get_or_insert_with(HashMap::new);
+        let duplicate = latest.insert(key.clone(), self.stamp).is_some();
+        self.order.push_back((key, self.stamp));
+
+        while latest.len() > capacity {
+            match self.order.pop_front() {
+                Option::Some((oldest, stamp)) => {
+                    if latest.get(&oldest) == Option::Some(&stamp) {
+                        latest.remove(&oldest);
+                    }
+                }
+                Option::None => break,
+            }
+        }
+        // repeated sightings leave stale entries behind, they are dropped once they outnumber the keys
+        if self.order.len() > 2 * latest.len().max(capacity) {
+            self.order.retain(|(key, stamp)| latest.get(key) == Option::Some(stamp));
+        }
+        duplicate
+    }
+}
+
+// a key field as text, hex strings are compared regardless of case and quantities regardless of
+// their encoding, so "0x1a" and 26 are the same log index, other strings are kept as they are
+fn key_part(value: &Value) -> String {
+    match value {
+        Value::String(s) => match s.trim().strip_prefix("0x").or(s.trim().strip_prefix("0X")) {
+            Option::Some(digits) => {
+                let digits = digits.to_lowercase();
+                match digits.len() <= 16 {
+                    true => u64::from_str_radix(&digits, 16).map(|n| n.to_string()).unwrap_or(format!("0x{}", digits)),
+                    false => format!("0x{}", digits),
+                }
+            }
+            Option::None => s.trim().to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_part_compares_hex_regardless_of_case_and_quantities_regardless_of_encoding() {
+        assert_eq!(key_part(&Value::from("0x1a")), key_part(&Value::from(26)));
+        assert_eq!(key_part(&Value::from(" 0x1A ")), "26");
+        assert_eq!(key_part(&Value::from("0xABCDEF0123456789ab")), "0xabcdef0123456789ab");
+        assert_eq!(key_part(&Value::from("Transfer")), "Transfer");
+        assert_eq!(key_part(&Value::Bool(true)), "true");
+    }
+
+    #[test]
+    fn sighting_forgets_the_least_recently_seen_keys_beyond_capacity() {
+        let mut seen = Seen::empty();
+        assert!(!seen.sighting("a".to_string(), 2));
+        assert!(!seen.sighting("b".to_string(), 2));
+        assert!(seen.sighting("a".to_string(), 2));
+        // b is now the least recently seen and is evicted for c
+        assert!(!seen.sighting("c".to_string(), 2));
+        assert!(!seen.sighting("b".to_string(), 2));
+        assert!(seen.sighting("b".to_string(), 2));
+    }
+}
@@ -0,0 +1,243 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass records without contract code through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// the functions a contract of each standard must implement, their selectors are the constants the
+// solidity and vyper dispatchers compare the calldata against
+const STANDARDS: [(&str, &[&str]); 4] = [
+    ("erc20", &[
+        "totalSupply()",
+        "balanceOf(address)",
+        "transfer(address,uint256)",
+        "transferFrom(address,address,uint256)",
+        "approve(address,uint256)",
+        "allowance(address,address)",
+    ]),
+    ("erc721", &[
+        "balanceOf(address)",
+        "ownerOf(uint256)",
+        "safeTransferFrom(address,address,uint256)",
+        "safeTransferFrom(address,address,uint256,bytes)",
+        "transferFrom(address,address,uint256)",
+        "approve(address,uint256)",
+        "setApprovalForAll(address,bool)",
+        "getApproved(uint256)",
+        "isApprovedForAll(address,address)",
+    ]),
+    ("erc1155", &[
+        "safeTransferFrom(address,address,uint256,uint256,bytes)",
+        "safeBatchTransferFrom(address,address,uint256[],uint256[],bytes)",
+        "balanceOf(address,uint256)",
+        "balanceOfBatch(address[],uint256[])",
+        "setApprovalForAll(address,bool)",
+        "isApprovedForAll(address,address)",
+    ]),
+    ("erc4626", &[
+        "asset()",
+        "totalAssets()",
+        "convertToShares(uint256)",
+        "convertToAssets(uint256)",
+        "maxDeposit(address)",
+        "previewDeposit(uint256)",
+        "deposit(uint256,address)",
+        "maxMint(address)",
+        "previewMint(uint256)",
+        "mint(uint256,address)",
+        "maxWithdraw(address)",
+        "previewWithdraw(uint256)",
+        "withdraw(uint256,address,address)",
+        "maxRedeem(address)",
+        "previewRedeem(uint256)",
+        "redeem(uint256,address,address)",
+    ]),
+];
+
+// the ERC-165 interface ids a contract answers supportsInterface with, they appear as constants in its
+// code, a standard whose interface id is found is detected even when some of its functions are missing
+const INTERFACE_IDS: [(&str, &str); 8] = [
+    ("01ffc9a7", "erc165"),
+    ("80ac58cd", "erc721"),
+    ("5b5e139f", "erc721Metadata"),
+    ("780e9d63", "erc721Enumerable"),
+    ("d9b67a26", "erc1155"),
+    ("0e89341c", "erc1155MetadataUri"),
+    ("2a55205a", "erc2981"),
+    ("49064906", "erc4906"),
+];
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match detect_standards(&input) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fixed schema classification document of a contract from its runtime `code`, code matching no
+// standard, e.g. of a proxy or an account without code, is classified with no standards, records that
+// carry no code give None
+fn detect_standards(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    let code = input
+        .get("code")
+        .or(input.get("bytecode"))
+        .or(input.get("deployedBytecode"))?
+        .as_str()?
+        .trim()
+        .to_lowercase();
+    let code = hex::decode(code.strip_prefix("0x").unwrap_or(&code)).ok()?;
+    let constants = push_constants(&code);
+
+    let interfaces: Vec<&str> = INTERFACE_IDS
+        .iter()
+        .filter(|(id, _)| constants.contains(*id))
+        .map(|(_, name)| *name)
+        .collect();
+    let mut standards: Vec<&str> = Vec::new();
+    let mut matches = serde_json::Map::new();
+    for (standard, functions) in STANDARDS.iter() {
+        let found = functions.iter().filter(|sig| constants.contains(&selector(sig))).count();
+        matches.insert(standard.to_string(), serde_json::json!({"found": found, "required": functions.len()}));
+        if found == functions.len() || interfaces.contains(standard) {
+            standards.push(standard);
+        }
+    }
+    if interfaces.contains(&"erc165") {
+        standards.push("erc165");
+    }
+
+    let mut output = HashMap::new();
+    let address = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("address".to_string(), serde_json::json!(address));
+    output.insert("standards".to_string(), serde_json::json!(standards));
+    output.insert("interfaces".to_string(), serde_json::json!(interfaces));
+    output.insert("functionMatches".to_string(), Value::Object(matches));
+    output.insert("codeLength".to_string(), Value::from(code.len()));
+    for key in ["blockHash", "blockNumber"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the values of the code's PUSH1 to PUSH4 instructions as 4-byte words in hex, the compilers push
+// selectors and interface ids with leading zero bytes stripped, e.g. 0x00fdd58e with PUSH3
+fn push_constants(code: &[u8]) -> HashSet<String> {
+    let mut constants = HashSet::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = code[pc];
+        // PUSH1 to PUSH32 are followed by their 1 to 32 bytes of data, which aren't instructions
+        let width = match op {
+            0x60..=0x7f => (op - 0x5f) as usize,
+            _ => 0,
+        };
+        if (1..=4).contains(&width) {
+            if let Option::Some(data) = code.get(pc + 1..pc + 1 + width) {
+                constants.insert(format!("{:0>8}", hex::encode(data)));
+            }
+        }
+        pc += 1 + width;
+    }
+    constants
+}
+
+// the first 4 bytes of the hashed signature, which prefix the calldata of the function
+fn selector(sig: &str) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(sig.as_bytes());
+    hex::encode(&hasher.finalize()[..4])
+}
@@ -0,0 +1,207 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass records that show no proxy through unchanged instead of dropping them
+    #[serde(default)]
+    pub keep_unmatched: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// the EIP-1967 slots, keccak256("eip1967.proxy.implementation") - 1 and the admin and beacon alike
+const IMPLEMENTATION_SLOT: &str = "360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+const ADMIN_SLOT: &str = "b53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103";
+const BEACON_SLOT: &str = "a3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+// the slots of proxies predating EIP-1967, keccak256("PROXIABLE") of EIP-1822 upgradeable proxies
+// and keccak256("org.zeppelinos.proxy.implementation") of the zeppelinos proxies
+const PROXIABLE_SLOT: &str = "c5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7";
+const ZEPPELINOS_SLOT: &str = "7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036e5a723fd8ee048ed3f8c3";
+
+// the runtime code of EIP-1167 minimal proxies around the implementation address, and of the
+// EIP-7511 variant using PUSH0
+const MINIMAL_PROXY: (&str, &str) = ("363d3d373d3d3d363d73", "5af43d82803e903d91602b57fd5bf3");
+const MINIMAL_PROXY_PUSH0: (&str, &str) = ("365f5f375f5f365f73", "5af43d5f5f3e5f3d91602a57fd5bf3");
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let output = match detect_proxy(&input) {
+        Option::Some(output) => output,
+        Option::None if params.keep_unmatched => input,
+        Option::None => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return try_transform();
+        }
+    };
+
+    let result_json = serde_json::to_vec(&output)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the fixed schema document of a contract found to be a proxy, from its runtime `code`, the slots of
+// its `storage` or a record of a single storage `slot` and its `value`, contracts that are no proxy
+// or whose proxy slots are unset give None
+fn detect_proxy(input: &HashMap<String, Value>) -> Option<HashMap<String, Value>> {
+    let mut storage: HashMap<String, String> = input
+        .get("storage")
+        .and_then(|s| s.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(slot, value)| Option::Some((word(slot)?, word(value.as_str()?)?)))
+        .collect();
+    let slot = input.get("slot").or(input.get("key")).and_then(|s| s.as_str()).and_then(word);
+    let value = input.get("value").and_then(|v| v.as_str()).and_then(word);
+    if let (Option::Some(slot), Option::Some(value)) = (slot, value) {
+        storage.insert(slot, value);
+    }
+    let slot_address = |slot: &str| storage.get(slot).and_then(|value| word_address(value));
+
+    let code = input
+        .get("code")
+        .or(input.get("bytecode"))
+        .and_then(|c| c.as_str())
+        .map(|c| c.trim().to_lowercase())
+        .unwrap_or_default();
+    let code = code.strip_prefix("0x").unwrap_or(&code);
+
+    // minimal proxies can't be upgraded, so their code is the most certain sign, the EIP-1967 admin and
+    // beacon are reported alongside any implementation as a beacon proxy stores no implementation itself
+    let admin = slot_address(ADMIN_SLOT);
+    let beacon = slot_address(BEACON_SLOT);
+    let (proxy_type, implementation) = if let Option::Some(implementation) = minimal_proxy(code) {
+        ("eip1167", Option::Some(implementation))
+    } else if let Option::Some(implementation) = slot_address(IMPLEMENTATION_SLOT) {
+        ("eip1967", Option::Some(implementation))
+    } else if beacon.is_some() {
+        ("eip1967Beacon", Option::None)
+    } else if let Option::Some(implementation) = slot_address(PROXIABLE_SLOT) {
+        ("eip1822", Option::Some(implementation))
+    } else if let Option::Some(implementation) = slot_address(ZEPPELINOS_SLOT) {
+        ("zeppelinos", Option::Some(implementation))
+    } else if admin.is_some() {
+        ("eip1967", Option::None)
+    } else {
+        return Option::None;
+    };
+
+    let mut output = HashMap::new();
+    let address = input.get("address").and_then(|a| a.as_str()).map(|a| a.trim().to_lowercase());
+    output.insert("address".to_string(), serde_json::json!(address));
+    output.insert("proxyType".to_string(), Value::String(proxy_type.to_string()));
+    output.insert("implementation".to_string(), serde_json::json!(implementation));
+    output.insert("admin".to_string(), serde_json::json!(admin));
+    output.insert("beacon".to_string(), serde_json::json!(beacon));
+    for key in ["blockHash", "blockNumber"] {
+        output.insert(key.to_string(), input.get(key).cloned().unwrap_or(Value::Null));
+    }
+    Option::Some(output)
+}
+
+// the implementation address embedded in the runtime code of a minimal proxy
+fn minimal_proxy(code: &str) -> Option<String> {
+    [MINIMAL_PROXY, MINIMAL_PROXY_PUSH0].iter().find_map(|(prefix, suffix)| {
+        let address = code.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        match address.len() == 40 && address.chars().all(|c| c.is_ascii_hexdigit()) {
+            true => Option::Some(format!("0x{}", address)),
+            false => Option::None,
+        }
+    })
+}
+
+// a storage slot or value as 64 lowercase hex digits, rpc nodes return both without leading zeros
+fn word(s: &str) -> Option<String> {
+    let s = s.trim().to_lowercase();
+    let digits = s.strip_prefix("0x").unwrap_or(&s);
+    match digits.len() <= 64 && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Option::Some(format!("{:0>64}", digits)),
+        false => Option::None,
+    }
+}
+
+// the address held by a slot, None while the slot is unset or when it holds more than an address
+fn word_address(word: &str) -> Option<String> {
+    match word[..24].chars().all(|c| c == '0') && word[24..].chars().any(|c| c != '0') {
+        true => Option::Some(format!("0x{}", &word[24..])),
+        false => Option::None,
+    }
+}
@@ -0,0 +1,209 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    MissingFieldError{field: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::MissingFieldError { field } =>
+                write!(f, "The record has no `{}` to key it by.", field),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the fields that together identify a record, in the order they are hashed, a log by default, a
+    // record lacking one of them fails rather than sharing the key of every record lacking it
+    #[serde(default = "fields_default")]
+    pub fields: Vec<String>,
+    // the field the key is inserted as, replacing any value it already has
+    #[serde(default = "field_default")]
+    pub field: String,
+    // the chain id hashed for `chainId` when a record doesn't carry one, rpc logs and receipts don't so
+    // keying them by the default fields needs it set
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+}
+
+fn fields_default() -> Vec<String> {
+    vec!["chainId".to_string(), "blockHash".to_string(), "transactionHash".to_string(), "logIndex".to_string()]
+}
+
+fn field_default() -> String {
+    "docKey".to_string()
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            fields: fields_default(),
+            field: field_default(),
+            chain_id: Option::None,
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let key = match preimage(&params, &input) {
+        Ok(preimage) => format!("0x{}", hex::encode(Keccak256::digest(preimage.as_bytes()))),
+        Err(e) => {
+            lens_sdk::free_transport_buffer(ptr)?;
+            return Err(e.into());
+        }
+    };
+    input.insert(params.field.clone(), Value::String(key));
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the hashed parts of a record's key, each prefixed by its length so that no two part lists, e.g.
+// ["a|b", "c"] and ["a", "b|c"], share a preimage
+fn preimage(params: &Parameters, input: &HashMap<String, Value>) -> Result<String, ModuleError> {
+    let chain_id = params.chain_id.map(Value::from);
+    params.fields
+        .iter()
+        .map(|field| {
+            let value = match field.as_str() {
+                "chainId" => input.get(field).filter(|v| !v.is_null()).or(chain_id.as_ref()),
+                _ => input.get(field),
+            };
+            match value.filter(|v| !v.is_null()).map(key_part) {
+                Option::Some(part) => Ok(format!("{}:{}", part.len(), part)),
+                Option::None => Err(ModuleError::MissingFieldError { field: field.clone() }),
+            }
+        })
+        .collect()
+}
+
+// a key field as text, hex strings are compared regardless of case and quantities regardless of
+// their encoding, so "0x1a" and 26 are the same log index, other strings are kept as they are
+fn key_part(value: &Value) -> String {
+    match value {
+        Value::String(s) => match s.trim().strip_prefix("0x").or(s.trim().strip_prefix("0X")) {
+            Option::Some(digits) => {
+                let digits = digits.to_lowercase();
+                match digits.len() <= 16 {
+                    true => u64::from_str_radix(&digits, 16).map(|n| n.to_string()).unwrap_or(format!("0x{}", digits)),
+                    false => format!("0x{}", digits),
+                }
+            }
+            Option::None => s.trim().to_string(),
+        },
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_part_compares_hex_regardless_of_case_and_quantities_regardless_of_encoding() {
+        assert_eq!(key_part(&Value::from("0x1a")), key_part(&Value::from(26)));
+        assert_eq!(key_part(&Value::from(" 0x1A ")), "26");
+        assert_eq!(key_part(&Value::from("0xABCDEF0123456789ab")), "0xabcdef0123456789ab");
+        assert_eq!(key_part(&Value::from(format!("0x{}", "AB".repeat(32)))), format!("0x{}", "ab".repeat(32)));
+        assert_eq!(key_part(&Value::from("Transfer")), "Transfer");
+    }
+
+    #[test]
+    fn preimage_length_prefixes_the_parts_and_fails_on_a_missing_field() {
+        let params = Parameters { fields: vec!["a".to_string(), "b".to_string()], ..Parameters::default() };
+        let record = |fields: Value| serde_json::from_value::<HashMap<String, Value>>(fields).unwrap();
+
+        assert_eq!(preimage(&params, &record(serde_json::json!({ "a": "x|y", "b": "0x1a" }))).unwrap(), "3:x|y2:26");
+        assert_ne!(
+            preimage(&params, &record(serde_json::json!({ "a": "x|", "b": "y" }))),
+            preimage(&params, &record(serde_json::json!({ "a": "x", "b": "|y" }))),
+        );
+        assert_eq!(
+            preimage(&params, &record(serde_json::json!({ "a": "x", "b": null }))),
+            Err(ModuleError::MissingFieldError { field: "b".to_string() }),
+        );
+    }
+
+    #[test]
+    fn preimage_takes_the_configured_chain_id_for_records_without_one() {
+        let params = Parameters { fields: vec!["chainId".to_string()], chain_id: Option::Some(10), ..Parameters::default() };
+        assert_eq!(preimage(&params, &HashMap::new()).unwrap(), "2:10");
+        assert!(preimage(&Parameters::default(), &HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,901 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+// the variants keep the `Error` suffix that ParametersNotSetError gives the errors of every lens
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    AbiFormatError{reason: String},
+    EventNotFoundError{name: String},
+    EncodeError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::AbiFormatError { reason } =>
+                write!(f, "The abi parameter could not be read. Reason: {}", reason),
+            ModuleError::EventNotFoundError { name } =>
+                write!(f, "The event is not declared by the abi. Event: {}", name),
+            ModuleError::EncodeError { reason } =>
+                write!(f, "The event could not be encoded. Reason: {}", reason),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // a json abi document, or that document encoded in a string, declaring the events
+    pub abi: Value,
+    // keep the decoded fields next to the encoded `topics` and `data` instead of removing them
+    #[serde(default)]
+    pub keep_decoded: bool,
+    // the abi events keyed by name, overloads share an entry, built by set_param
+    #[serde(skip)]
+    pub events: HashMap<String, Vec<Value>>,
+}
+
+// shared so that reading the parameters for each record doesn't copy the abi
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    parameter.abi = normalize_abi(&parameter.abi)?;
+    let empty: Vec<Value> = Vec::new();
+    for item in parameter.abi.as_array().unwrap_or(&empty).iter().filter(|item| item["type"] == "event") {
+        let name = item["name"].as_str().unwrap_or_default().to_string();
+        parameter.events.entry(name).or_default().push(item.clone());
+    }
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(Arc::new(parameter));
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    // decode_event emits the event flat, with an `arguments` array, or nested under `decodedEvent`
+    // with an `args` map, records that hold no decoded event are passed through as they are
+    let nested = input.get("decodedEvent").filter(|v| v.is_object());
+    let event = nested.unwrap_or(&Value::Null);
+    let name = input.get("eventName").or(event.get("name")).and_then(|v| v.as_str()).map(|s| s.to_string());
+    if let Option::Some(name) = name {
+        let signature = input.get("signature").or(event.get("signature")).and_then(|v| v.as_str());
+        let arguments = input.get("arguments").or(event.get("args")).cloned().unwrap_or(Value::Null);
+
+        match encode_log(&params, &name, signature, &arguments) {
+            Ok((topics, data)) => {
+                input.insert("topics".to_string(), serde_json::json!(topics));
+                input.insert("data".to_string(), Value::String(data));
+                if !params.keep_decoded {
+                    remove_decoded_fields(&mut input);
+                }
+            }
+            Err(e) => {
+                input.insert("encodeError".to_string(), Value::String(e.to_string()));
+            }
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// remove the fields decode_event added, restoring the raw log fields it moved to `hash` and `block`
+fn remove_decoded_fields(input: &mut HashMap<String, Value>) {
+    for (moved, raw) in [("hash", "transactionHash"), ("block", "blockNumber")] {
+        if let Option::Some(value) = input.remove(moved) {
+            input.entry(raw.to_string()).or_insert(value);
+        }
+    }
+    for key in ["eventName", "signature", "signatureSource", "arguments", "decodedEvent", "decodeError"] {
+        input.remove(key);
+    }
+}
+
+// encode the topics and data of a log of the named event, overloads are told apart by the record's
+// signature and otherwise by which of them the arguments can be encoded as
+fn encode_log(
+    params: &Parameters,
+    name: &str,
+    signature: Option<&str>,
+    arguments: &Value,
+) -> Result<(Vec<String>, String), ModuleError> {
+    let empty: Vec<Value> = Vec::new();
+    let candidates: Vec<&Value> = params.events
+        .get(name)
+        .unwrap_or(&empty)
+        .iter()
+        .filter(|item| match signature {
+            Option::Some(sig) => sig.replace(' ', "") == event_signature(item),
+            Option::None => true,
+        })
+        .collect();
+
+    let mut failure = ModuleError::EventNotFoundError { name: signature.unwrap_or(name).to_string() };
+    for (i, item) in candidates.iter().enumerate() {
+        match encode_event(item, arguments) {
+            Ok(encoded) => return Ok(encoded),
+            // the failure reported is that of the first candidate
+            Err(e) if i == 0 => failure = e,
+            Err(_) => {}
+        }
+    }
+    Err(failure)
+}
+
+// encode a log of an abi event, the indexed arguments become topics after the signature hash, which
+// anonymous events leave out, and the others are abi encoded together as the data
+fn encode_event(item: &Value, arguments: &Value) -> Result<(Vec<String>, String), ModuleError> {
+    let sig = event_signature(item);
+    let empty: Vec<Value> = Vec::new();
+    let inputs = item["inputs"].as_array().unwrap_or(&empty);
+    let is_indexed = |input_item: &Value| input_item["indexed"].as_bool().unwrap_or(false);
+
+    let mut topics = Vec::new();
+    if item["anonymous"] != true {
+        topics.push(signature_hash(&sig));
+    }
+
+    // decode_event lists the indexed arguments first, then the others, each in abi order
+    let indexed_count = inputs.iter().filter(|i| is_indexed(i)).count();
+    let mut positions = (0, indexed_count);
+
+    let mut data_types = Vec::new();
+    let mut data_values = Vec::new();
+    for input_item in inputs.iter() {
+        let name = input_item["name"].as_str().unwrap_or_default();
+        let position = match is_indexed(input_item) {
+            true => &mut positions.0,
+            false => &mut positions.1,
+        };
+        let value = argument_value(arguments, name, *position).ok_or(ModuleError::EncodeError {
+            reason: format!("argument `{}` of {} is missing", component_key(*position, name), sig),
+        })?;
+        *position += 1;
+
+        let param = ParamType::from_abi(input_item);
+        let failed = |e: ModuleError| match e {
+            ModuleError::EncodeError { reason } => ModuleError::EncodeError {
+                reason: format!("argument `{}` of {}: {}", name, sig, reason),
+            },
+            e => e,
+        };
+        match is_indexed(input_item) {
+            true => topics.push(format!("0x{}", hex::encode(encode_topic(&param, value).map_err(failed)?))),
+            false => {
+                data_types.push(param);
+                data_values.push(value);
+            }
+        }
+    }
+
+    let data = encode_tuple(&data_types.iter().collect::<Vec<&ParamType>>(), &data_values).map_err(|e| match e {
+        ModuleError::EncodeError { reason } => ModuleError::EncodeError { reason: format!("{}: {}", sig, reason) },
+        e => e,
+    })?;
+    Ok((topics, format!("0x{}", hex::encode(data))))
+}
+
+// the value of an argument, from an array of decoded arguments or a map of name to value, found by
+// its name or, for unnamed arguments, at its position in the decoded arguments
+fn argument_value<'a>(arguments: &'a Value, name: &str, position: usize) -> Option<&'a Value> {
+    match arguments {
+        Value::Array(items) => match name.is_empty() {
+            false => items.iter().find(|a| a["name"] == name),
+            true => items.get(position),
+        }
+        .and_then(|a| a.get("value")),
+        Value::Object(map) => map.get(&component_key(position, name)),
+        _ => Option::None,
+    }
+}
+
+// the topic of an indexed argument, value types are stored as their 32-byte word and reference types
+// (string, bytes, arrays and tuples) as the keccak hash of their encoding, the decoded `valueHash`
+// of a reference type is stored as it is since its value can't be recovered from the log
+fn encode_topic(param: &ParamType, value: &Value) -> Result<Vec<u8>, ModuleError> {
+    if let ParamType::Elementary(typ) = param {
+        if typ != "string" && typ != "bytes" {
+            return Ok(encode_word(typ, value)?.to_vec());
+        }
+    }
+    if let Option::Some(hash) = value.get("valueHash").and_then(|v| v.as_str()) {
+        return match hex_bytes(hash) {
+            Option::Some(bytes) if bytes.len() == 32 => Ok(bytes),
+            _ => Err(ModuleError::EncodeError { reason: format!("the value hash {} is not 32 bytes of hex", hash) }),
+        };
+    }
+    let mut hasher = Keccak256::new();
+    hasher.update(encode_in_place(param, value)?);
+    Ok(hasher.finalize().to_vec())
+}
+
+// the encoding hashed into the topic of an indexed reference type, strings and bytes are their raw
+// content and the members of arrays and tuples are concatenated, each padded to whole 32-byte
+// words, without any offset or length
+fn encode_in_place(param: &ParamType, value: &Value) -> Result<Vec<u8>, ModuleError> {
+    match param {
+        ParamType::Elementary(typ) if typ == "string" || typ == "bytes" => Ok(dynamic_bytes(typ, value)?),
+        ParamType::Elementary(typ) => Ok(encode_word(typ, value)?.to_vec()),
+        ParamType::Array(elem, len) => {
+            let items = array_items(value, *len)?;
+            let mut encoded = Vec::new();
+            for item in items.iter() {
+                let mut member = encode_in_place(elem, item)?;
+                if param_is_bytes(elem) {
+                    pad_right(&mut member);
+                }
+                encoded.extend(member);
+            }
+            Ok(encoded)
+        }
+        ParamType::Tuple(components) => {
+            let values = tuple_values(components, value)?;
+            let mut encoded = Vec::new();
+            for ((_, component), value) in components.iter().zip(values) {
+                let mut member = encode_in_place(component, value)?;
+                if param_is_bytes(component) {
+                    pad_right(&mut member);
+                }
+                encoded.extend(member);
+            }
+            Ok(encoded)
+        }
+    }
+}
+
+fn param_is_bytes(param: &ParamType) -> bool {
+    matches!(param, ParamType::Elementary(typ) if typ == "string" || typ == "bytes")
+}
+
+// abi encode values as a tuple, static members are encoded in the head and dynamic ones in the tail
+// with their head slot holding their offset from the start of the tuple
+fn encode_tuple(types: &[&ParamType], values: &[&Value]) -> Result<Vec<u8>, ModuleError> {
+    let head_size: usize = types.iter().map(|t| t.head_size()).sum();
+    let mut head = Vec::new();
+    let mut tail = Vec::new();
+    for (param, value) in types.iter().zip(values.iter()) {
+        let encoded = encode_value(param, value)?;
+        match param.is_dynamic() {
+            true => {
+                head.extend(U256::from_u64((head_size + tail.len()) as u64).to_be_bytes());
+                tail.extend(encoded);
+            }
+            false => head.extend(encoded),
+        }
+    }
+    head.extend(tail);
+    Ok(head)
+}
+
+// abi encode a value of type `param`, dynamic values are given as they are encoded in the tail
+fn encode_value(param: &ParamType, value: &Value) -> Result<Vec<u8>, ModuleError> {
+    match param {
+        ParamType::Elementary(typ) if typ == "string" || typ == "bytes" => {
+            // the length in bytes followed by the content padded to whole words
+            let mut content = dynamic_bytes(typ, value)?;
+            let mut encoded = U256::from_u64(content.len() as u64).to_be_bytes().to_vec();
+            pad_right(&mut content);
+            encoded.extend(content);
+            Ok(encoded)
+        }
+        ParamType::Elementary(typ) => Ok(encode_word(typ, value)?.to_vec()),
+        ParamType::Array(elem, len) => {
+            let items = array_items(value, *len)?;
+            let types = vec![elem.as_ref(); items.len()];
+            let encoded = encode_tuple(&types, &items.iter().collect::<Vec<&Value>>())?;
+            match len {
+                // dynamic arrays are prefixed with their length
+                Option::None => {
+                    let mut prefixed = U256::from_u64(items.len() as u64).to_be_bytes().to_vec();
+                    prefixed.extend(encoded);
+                    Ok(prefixed)
+                }
+                Option::Some(_) => Ok(encoded),
+            }
+        }
+        ParamType::Tuple(components) => {
+            let types: Vec<&ParamType> = components.iter().map(|(_, c)| c).collect();
+            encode_tuple(&types, &tuple_values(components, value)?)
+        }
+    }
+}
+
+// the items of an array value, which must have the length of a fixed array type
+fn array_items(value: &Value, len: Option<usize>) -> Result<Vec<Value>, ModuleError> {
+    let items = value.as_array().ok_or(ModuleError::EncodeError { reason: format!("expected an array, got {}", value) })?;
+    match len {
+        Option::Some(k) if items.len() != k => Err(ModuleError::EncodeError {
+            reason: format!("expected an array of {} items, got {}", k, items.len()),
+        }),
+        _ => Ok(items.clone()),
+    }
+}
+
+// the member values of a tuple, an object keyed as decode_event keys them or an array in order
+fn tuple_values<'a>(components: &[(String, ParamType)], value: &'a Value) -> Result<Vec<&'a Value>, ModuleError> {
+    match value {
+        Value::Object(members) => components
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| {
+                let key = component_key(i, name);
+                members.get(&key).ok_or(ModuleError::EncodeError { reason: format!("the tuple member `{}` is missing", key) })
+            })
+            .collect(),
+        Value::Array(members) if members.len() == components.len() => Ok(members.iter().collect()),
+        _ => Err(ModuleError::EncodeError {
+            reason: format!("expected a tuple of {} members, got {}", components.len(), value),
+        }),
+    }
+}
+
+// the content of a `string`, as utf-8, or of `bytes`, from 0x-prefixed hex
+fn dynamic_bytes(typ: &str, value: &Value) -> Result<Vec<u8>, ModuleError> {
+    let text = value.as_str().ok_or(ModuleError::EncodeError { reason: format!("expected a {} string, got {}", typ, value) })?;
+    match typ {
+        "string" => Ok(text.as_bytes().to_vec()),
+        _ => hex_bytes(text).ok_or(ModuleError::EncodeError { reason: format!("the bytes {} are not hex", text) }),
+    }
+}
+
+// zero pad bytes on the right to whole 32-byte words
+fn pad_right(bytes: &mut Vec<u8>) {
+    let padded = bytes.len().div_ceil(32) * 32;
+    bytes.resize(padded, 0);
+}
+
+// decode a 0x-prefixed hex string, the prefix is optional
+fn hex_bytes(text: &str) -> Option<Vec<u8>> {
+    let clean = text.trim();
+    hex::decode(clean.strip_prefix("0x").unwrap_or(clean)).ok()
+}
+
+// encode a value of a static elementary type into its 32-byte word
+fn encode_word(typ: &str, value: &Value) -> Result<[u8; 32], ModuleError> {
+    let invalid = || ModuleError::EncodeError { reason: format!("{} is not a valid {}", value, typ) };
+    let mut word = [0u8; 32];
+    match typ {
+        "address" => {
+            let bytes = value.as_str().and_then(hex_bytes).filter(|b| b.len() == 20).ok_or_else(invalid)?;
+            word[12..].copy_from_slice(&bytes);
+        }
+        "bool" => {
+            let flag = match value {
+                Value::Bool(flag) => *flag,
+                Value::String(s) if s == "true" || s == "false" => s == "true",
+                _ => return Err(invalid()),
+            };
+            word[31] = flag as u8;
+        }
+        // an external function is a 20-byte address followed by a 4-byte selector, left-aligned
+        "function" => {
+            let bytes = match value {
+                Value::Object(function) => {
+                    let joined = format!(
+                        "{}{}",
+                        function.get("address").and_then(|v| v.as_str()).unwrap_or_default().trim_start_matches("0x"),
+                        function.get("selector").and_then(|v| v.as_str()).unwrap_or_default().trim_start_matches("0x"),
+                    );
+                    hex_bytes(&joined)
+                }
+                _ => value.as_str().and_then(hex_bytes),
+            };
+            let bytes = bytes.filter(|b| b.len() == 24).ok_or_else(invalid)?;
+            word[..24].copy_from_slice(&bytes);
+        }
+        _ => {
+            if let Option::Some(width) = bytes_width(typ) {
+                let bytes = value.as_str().and_then(hex_bytes).filter(|b| b.len() == width).ok_or_else(invalid)?;
+                word[..width].copy_from_slice(&bytes);
+                return Ok(word);
+            }
+
+            let (signed, bits, decimals) = match (int_bits(typ, "uint"), int_bits(typ, "int"), parse_fixed(typ)) {
+                (Option::Some(bits), _, _) => (false, bits, 0),
+                (_, Option::Some(bits), _) => (true, bits, 0),
+                (_, _, Option::Some(fixed)) => fixed,
+                _ => return Err(ModuleError::EncodeError { reason: format!("the type {} is not supported", typ) }),
+            };
+            let (negative, magnitude) = integer(value, decimals).ok_or_else(invalid)?;
+
+            // the word is the two's complement of negative values, the bits above the width of the
+            // type must all repeat its sign bit, or be zero for unsigned types
+            let negative = negative && !magnitude.is_zero();
+            let integer = match negative {
+                true => magnitude.wrapping_neg(),
+                false => magnitude,
+            };
+            let first = match signed {
+                true => bits - 1,
+                false => bits,
+            };
+            if (signed || !negative) && (first..256).all(|i| integer.bit(i) == negative) {
+                word = integer.to_be_bytes();
+            } else {
+                return Err(ModuleError::EncodeError { reason: format!("{} is out of range for {}", value, typ) });
+            }
+        }
+    }
+    Ok(word)
+}
+
+// read an integer as its sign and magnitude, from a json number or a decimal or 0x-prefixed hex
+// string, decimal fixed point values are scaled by `decimals`, hex is read as the raw integer and
+// the `{ "value", "raw" }` objects of decode_event's `both` number format by their raw hex
+fn integer(value: &Value, decimals: usize) -> Option<(bool, U256)> {
+    let text = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        Value::Object(both) => return integer(both.get("raw")?, 0),
+        _ => return Option::None,
+    };
+    let (negative, digits) = match text.strip_prefix('-') {
+        Option::Some(digits) => (true, digits),
+        Option::None => (false, text.as_str()),
+    };
+
+    if let Option::Some(hex_digits) = digits.strip_prefix("0x") {
+        // quantities are encoded without leading zeros so their digit count may be odd
+        let padded = format!("{:0>width$}", hex_digits, width = hex_digits.len() + hex_digits.len() % 2);
+        return match !hex_digits.is_empty() && padded.len() <= 64 {
+            true => hex::decode(padded).ok().map(|bytes| (negative, U256::from_be_slice(&bytes))),
+            false => Option::None,
+        };
+    }
+
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals {
+        return Option::None;
+    }
+    let scaled = format!("{}{}{}", whole, fraction, "0".repeat(decimals - fraction.len()));
+    U256::from_dec_str(&scaled).map(|n| (negative, n))
+}
+
+// build the signature string of an abi event, e.g. "Transfer(address,address,uint256)"
+fn event_signature(item: &Value) -> String {
+    let name = item["name"].as_str().unwrap_or_default();
+
+    // extract types from inputs
+    let empty: Vec<Value> = Vec::new();
+    let inputs = item["inputs"].as_array().unwrap_or(&empty);
+
+    // extract the types to be reused, tuples are spelled out in canonical form
+    let types: Vec<String> = inputs
+        .iter()
+        .map(|input| ParamType::from_abi(input).canonical())
+        .collect();
+
+    format!("{}({})", name, types.join(","))
+}
+// hash the signature, giving the topic0 of the event
+fn signature_hash(sig: &str) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(sig.as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+// normalize the accepted abi formats to a single json abi array
+fn normalize_abi(abi: &Value) -> Result<Value, ModuleError> {
+    match abi {
+        // a document encoded in a string, e.g. the raw json abi or a copy-pasted artifact
+        Value::String(document) => match serde_json::from_str::<Value>(document) {
+            Ok(abi) => normalize_abi(&abi),
+            Err(e) => Err(ModuleError::AbiFormatError { reason: e.to_string() }),
+        },
+        // hardhat and foundry artifacts carry the abi next to the bytecode and metadata
+        Value::Object(artifact) if artifact.contains_key("abi") => normalize_abi(&artifact["abi"]),
+        // the etherscan getabi response holds the abi as a json encoded string in `result`,
+        // or an error message in its place when the status is not "1"
+        Value::Object(response) if response.contains_key("status") && response.contains_key("result") => {
+            let result = response["result"].as_str().unwrap_or_default();
+            match (response["status"].as_str(), serde_json::from_str::<Value>(result)) {
+                (Option::Some("1"), Ok(abi)) => normalize_abi(&abi),
+                _ => Err(ModuleError::AbiFormatError { reason: format!("etherscan returned: {}", result) }),
+            }
+        }
+        // an abi, or a list of abi documents to merge into one table, the entries may be abi items,
+        // human-readable declarations, or whole documents in any of the accepted formats
+        Value::Array(entries) => {
+            let mut items: Vec<Value> = Vec::new();
+            for entry in entries.iter() {
+                let normalized = match entry {
+                    Value::String(s) if !s.trim_start().starts_with(['[', '{']) => {
+                        parse_declaration(s).into_iter().collect()
+                    }
+                    Value::Object(item) if !item.contains_key("abi") && !item.contains_key("result") => {
+                        vec![entry.clone()]
+                    }
+                    _ => normalize_abi(entry)?.as_array().cloned().unwrap_or_default(),
+                };
+
+                // the same event is commonly declared by several of the merged contracts
+                for item in normalized {
+                    if !items.contains(&item) {
+                        items.push(item);
+                    }
+                }
+            }
+            Ok(Value::Array(items))
+        }
+        _ => Err(ModuleError::AbiFormatError { reason: "expected a json array or object".to_string() }),
+    }
+}
+
+// parse a human-readable event declaration into its json abi form, e.g.
+// "event Transfer(address indexed from, address indexed to, uint256 value)"
+// declarations other than events are not needed to decode logs and are skipped
+fn parse_declaration(declaration: &str) -> Option<Value> {
+    let rest = declaration.trim().strip_prefix("event ")?.trim();
+    let open = rest.find('(')?;
+    let close = matching_paren(rest, open)?;
+
+    let inputs: Vec<Value> = split_components(&rest[open + 1..close])
+        .into_iter()
+        .map(parse_declared_param)
+        .collect();
+
+    Option::Some(serde_json::json!({
+        "type": "event",
+        "name": rest[..open].trim(),
+        "inputs": inputs,
+        "anonymous": rest[close + 1..].trim() == "anonymous",
+    }))
+}
+
+// parse a declared parameter such as `address indexed from` or `(uint256 id, address to)[] orders`
+fn parse_declared_param(param: &str) -> Value {
+    let param = param.trim();
+    let (typ, components, rest) = match param.strip_prefix("tuple").unwrap_or(param).starts_with('(') {
+        true => {
+            let open = param.find('(').unwrap_or(0);
+            let close = matching_paren(param, open).unwrap_or(param.len() - 1);
+            let components: Vec<Value> = split_components(&param[open + 1..close])
+                .into_iter()
+                .map(parse_declared_param)
+                .collect();
+
+            // the array suffix directly follows the closing parenthesis
+            let after = &param[close + 1..];
+            let suffix_len = after.find(|c: char| c.is_whitespace()).unwrap_or(after.len());
+            (format!("tuple{}", &after[..suffix_len]), Option::Some(components), &after[suffix_len..])
+        }
+        false => {
+            let typ_len = param.find(|c: char| c.is_whitespace()).unwrap_or(param.len());
+            (param[..typ_len].to_string(), Option::None, &param[typ_len..])
+        }
+    };
+
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let indexed = words.contains(&"indexed");
+    let name = words.iter().find(|w| **w != "indexed").copied().unwrap_or_default();
+
+    let mut value = serde_json::json!({
+        "type": typ,
+        "name": name,
+        "indexed": indexed,
+    });
+    if let Option::Some(components) = components {
+        value["components"] = Value::Array(components);
+    }
+    value
+}
+
+// position of the parenthesis closing the one at `open`
+fn matching_paren(s: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices().skip_while(|(i, _)| *i < open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Option::Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    Option::None
+}
+
+// recursive model of an abi type, built from an abi input or tuple component
+#[derive(Clone, Debug)]
+enum ParamType {
+    Elementary(String),
+    Array(Box<ParamType>, Option<usize>),
+    Tuple(Vec<(String, ParamType)>),
+}
+
+impl ParamType {
+    fn from_abi(param: &Value) -> ParamType {
+        let typ = param["type"].as_str().unwrap_or_default();
+        ParamType::from_type(typ, param)
+    }
+
+    // `param` carries the `components` used when the innermost type is a tuple
+    fn from_type(typ: &str, param: &Value) -> ParamType {
+        if let Option::Some((elem, len)) = parse_array(typ) {
+            return ParamType::Array(Box::new(ParamType::from_type(elem, param)), len);
+        }
+
+        if typ == "tuple" {
+            let empty: Vec<Value> = Vec::new();
+            let components = param["components"]
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .map(|c| (c["name"].as_str().unwrap_or_default().to_string(), ParamType::from_abi(c)))
+                .collect();
+            return ParamType::Tuple(components);
+        }
+
+        // tuples spelled out inline carry no component names, e.g. `tuple(uint256[],address)`
+        if let Option::Some(inner) = typ.strip_prefix("tuple").unwrap_or(typ).strip_prefix('(') {
+            if let Option::Some(inner) = inner.strip_suffix(')') {
+                let components = split_components(inner)
+                    .into_iter()
+                    .map(|c| (String::new(), ParamType::from_type(c, &Value::Null)))
+                    .collect();
+                return ParamType::Tuple(components);
+            }
+        }
+
+        ParamType::Elementary(typ.to_string())
+    }
+
+    // the form used in event signatures, e.g. `(uint256,address)[]`
+    fn canonical(&self) -> String {
+        match self {
+            ParamType::Elementary(typ) => match typ.as_str() {
+                // aliases are hashed under their explicit width
+                "uint" | "int" => format!("{}256", typ),
+                "fixed" | "ufixed" => format!("{}128x18", typ),
+                _ => typ.clone(),
+            },
+            ParamType::Array(elem, Option::None) => format!("{}[]", elem.canonical()),
+            ParamType::Array(elem, Option::Some(k)) => format!("{}[{}]", elem.canonical(), k),
+            ParamType::Tuple(components) => {
+                let types: Vec<String> = components.iter().map(|(_, c)| c.canonical()).collect();
+                format!("({})", types.join(","))
+            }
+        }
+    }
+
+    // whether the type is encoded in the tail with its head slot holding an offset
+    fn is_dynamic(&self) -> bool {
+        match self {
+            ParamType::Elementary(typ) => typ == "string" || typ == "bytes",
+            ParamType::Array(_, Option::None) => true,
+            ParamType::Array(elem, Option::Some(_)) => elem.is_dynamic(),
+            ParamType::Tuple(components) => components.iter().any(|(_, c)| c.is_dynamic()),
+        }
+    }
+
+    // number of bytes the type occupies in the head
+    fn head_size(&self) -> usize {
+        if self.is_dynamic() {
+            return 32;
+        }
+        match self {
+            ParamType::Array(elem, Option::Some(k)) => k * elem.head_size(),
+            ParamType::Tuple(components) => components.iter().map(|(_, c)| c.head_size()).sum(),
+            _ => 32,
+        }
+    }
+}
+
+
+// the key of a decoded tuple member, its name from the abi `components` or `field<position>` for unnamed members
+fn component_key(index: usize, name: &str) -> String {
+    match name.is_empty() {
+        true => format!("field{}", index),
+        false => name.to_string(),
+    }
+}
+
+// split the inside of an inline tuple on its top-level commas
+// e.g. `uint256,(address,bool)[]` -> [`uint256`, `(address,bool)[]`]
+fn split_components(inner: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !inner[start..].trim().is_empty() {
+        parts.push(inner[start..].trim());
+    }
+    parts
+}
+
+// split an array type into its element type and length, `None` for dynamic arrays
+// e.g. `uint256[3][]` -> (`uint256[3]`, None)
+fn parse_array(typ: &str) -> Option<(&str, Option<usize>)> {
+    let body = typ.strip_suffix(']')?;
+    let open = body.rfind('[')?;
+    let len = &body[open + 1..];
+    if len.is_empty() {
+        return Option::Some((&body[..open], Option::None));
+    }
+    len.parse().ok().map(|k| (&body[..open], Option::Some(k)))
+}
+
+// width of an `uintN`/`intN` type, 8 to 256 in steps of 8, a bare `uint`/`int` is 256 bits
+fn int_bits(typ: &str, prefix: &str) -> Option<usize> {
+    let width = typ.strip_prefix(prefix)?;
+    if width.is_empty() {
+        return Option::Some(256);
+    }
+    match width.parse::<usize>() {
+        Ok(bits) if (8..=256).contains(&bits) && bits.is_multiple_of(8) => Option::Some(bits),
+        _ => Option::None,
+    }
+}
+
+// signedness, width and decimals of a `fixedMxN`/`ufixedMxN` type
+// M is 8 to 256 in steps of 8 and N is 1 to 80, a bare `fixed`/`ufixed` is `fixed128x18`
+fn parse_fixed(typ: &str) -> Option<(bool, usize, usize)> {
+    let (signed, rest) = match typ.strip_prefix("ufixed") {
+        Option::Some(rest) => (false, rest),
+        Option::None => (true, typ.strip_prefix("fixed")?),
+    };
+    if rest.is_empty() {
+        return Option::Some((signed, 128, 18));
+    }
+
+    let (bits, decimals) = rest.split_once('x')?;
+    let bits: usize = bits.parse().ok()?;
+    let decimals: usize = decimals.parse().ok()?;
+    match (8..=256).contains(&bits) && bits.is_multiple_of(8) && (1..=80).contains(&decimals) {
+        true => Option::Some((signed, bits, decimals)),
+        false => Option::None,
+    }
+}
+
+// width of a fixed `bytesN` type, 1 to 32
+fn bytes_width(typ: &str) -> Option<usize> {
+    match typ.strip_prefix("bytes")?.parse::<usize>() {
+        Ok(width) if (1..=32).contains(&width) => Option::Some(width),
+        _ => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    fn from_u64(n: u64) -> U256 {
+        U256([n, 0, 0, 0])
+    }
+
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // two's complement negation modulo 2^256
+    fn wrapping_neg(self) -> U256 {
+        let mut limbs = [0u64; 4];
+        let mut carry = true;
+        for (i, l) in self.0.iter().enumerate() {
+            let (v, overflow) = (!l).overflowing_add(carry as u64);
+            limbs[i] = v;
+            carry = overflow;
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            let mut carry = c.to_digit(10)? as u128;
+            for limb in n.0.iter_mut() {
+                let acc = *limb as u128 * 10 + carry;
+                *limb = acc as u64;
+                carry = acc >> 64;
+            }
+            if carry != 0 {
+                return Option::None;
+            }
+        }
+        Option::Some(n)
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        self.0[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    fn to_be_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (i, limb) in self.0.iter().rev().enumerate() {
+            bytes[i * 8..i * 8 + 8].copy_from_slice(&limb.to_be_bytes());
+        }
+        bytes
+    }
+}
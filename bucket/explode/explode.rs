@@ -0,0 +1,153 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::VecDeque;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the array field of a document whose items are emitted, e.g. "logs" of a receipt, inputs that are
+    // arrays themselves are always exploded
+    #[serde(default)]
+    pub field: Option<String>,
+    // fields of the document copied into each of its items that doesn't have them, e.g. ["blockNumber"]
+    #[serde(default)]
+    pub inherit: Vec<String>,
+    // the field each item gets its position in the array as, left out when not set
+    #[serde(default)]
+    pub index_field: Option<String>,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+
+    let ptr = unsafe { next() };
+    // read as any json value rather than a document since batched inputs may be bare arrays
+    let input = match lens_sdk::try_from_mem::<Value>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let empty = serde_json::Map::new();
+    let batch = match &input {
+        Value::Array(items) => Option::Some((items, &empty)),
+        Value::Object(document) => match params.field.as_ref().and_then(|f| document.get(f)) {
+            Option::Some(Value::Array(items)) => Option::Some((items, document)),
+            _ => Option::None,
+        },
+        _ => Option::None,
+    };
+    // documents without the array are not batches and are passed through as they are
+    let (items, parent) = match batch {
+        Option::Some(batch) => batch,
+        Option::None => {
+            let result_json = serde_json::to_vec(&input)?;
+            lens_sdk::free_transport_buffer(ptr)?;
+            return Ok(Some(result_json));
+        }
+    };
+
+    let mut documents = VecDeque::new();
+    for (i, item) in items.iter().enumerate() {
+        // items that aren't documents, e.g. the hashes of a block's transactions, are wrapped in one
+        let mut document = match item {
+            Value::Object(document) => document.clone(),
+            _ => serde_json::Map::from_iter([("value".to_string(), item.clone())]),
+        };
+        for key in params.inherit.iter() {
+            if let (false, Option::Some(value)) = (document.contains_key(key), parent.get(key)) {
+                document.insert(key.clone(), value.clone());
+            }
+        }
+        if let Option::Some(index_field) = &params.index_field {
+            document.insert(index_field.clone(), Value::from(i));
+        }
+        documents.push_back(serde_json::to_vec(&document)?);
+    }
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    // an empty batch has nothing to emit
+    let first = documents.pop_front();
+    PENDING.write()?.extend(documents);
+    match first {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
@@ -0,0 +1,158 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the accepted values of each topic position, a log passes when each position with values holds one
+// of them, positions left empty accept any topic and a log lacking a topic at a position with values
+// fails it, e.g. {"topic0": ["0xddf252ad..."], "topic2": ["0x28c6c06298d514db089934071355e5743bf21d60"]}
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    #[serde(default)]
+    pub topic0: HashSet<String>,
+    #[serde(default)]
+    pub topic1: HashSet<String>,
+    #[serde(default)]
+    pub topic2: HashSet<String>,
+    #[serde(default)]
+    pub topic3: HashSet<String>,
+    #[serde(default, rename = "match")]
+    pub match_mode: Match,
+}
+
+// how the values are compared with the topics
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Match {
+    // values are whole topics, shorter values such as addresses are left padded to 32 bytes
+    #[default]
+    Exact,
+    // values are leading hex digits of the topics, e.g. the 4-byte "0xddf252ad" of a topic0
+    Prefix,
+}
+
+// shared so that reading the parameters for each record doesn't copy the topic sets
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // values are compared as lowercase hex digits without the 0x prefix
+    let match_mode = parameter.match_mode;
+    for values in [&mut parameter.topic0, &mut parameter.topic1, &mut parameter.topic2, &mut parameter.topic3] {
+        *values = values.iter().map(|value| normalize(value, match_mode)).collect();
+    }
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(Arc::new(parameter));
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    let empty: Vec<serde_json::Value> = Vec::new();
+    let topics: Vec<String> = input
+        .get("topics")
+        .and_then(|v| v.as_array())
+        .unwrap_or(&empty)
+        .iter()
+        .map(|t| normalize(t.as_str().unwrap_or_default(), Match::Exact))
+        .collect();
+
+    let accepted = [&params.topic0, &params.topic1, &params.topic2, &params.topic3]
+        .iter()
+        .enumerate()
+        .filter(|(_, values)| !values.is_empty())
+        .all(|(i, values)| match (topics.get(i), params.match_mode) {
+            (Option::Some(topic), Match::Exact) => values.contains(topic),
+            (Option::Some(topic), Match::Prefix) => values.iter().any(|prefix| topic.starts_with(prefix.as_str())),
+            (Option::None, _) => false,
+        });
+
+    // dropped records are skipped by moving on to the next input
+    if !accepted {
+        lens_sdk::free_transport_buffer(ptr)?;
+        return try_transform();
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// a topic or a value as lowercase hex digits without the 0x prefix, left padded to the 64 digits of a
+// topic unless it is a prefix
+fn normalize(value: &str, match_mode: Match) -> String {
+    let value = value.trim().to_lowercase();
+    let digits = value.strip_prefix("0x").unwrap_or(&value);
+    match match_mode {
+        Match::Exact => format!("{:0>64}", digits),
+        Match::Prefix => digits.to_string(),
+    }
+}
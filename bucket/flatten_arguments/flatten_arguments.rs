@@ -0,0 +1,179 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the field holding the `{name, type, value}` arguments, as emitted by decode_event and decode_function_call
+    #[serde(default = "field_default")]
+    pub field: String,
+    // prepended to the argument names to form the field names
+    #[serde(default = "prefix_default")]
+    pub prefix: String,
+    // keep the arguments array next to the flattened fields
+    #[serde(default)]
+    pub keep_arguments: bool,
+}
+
+fn field_default() -> String {
+    "arguments".to_string()
+}
+
+fn prefix_default() -> String {
+    "arg_".to_string()
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            field: field_default(),
+            prefix: prefix_default(),
+            keep_arguments: false,
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // records without arguments, e.g. of logs that weren't decoded, are passed through as they are
+    let arguments = match input.get(&params.field) {
+        Option::Some(Value::Array(arguments)) => arguments.clone(),
+        _ => {
+            let result_json = serde_json::to_vec(&input)?;
+            lens_sdk::free_transport_buffer(ptr)?;
+            return Ok(Some(result_json));
+        }
+    };
+    if !params.keep_arguments {
+        input.remove(&params.field);
+    }
+
+    for (i, argument) in arguments.iter().enumerate() {
+        // unnamed arguments are named by their position
+        let name = match argument["name"].as_str().unwrap_or_default() {
+            "" => i.to_string(),
+            name => name.to_string(),
+        };
+        let key = free_key(&input, format!("{}{}", params.prefix, name));
+        flatten(&mut input, &key, &argument["value"]);
+
+        // the enum labels and scaled amounts of decode_event go next to their argument
+        for extra in ["label", "scaled"] {
+            if let Option::Some(value) = argument.get(extra) {
+                let extra_key = free_key(&input, format!("{}_{}", key, extra));
+                input.insert(extra_key, value.clone());
+            }
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// insert a value under `key`, tuples are objects whose components are flattened in turn under
+// `key_component`, arrays are kept whole
+fn flatten(input: &mut HashMap<String, Value>, key: &str, value: &Value) {
+    match value {
+        Value::Object(components) => {
+            for (name, component) in components.iter() {
+                let component_key = free_key(input, format!("{}_{}", key, name));
+                flatten(input, &component_key, component);
+            }
+        }
+        _ => {
+            input.insert(key.to_string(), value.clone());
+        }
+    }
+}
+
+// `key` if the record has no such field yet, otherwise the first of `key_2`, `key_3`, ... that is free,
+// so arguments sharing a name, or a name clashing with a field of the record, overwrite nothing
+fn free_key(input: &HashMap<String, Value>, key: String) -> String {
+    if !input.contains_key(&key) {
+        return key;
+    }
+    (2..)
+        .map(|n| format!("{}_{}", key, n))
+        .find(|candidate| !input.contains_key(candidate))
+        .unwrap_or(key)
+}
@@ -0,0 +1,313 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the names of the top-level quantity fields, the quantities of the json-rpc blocks, transactions,
+    // receipts and logs by default, hashes, addresses and other data are never touched, nor are the
+    // fields of nested objects where a `value` or `type` may well be data
+    #[serde(default = "fields_default")]
+    pub fields: HashSet<String>,
+    #[serde(default)]
+    pub format: Format,
+    // the width in bytes hex quantities are zero padded to
+    #[serde(default = "width_default")]
+    pub width: usize,
+}
+
+// how the quantities are written whatever their encoding was, a json number, a decimal string or a
+// 0x-prefixed hex quantity
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    // decimal strings, e.g. "21000"
+    #[default]
+    Decimal,
+    // 0x-prefixed hex zero padded to `width` bytes, e.g. "0x0000...5208"
+    Hex,
+}
+
+// the quantity fields of the json-rpc block, transaction, receipt, log and withdrawal objects, `nonce`
+// is left out as the nonce of a block is 8 bytes of data rather than a quantity
+const QUANTITY_FIELDS: [&str; 30] = [
+    "number", "blockNumber", "transactionIndex", "logIndex", "gas", "gasPrice", "gasUsed",
+    "cumulativeGasUsed", "gasLimit", "value", "maxFeePerGas", "maxPriorityFeePerGas", "maxFeePerBlobGas",
+    "baseFeePerGas", "effectiveGasPrice", "blobGasUsed", "blobGasPrice", "excessBlobGas", "timestamp",
+    "difficulty", "totalDifficulty", "size", "status", "type", "chainId", "v", "yParity", "index",
+    "validatorIndex", "amount",
+];
+
+fn fields_default() -> HashSet<String> {
+    QUANTITY_FIELDS.iter().map(|f| f.to_string()).collect()
+}
+
+fn width_default() -> usize {
+    32
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            fields: fields_default(),
+            format: Format::default(),
+            width: width_default(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    for (key, value) in input.iter_mut() {
+        match key.as_str() {
+            "arguments" => normalize_arguments(&params, value),
+            _ if params.fields.contains(key) => normalize(&params, value),
+            _ => {}
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// rewrite the value of a quantity field, values that aren't quantities, e.g. the `type` of a trace
+// frame, are left as they are
+fn normalize(params: &Parameters, value: &mut Value) {
+    if let Option::Some(n) = quantity(value) {
+        *value = Value::String(match params.format {
+            Format::Decimal => n.to_string(),
+            Format::Hex => format!("0x{:0>width$}", n.to_hex(), width = params.width * 2),
+        });
+    }
+}
+
+// rewrite the values of the decoded `arguments` whose abi type is an integer or an array of integers,
+// those of other types, e.g. an address or a bytes32, are data
+fn normalize_arguments(params: &Parameters, arguments: &mut Value) {
+    for argument in arguments.as_array_mut().into_iter().flatten() {
+        let typ = argument.get("type").and_then(|t| t.as_str()).unwrap_or_default();
+        if typ.starts_with("uint") || typ.starts_with("int") {
+            if let Option::Some(value) = argument.get_mut("value") {
+                normalize_integers(params, value);
+            }
+        }
+    }
+}
+
+// rewrite an integer argument value, or the items of an integer array
+fn normalize_integers(params: &Parameters, value: &mut Value) {
+    match value {
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                normalize_integers(params, item);
+            }
+        }
+        _ => normalize(params, value),
+    }
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from_u64),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => {
+                // quantities are encoded without leading zeros so their digit count may be odd
+                let padded = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+                match padded.len() <= 64 {
+                    true => hex::decode(padded).ok().map(|bytes| U256::from_be_slice(&bytes)),
+                    false => Option::None,
+                }
+            }
+            Option::None => U256::from_dec_str(s.trim()),
+        },
+        _ => Option::None,
+    }
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    fn from_u64(n: u64) -> U256 {
+        U256([n, 0, 0, 0])
+    }
+
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    // parse a decimal string of digits, None if it is empty, not decimal or overflows 256 bits
+    fn from_dec_str(digits: &str) -> Option<U256> {
+        if digits.is_empty() {
+            return Option::None;
+        }
+        let mut n = U256::default();
+        for c in digits.chars() {
+            n = n.checked_mul_u64(10)?.checked_add_u64(c.to_digit(10)? as u64)?;
+        }
+        Option::Some(n)
+    }
+
+    // the hex digits without leading zeros, "0" for zero
+    fn to_hex(self) -> String {
+        let digits: String = self.0.iter().rev().map(|l| format!("{:016x}", l)).collect();
+        match digits.trim_start_matches('0') {
+            "" => "0".to_string(),
+            digits => digits.to_string(),
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    fn checked_mul_u64(self, factor: u64) -> Option<U256> {
+        let mut limbs = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let acc = self.0[i] as u128 * factor as u128 + carry;
+            *limb = acc as u64;
+            carry = acc >> 64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    fn checked_add_u64(self, term: u64) -> Option<U256> {
+        let mut limbs = self.0;
+        let mut carry = term;
+        for limb in limbs.iter_mut() {
+            let (v, overflow) = limb.overflowing_add(carry);
+            *limb = v;
+            carry = overflow as u64;
+        }
+        match carry {
+            0 => Option::Some(U256(limbs)),
+            _ => Option::None,
+        }
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}
@@ -0,0 +1,219 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // emit the transaction and receipt records too instead of consuming them
+    #[serde(default)]
+    pub keep_transactions: bool,
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// records produced by an earlier input that are still to be emitted, one per transform call
+static PENDING: RwLock<VecDeque<Vec<u8>>> = RwLock::new(VecDeque::new());
+
+// set once the input has ended, so that the end of stream is passed on after the pending records
+static ENDED: RwLock<bool> = RwLock::new(false);
+
+// the block being read, the context of each of its transactions and its logs waiting for them
+struct Block {
+    number: u64,
+    contexts: HashMap<String, serde_json::Map<String, Value>>,
+    logs: Vec<HashMap<String, Value>>,
+}
+
+static BLOCK: RwLock<Option<Block>> = RwLock::new(Option::None);
+
+// the transaction fields each log is enriched with, the gas used is only known from a receipt
+const CONTEXT_FIELDS: [(&str, &str); 4] = [
+    ("from", "transactionFrom"),
+    ("to", "transactionTo"),
+    ("value", "transactionValue"),
+    ("gasUsed", "transactionGasUsed"),
+];
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if let Option::Some(result_json) = PENDING.write()?.pop_front() {
+        return Ok(Some(result_json));
+    }
+    if *ENDED.read()? {
+        *ENDED.write()? = false;
+        return Ok(EndOfStream);
+    }
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        // the logs of the last block are emitted before the end of stream
+        EndOfStream => {
+            let mut documents = flush(BLOCK.write()?.take())?;
+            return Ok(match documents.pop_front() {
+                Option::Some(result_json) => {
+                    PENDING.write()?.extend(documents);
+                    *ENDED.write()? = true;
+                    Some(result_json)
+                }
+                Option::None => EndOfStream,
+            });
+        }
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // logs carry topics, transactions their own `hash` and receipts the `transactionHash` along with
+    // their gas, other records are passed through as they are
+    let is_log = input.contains_key("topics");
+    let context_hash = match is_log {
+        true => Option::None,
+        false => input
+            .get("hash")
+            .filter(|_| input.contains_key("nonce") || input.contains_key("input"))
+            .or(input.get("transactionHash").filter(|_| input.contains_key("cumulativeGasUsed")))
+            .and_then(|h| h.as_str())
+            .map(|h| h.trim().to_lowercase()),
+    };
+    let number = input.get("blockNumber").and_then(quantity);
+
+    let mut documents = VecDeque::new();
+    match (number, is_log || context_hash.is_some()) {
+        // pending logs and transactions belong to no block, and so can't be joined
+        (Option::Some(number), true) => {
+            // the logs of a block are complete once the records of a later block arrive
+            let mut block = BLOCK.write()?;
+            if block.as_ref().is_some_and(|b| b.number != number) {
+                documents.extend(flush(block.take())?);
+            }
+            let block = block.get_or_insert_with(|| Block { number, contexts: HashMap::new(), logs: Vec::new() });
+
+            match context_hash {
+                Option::Some(hash) => {
+                    // a transaction and its receipt complete each other, the first non-null value is kept
+                    let context = block.contexts.entry(hash).or_default();
+                    for (field, _) in CONTEXT_FIELDS.iter() {
+                        if context.get(*field).unwrap_or(&Value::Null).is_null() {
+                            context.insert(field.to_string(), input.get(*field).cloned().unwrap_or(Value::Null));
+                        }
+                    }
+                    if params.keep_transactions {
+                        documents.push_back(serde_json::to_vec(&input)?);
+                    }
+                }
+                Option::None => block.logs.push(input),
+            }
+        }
+        _ => documents.push_back(serde_json::to_vec(&input)?),
+    }
+    lens_sdk::free_transport_buffer(ptr)?;
+
+    let first = documents.pop_front();
+    PENDING.write()?.extend(documents);
+    match first {
+        Option::Some(result_json) => Ok(Some(result_json)),
+        Option::None => try_transform(),
+    }
+}
+
+// the logs of a finished block, each with the context of its transaction, null when the block's
+// records held no transaction of that hash
+fn flush(block: Option<Block>) -> Result<VecDeque<Vec<u8>>, serde_json::Error> {
+    let block = match block {
+        Option::Some(block) => block,
+        Option::None => return Ok(VecDeque::new()),
+    };
+
+    let mut documents = VecDeque::new();
+    for mut log in block.logs {
+        let hash = log.get("transactionHash").and_then(|h| h.as_str()).map(|h| h.trim().to_lowercase());
+        let context = hash.and_then(|h| block.contexts.get(&h));
+        for (field, key) in CONTEXT_FIELDS.iter() {
+            let value = context.and_then(|c| c.get(*field)).cloned().unwrap_or(Value::Null);
+            log.insert(key.to_string(), value);
+        }
+        documents.push_back(serde_json::to_vec(&log)?);
+    }
+    Ok(documents)
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => u64::from_str_radix(digits, 16).ok(),
+            Option::None => s.trim().parse::<u64>().ok(),
+        },
+        _ => Option::None,
+    }
+}
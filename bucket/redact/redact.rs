@@ -0,0 +1,248 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    SaltNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::SaltNotSetError => f.write_str("A salt is required to hash the fields."),
+        }
+    }
+}
+
+// the redacted fields are field names or dotted paths into nested objects and arrays, e.g.
+// "receipt.from", where a "*" segment stands for every item of an array or field of an object, e.g.
+// "transactions.*.input", fields a record doesn't have are left out
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub method: Method,
+    // the secret the hashes are keyed with, the same value pseudonymizes to the same hash under the same salt
+    #[serde(default)]
+    pub salt: String,
+    // the value replaced fields get
+    #[serde(default = "replacement_default")]
+    pub replacement: Value,
+}
+
+fn replacement_default() -> Value {
+    Value::String("[redacted]".to_string())
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// how the fields are redacted
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    // the value is replaced by its HMAC-Keccak256 under the salt, e.g. "0x5c1a…", so pseudonymized records
+    // can still be joined and counted by the field
+    #[default]
+    Hash,
+    // the value is replaced by `replacement`
+    Replace,
+    // the field is removed
+    Remove,
+}
+
+// the block size of HMAC with keccak256 is the rate of the sponge
+const KECCAK256_BLOCK_SIZE: usize = 136;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // an unkeyed hash of an address or a hash is reversed by hashing the candidates, so hashing
+    // without a salt is refused rather than giving a false sense of privacy
+    if parameter.method == Method::Hash && parameter.salt.is_empty() {
+        return Err(ModuleError::SaltNotSetError.into());
+    }
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    let mut record = Value::Object(input.into_iter().collect());
+    for path in params.fields.iter() {
+        // a field of the record holding the whole path, dots included, is preferred over the nested lookup
+        let whole = record.get(path.as_str()).is_some();
+        match whole {
+            true => redact_field(&mut record, path, &params),
+            false => {
+                let segments: Vec<&str> = path.split('.').collect();
+                redact(&mut record, &segments, &params);
+            }
+        }
+    }
+
+    let result_json = serde_json::to_vec(&record)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// redact the values the segments lead to from `value`, numeric segments index into arrays
+fn redact(value: &mut Value, segments: &[&str], params: &Parameters) {
+    let (segment, rest) = match segments.split_first() {
+        Option::Some(split) => split,
+        Option::None => return,
+    };
+    if rest.is_empty() {
+        match *segment {
+            "*" => {
+                let keys: Vec<String> = match value {
+                    Value::Object(fields) => fields.keys().cloned().collect(),
+                    Value::Array(items) => (0..items.len()).map(|i| i.to_string()).collect(),
+                    _ => Vec::new(),
+                };
+                // removing every item of an array one at a time would shift the indexes, it is emptied instead
+                if params.method == Method::Remove && value.is_array() {
+                    *value = Value::Array(Vec::new());
+                    return;
+                }
+                for key in keys.iter() {
+                    redact_field(value, key, params);
+                }
+            }
+            _ => redact_field(value, segment, params),
+        }
+        return;
+    }
+    match (value, *segment) {
+        (Value::Object(fields), "*") => fields.values_mut().for_each(|v| redact(v, rest, params)),
+        (Value::Array(items), "*") => items.iter_mut().for_each(|v| redact(v, rest, params)),
+        (Value::Object(fields), key) => {
+            if let Option::Some(child) = fields.get_mut(key) {
+                redact(child, rest, params);
+            }
+        }
+        (Value::Array(items), index) => {
+            if let Option::Some(child) = index.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                redact(child, rest, params);
+            }
+        }
+        _ => {}
+    }
+}
+
+// redact the field `key` of an object or the item at index `key` of an array, nulls stay null
+fn redact_field(container: &mut Value, key: &str, params: &Parameters) {
+    let target = match container {
+        Value::Object(fields) => {
+            if params.method == Method::Remove {
+                fields.remove(key);
+                return;
+            }
+            fields.get_mut(key)
+        }
+        Value::Array(items) => {
+            let index = key.parse::<usize>().ok().filter(|i| *i < items.len());
+            if let (Method::Remove, Option::Some(i)) = (params.method, index) {
+                items.remove(i);
+                return;
+            }
+            index.and_then(|i| items.get_mut(i))
+        }
+        _ => Option::None,
+    };
+    match target {
+        Option::Some(value) if !value.is_null() => {
+            *value = match params.method {
+                Method::Hash => Value::String(format!("0x{}", hex::encode(hmac(params.salt.as_bytes(), canonical(value).as_bytes())))),
+                _ => params.replacement.clone(),
+            };
+        }
+        _ => {}
+    }
+}
+
+// the text a value is hashed as, hex strings are hashed regardless of case so a checksummed address
+// and its lowercase form get the same pseudonym
+fn canonical(value: &Value) -> String {
+    match value {
+        Value::String(s) if s.trim().starts_with("0x") || s.trim().starts_with("0X") => s.trim().to_lowercase(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+// HMAC as specified by RFC 2104 with keccak256, keccak256((key ^ opad) || keccak256((key ^ ipad) || message))
+fn hmac(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; KECCAK256_BLOCK_SIZE];
+    match key.len() > KECCAK256_BLOCK_SIZE {
+        true => block[..32].copy_from_slice(&Keccak256::digest(key)),
+        false => block[..key.len()].copy_from_slice(key),
+    }
+    let mut inner = Keccak256::new();
+    inner.update(block.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    inner.update(message);
+    let mut outer = Keccak256::new();
+    outer.update(block.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
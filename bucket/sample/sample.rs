@@ -0,0 +1,148 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use sha3::{Digest, Keccak256};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    RateOutOfRangeError{rate: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::RateOutOfRangeError { rate } =>
+                write!(f, "The rate must be between 0 and 1, got {}.", rate),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the fraction of records passed, e.g. 0.01 for one in a hundred
+    pub rate: f64,
+    // the fields that together identify a record, a log by default, records lacking one are sampled
+    // by their whole content instead
+    #[serde(default = "fields_default")]
+    pub fields: Vec<String>,
+    // mixed into the hash so that the same rate can select different, equally reproducible samples
+    #[serde(default)]
+    pub seed: String,
+}
+
+fn fields_default() -> Vec<String> {
+    vec!["transactionHash".to_string(), "logIndex".to_string()]
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    if !(0.0..=1.0).contains(&parameter.rate) {
+        return Err(ModuleError::RateOutOfRangeError { rate: parameter.rate.to_string() }.into());
+    }
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    let key = params.fields
+        .iter()
+        .map(|field| input.get(field).filter(|v| !v.is_null()).map(key_part))
+        .collect::<Option<Vec<String>>>();
+    // the fields of a json object serialize in sorted order, so a record's content hashes the same
+    // however it was delivered
+    let key = match key {
+        Option::Some(parts) => parts.join("|"),
+        Option::None => Value::Object(input.clone().into_iter().collect()).to_string(),
+    };
+
+    // the first 8 bytes of the hash are uniform over the u64 range, a record is passed when they fall
+    // in the lowest `rate` of it
+    let digest = Keccak256::digest(format!("{}|{}", params.seed, key).as_bytes());
+    let position = u64::from_be_bytes(digest[..8].try_into()?) as f64 / u64::MAX as f64;
+    if position >= params.rate && params.rate < 1.0 {
+        lens_sdk::free_transport_buffer(ptr)?;
+        return try_transform();
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// a key field as text, hex strings are compared regardless of case and quantities regardless of
+// their encoding, so "0x1a" and 26 are the same log index
+fn key_part(value: &Value) -> String {
+    match value {
+        Value::String(s) => {
+            let s = s.trim().to_lowercase();
+            match s.strip_prefix("0x").filter(|digits| digits.len() <= 16) {
+                Option::Some(digits) => u64::from_str_radix(digits, 16).map(|n| n.to_string()).unwrap_or(s),
+                Option::None => s,
+            }
+        }
+        _ => value.to_string(),
+    }
+}
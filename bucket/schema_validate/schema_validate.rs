@@ -0,0 +1,460 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+// the variants keep the `Error` suffix that ParametersNotSetError gives the errors of every lens
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    SchemaFormatError{reason: String},
+    ValidationError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::SchemaFormatError { reason } =>
+                write!(f, "The schema parameter could not be read. Reason: {}", reason),
+            ModuleError::ValidationError { reason } =>
+                write!(f, "The record does not conform to the schema. Reason: {}", reason),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // a json schema document, or that document encoded in a string, the records are validated against
+    pub schema: Value,
+    #[serde(default)]
+    pub action: Action,
+}
+
+// shared so that reading the parameters for each record doesn't copy the schema
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
+
+// what is done with records that don't conform to the schema
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    // the record is dropped
+    #[default]
+    Drop,
+    // the record is emitted with a `schemaErrors` field listing where and why it doesn't conform
+    Annotate,
+    // the transform returns an error for the record
+    Error,
+}
+
+// the keywords that validate but need a regular expression engine or evaluation tracking the lens
+// doesn't have, a schema using them is rejected rather than passing every record they would fail
+const UNSUPPORTED_KEYWORDS: [&str; 8] = [
+    "pattern", "patternProperties", "format", "dependentSchemas", "dependencies", "unevaluatedItems",
+    "unevaluatedProperties", "$dynamicRef",
+];
+
+// how deep subschemas and `$ref`s are followed, deeper ones are taken for a recursive schema and
+// fail the record
+const MAX_DEPTH: usize = 64;
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    if let Value::String(document) = &parameter.schema {
+        parameter.schema = serde_json::from_str::<Value>(document)
+            .map_err(|e| ModuleError::SchemaFormatError { reason: e.to_string() })?;
+    }
+    // a schema is an object of keywords, or true or false to accept or reject every record
+    if !parameter.schema.is_object() && !parameter.schema.is_boolean() {
+        return Err(ModuleError::SchemaFormatError { reason: "expected a json object or a boolean".to_string() }.into());
+    }
+    supported(&parameter.schema, "")?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(Arc::new(parameter));
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    let record = Value::Object(input.clone().into_iter().collect());
+    let mut errors = Vec::new();
+    validate(&params.schema, &params.schema, &record, "", 0, &mut errors);
+
+    if !errors.is_empty() {
+        match params.action {
+            Action::Drop => {
+                lens_sdk::free_transport_buffer(ptr)?;
+                return try_transform();
+            }
+            Action::Annotate => {
+                input.insert("schemaErrors".to_string(), serde_json::json!(errors));
+            }
+            Action::Error => {
+                lens_sdk::free_transport_buffer(ptr)?;
+                return Err(ModuleError::ValidationError { reason: errors.join("; ") }.into());
+            }
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// fail a schema using one of the unsupported keywords in itself or any subschema, `path` is the json
+// pointer of the subschema so the error tells where, e.g. "/properties/address"
+fn supported(schema: &Value, path: &str) -> Result<(), ModuleError> {
+    let keywords = match schema {
+        Value::Object(keywords) => keywords,
+        _ => return Ok(()),
+    };
+    if let Option::Some(keyword) = UNSUPPORTED_KEYWORDS.iter().find(|k| keywords.contains_key(**k)) {
+        return Err(ModuleError::SchemaFormatError {
+            reason: format!("the keyword {} at {} is not supported", keyword, if path.is_empty() { "/" } else { path }),
+        });
+    }
+
+    // only keywords holding subschemas are followed, the values of `enum` or `const` and the names of
+    // `properties` may well look like keywords
+    for (keyword, value) in keywords.iter() {
+        let child = format!("{}/{}", path, keyword);
+        match (keyword.as_str(), value) {
+            ("properties" | "$defs" | "definitions", Value::Object(subschemas)) => {
+                for (name, subschema) in subschemas.iter() {
+                    supported(subschema, &format!("{}/{}", child, name.replace('~', "~0").replace('/', "~1")))?;
+                }
+            }
+            ("allOf" | "anyOf" | "oneOf" | "prefixItems" | "items", Value::Array(subschemas)) => {
+                for (i, subschema) in subschemas.iter().enumerate() {
+                    supported(subschema, &format!("{}/{}", child, i))?;
+                }
+            }
+            ("items" | "additionalItems" | "additionalProperties" | "contains" | "propertyNames" | "not" | "if"
+                | "then" | "else", subschema) => supported(subschema, &child)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// validate `value` against `schema`, appending an error for each violation as the json pointer of the
+// offending value and the keyword it violates, e.g. "/logs/0/address: expected type string"
+// the keywords of draft 7 and 2020-12 that don't need a regular expression engine are supported,
+// set_param rejects schemas using the others
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str, depth: usize, errors: &mut Vec<String>) {
+    let at = |message: String| format!("{}: {}", if path.is_empty() { "/" } else { path }, message);
+    if depth > MAX_DEPTH {
+        errors.push(at("the schema nests too deeply or refers to itself".to_string()));
+        return;
+    }
+    let keywords = match schema {
+        Value::Bool(true) => return,
+        Value::Bool(false) => {
+            errors.push(at("no value is allowed".to_string()));
+            return;
+        }
+        Value::Object(keywords) => keywords,
+        _ => return,
+    };
+
+    if let Option::Some(reference) = keywords.get("$ref").and_then(|r| r.as_str()) {
+        match resolve(root, reference) {
+            Option::Some(target) => validate(root, target, value, path, depth + 1, errors),
+            Option::None => errors.push(at(format!("the reference {} can't be resolved", reference))),
+        }
+    }
+
+    if let Option::Some(expected) = keywords.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !types.iter().any(|t| is_type(value, t)) {
+            errors.push(at(format!("expected type {}, got {}", types.join(" or "), type_name(value))));
+        }
+    }
+    if let Option::Some(allowed) = keywords.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.iter().any(|a| equal(a, value)) {
+            errors.push(at(format!("{} is not one of the allowed values", value)));
+        }
+    }
+    if let Option::Some(constant) = keywords.get("const") {
+        if !equal(constant, value) {
+            errors.push(at(format!("expected {}, got {}", constant, value)));
+        }
+    }
+
+    match value {
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            let bound = |key: &str| keywords.get(key).and_then(|b| b.as_f64());
+            if let Option::Some(minimum) = bound("minimum").filter(|m| n < *m) {
+                errors.push(at(format!("{} is less than the minimum {}", n, minimum)));
+            }
+            if let Option::Some(maximum) = bound("maximum").filter(|m| n > *m) {
+                errors.push(at(format!("{} is greater than the maximum {}", n, maximum)));
+            }
+            if let Option::Some(minimum) = bound("exclusiveMinimum").filter(|m| n <= *m) {
+                errors.push(at(format!("{} is not greater than {}", n, minimum)));
+            }
+            if let Option::Some(maximum) = bound("exclusiveMaximum").filter(|m| n >= *m) {
+                errors.push(at(format!("{} is not less than {}", n, maximum)));
+            }
+            if let Option::Some(factor) = bound("multipleOf").filter(|f| *f > 0.0) {
+                let quotient = n / factor;
+                if (quotient - quotient.round()).abs() > 1e-9 {
+                    errors.push(at(format!("{} is not a multiple of {}", n, factor)));
+                }
+            }
+        }
+        Value::String(s) => {
+            // lengths count characters, not bytes
+            let length = s.chars().count() as u64;
+            if let Option::Some(minimum) = keywords.get("minLength").and_then(|m| m.as_u64()).filter(|m| length < *m) {
+                errors.push(at(format!("the string is shorter than {} characters", minimum)));
+            }
+            if let Option::Some(maximum) = keywords.get("maxLength").and_then(|m| m.as_u64()).filter(|m| length > *m) {
+                errors.push(at(format!("the string is longer than {} characters", maximum)));
+            }
+        }
+        Value::Array(items) => validate_array(root, keywords, items, path, depth, errors),
+        Value::Object(object) => validate_object(root, keywords, object, path, depth, errors),
+        _ => {}
+    }
+
+    // the combinators validate the same value against subschemas, only whether those pass is reported
+    // for anyOf, oneOf and not since the errors of the alternatives that fail don't tell what is wrong
+    if let Option::Some(all) = keywords.get("allOf").and_then(|a| a.as_array()) {
+        for subschema in all.iter() {
+            validate(root, subschema, value, path, depth + 1, errors);
+        }
+    }
+    let passes = |subschema: &Value| {
+        let mut found = Vec::new();
+        validate(root, subschema, value, path, depth + 1, &mut found);
+        found.is_empty()
+    };
+    if let Option::Some(any) = keywords.get("anyOf").and_then(|a| a.as_array()) {
+        if !any.iter().any(passes) {
+            errors.push(at("the value matches none of anyOf".to_string()));
+        }
+    }
+    if let Option::Some(one) = keywords.get("oneOf").and_then(|a| a.as_array()) {
+        let matched = one.iter().filter(|s| passes(s)).count();
+        if matched != 1 {
+            errors.push(at(format!("the value matches {} of oneOf instead of exactly one", matched)));
+        }
+    }
+    if let Option::Some(not) = keywords.get("not") {
+        if passes(not) {
+            errors.push(at("the value matches the schema of not".to_string()));
+        }
+    }
+    if let Option::Some(condition) = keywords.get("if") {
+        let branch = match passes(condition) {
+            true => keywords.get("then"),
+            false => keywords.get("else"),
+        };
+        if let Option::Some(branch) = branch {
+            validate(root, branch, value, path, depth + 1, errors);
+        }
+    }
+}
+
+fn validate_array(root: &Value, keywords: &serde_json::Map<String, Value>, items: &[Value], path: &str, depth: usize, errors: &mut Vec<String>) {
+    let at = |message: String| format!("{}: {}", if path.is_empty() { "/" } else { path }, message);
+    let length = items.len() as u64;
+    if let Option::Some(minimum) = keywords.get("minItems").and_then(|m| m.as_u64()).filter(|m| length < *m) {
+        errors.push(at(format!("the array has fewer than {} items", minimum)));
+    }
+    if let Option::Some(maximum) = keywords.get("maxItems").and_then(|m| m.as_u64()).filter(|m| length > *m) {
+        errors.push(at(format!("the array has more than {} items", maximum)));
+    }
+    if keywords.get("uniqueItems") == Option::Some(&Value::Bool(true)) {
+        let repeated = (0..items.len()).any(|i| items[..i].iter().any(|earlier| equal(earlier, &items[i])));
+        if repeated {
+            errors.push(at("the array has repeated items".to_string()));
+        }
+    }
+
+    // tuples are `prefixItems` with `items` for the rest since 2020-12, and an array of `items` with
+    // `additionalItems` for the rest in draft 7
+    let (prefix, rest) = match (keywords.get("prefixItems"), keywords.get("items")) {
+        (Option::Some(Value::Array(prefix)), rest) => (prefix.as_slice(), rest),
+        (_, Option::Some(Value::Array(prefix))) => (prefix.as_slice(), keywords.get("additionalItems")),
+        (_, rest) => (&[][..], rest),
+    };
+    for (i, item) in items.iter().enumerate() {
+        let subschema = match prefix.get(i) {
+            Option::Some(subschema) => subschema,
+            Option::None => match rest {
+                Option::Some(subschema) => subschema,
+                Option::None => continue,
+            },
+        };
+        validate(root, subschema, item, &format!("{}/{}", path, i), depth + 1, errors);
+    }
+
+    if let Option::Some(contains) = keywords.get("contains") {
+        let found = items.iter().any(|item| {
+            let mut found = Vec::new();
+            validate(root, contains, item, path, depth + 1, &mut found);
+            found.is_empty()
+        });
+        if !found {
+            errors.push(at("no item matches the schema of contains".to_string()));
+        }
+    }
+}
+
+fn validate_object(root: &Value, keywords: &serde_json::Map<String, Value>, object: &serde_json::Map<String, Value>, path: &str, depth: usize, errors: &mut Vec<String>) {
+    let at = |message: String| format!("{}: {}", if path.is_empty() { "/" } else { path }, message);
+    let empty: Vec<Value> = Vec::new();
+    for key in keywords.get("required").and_then(|r| r.as_array()).unwrap_or(&empty).iter().filter_map(|k| k.as_str()) {
+        if !object.contains_key(key) {
+            errors.push(at(format!("the required property {} is missing", key)));
+        }
+    }
+    let length = object.len() as u64;
+    if let Option::Some(minimum) = keywords.get("minProperties").and_then(|m| m.as_u64()).filter(|m| length < *m) {
+        errors.push(at(format!("the object has fewer than {} properties", minimum)));
+    }
+    if let Option::Some(maximum) = keywords.get("maxProperties").and_then(|m| m.as_u64()).filter(|m| length > *m) {
+        errors.push(at(format!("the object has more than {} properties", maximum)));
+    }
+
+    let properties = keywords.get("properties").and_then(|p| p.as_object());
+    for (key, property) in object.iter() {
+        // keys are escaped in json pointers, "~" as "~0" and "/" as "~1"
+        let child = format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"));
+        match properties.and_then(|p| p.get(key)) {
+            Option::Some(subschema) => validate(root, subschema, property, &child, depth + 1, errors),
+            Option::None => {
+                if let Option::Some(additional) = keywords.get("additionalProperties") {
+                    if additional == &Value::Bool(false) {
+                        errors.push(at(format!("the property {} is not allowed", key)));
+                    } else {
+                        validate(root, additional, property, &child, depth + 1, errors);
+                    }
+                }
+            }
+        }
+        if let Option::Some(names) = keywords.get("propertyNames") {
+            validate(root, names, &Value::String(key.clone()), &child, depth + 1, errors);
+        }
+    }
+    if let Option::Some(dependent) = keywords.get("dependentRequired").and_then(|d| d.as_object()) {
+        for (key, required) in dependent.iter().filter(|(key, _)| object.contains_key(*key)) {
+            for other in required.as_array().unwrap_or(&empty).iter().filter_map(|k| k.as_str()) {
+                if !object.contains_key(other) {
+                    errors.push(at(format!("the property {} is required by {}", other, key)));
+                }
+            }
+        }
+    }
+}
+
+// resolve a reference within the schema, "#" for the schema itself and "#/$defs/name" style json
+// pointers into it, references to other documents can't be fetched and don't resolve
+fn resolve<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    match pointer.is_empty() {
+        true => Option::Some(root),
+        false => root.pointer(pointer),
+    }
+}
+
+// whether a value is of a json schema type, integers are numbers without a fractional part
+fn is_type(value: &Value, t: &str) -> bool {
+    match t {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|f| f.fract() == 0.0),
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// json schema equality, numbers are equal by value so 1 and 1.0 are the same
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| equal(x, y)),
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).is_some_and(|w| equal(v, w)))
+        }
+        _ => a == b,
+    }
+}
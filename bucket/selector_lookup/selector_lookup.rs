@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+    DictionaryFormatError{reason: String},
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+            ModuleError::DictionaryFormatError { reason } =>
+                write!(f, "The signature dictionary could not be read. Reason: {}", reason),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // text signatures keyed by 4-byte selector or 32-byte topic0, e.g. {"0xa9059cbb":
+    // "transfer(address,uint256)"}, a key may list several colliding signatures, most probable
+    // first, and a 4byte.directory export of `hex_signature` and `text_signature` objects is read too
+    pub signatures: Value,
+    // the signatures keyed by their lowercase hex, built by set_param
+    #[serde(skip)]
+    pub table: HashMap<String, Vec<String>>,
+}
+
+// shared so that reading the parameters for each record doesn't copy the dictionary
+static PARAMETERS: RwLock<StreamOption<Arc<Parameters>>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let mut parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    parameter.table = read_dictionary(&parameter.signatures)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(Arc::new(parameter));
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    // logs are looked up by their first topic and calls by the selector prefixing their calldata,
+    // records decode_event or decode_function_call already matched are left as they are
+    let key = match input.get("topics").and_then(|v| v.as_array()) {
+        Option::Some(topics) if !input.contains_key("eventName") => topics
+            .first()
+            .and_then(|topic| topic.as_str())
+            .map(normalize_hex),
+        Option::Some(_) => Option::None,
+        Option::None if !input.contains_key("functionName") => input
+            .get("input")
+            .or(input.get("data"))
+            .and_then(|v| v.as_str())
+            .map(normalize_hex)
+            .filter(|calldata| calldata.len() >= 10)
+            .map(|calldata| calldata[..10].to_string()),
+        Option::None => Option::None,
+    };
+
+    if let Option::Some(signatures) = key.and_then(|key| params.table.get(&key)) {
+        let probable = signatures.first().cloned().unwrap_or_default();
+        let name = probable.split('(').next().unwrap_or_default().trim().to_string();
+        input.insert("probableName".to_string(), Value::String(name));
+        input.insert("probableSignature".to_string(), Value::String(probable));
+        input.insert("signatureCandidates".to_string(), serde_json::json!(signatures));
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// read the signature dictionary into a table of lowercase hex to text signatures
+fn read_dictionary(signatures: &Value) -> Result<HashMap<String, Vec<String>>, ModuleError> {
+    let mut table: HashMap<String, Vec<String>> = HashMap::new();
+    let mut add = |hex_signature: &str, text: &Value| -> Result<(), ModuleError> {
+        let texts = match text {
+            Value::String(sig) => vec![sig.clone()],
+            Value::Array(sigs) => sigs.iter().filter_map(|s| s.as_str().map(|s| s.to_string())).collect(),
+            _ => return Err(ModuleError::DictionaryFormatError {
+                reason: format!("the signature of {} is not a string or a list of strings", hex_signature),
+            }),
+        };
+
+        // selectors are 4 bytes and topics 32, anything else can match no record
+        let key = normalize_hex(hex_signature);
+        if key.len() != 10 && key.len() != 66 {
+            return Err(ModuleError::DictionaryFormatError {
+                reason: format!("{} is neither a 4-byte selector nor a 32-byte topic", hex_signature),
+            });
+        }
+        let entry = table.entry(key).or_default();
+        for text in texts.into_iter().map(|t| t.trim().to_string()) {
+            if !entry.contains(&text) {
+                entry.push(text);
+            }
+        }
+        Ok(())
+    };
+
+    match signatures {
+        // a document encoded in a string, e.g. the raw contents of an export file
+        Value::String(document) => match serde_json::from_str::<Value>(document) {
+            Ok(signatures) => return read_dictionary(&signatures),
+            Err(e) => return Err(ModuleError::DictionaryFormatError { reason: e.to_string() }),
+        },
+        // a page of the 4byte.directory api lists its signatures under `results`
+        Value::Object(page) if page.get("results").is_some_and(|r| r.is_array()) => {
+            return read_dictionary(&page["results"]);
+        }
+        Value::Object(entries) => {
+            for (hex_signature, text) in entries.iter() {
+                add(hex_signature, text)?;
+            }
+        }
+        // the 4byte.directory signatures, as exported or gathered from the pages of its api
+        Value::Array(entries) => {
+            for entry in entries.iter() {
+                match (entry["hex_signature"].as_str(), entry.get("text_signature")) {
+                    (Option::Some(hex_signature), Option::Some(text)) => add(hex_signature, text)?,
+                    _ => return Err(ModuleError::DictionaryFormatError {
+                        reason: "expected `hex_signature` and `text_signature` in each listed signature".to_string(),
+                    }),
+                }
+            }
+        }
+        _ => return Err(ModuleError::DictionaryFormatError { reason: "expected a json object or array".to_string() }),
+    }
+    Ok(table)
+}
+
+// normalize a hex string such as a topic or selector to lowercase with a 0x prefix, so sources
+// delivering mixed case or unprefixed hex still match the dictionary
+fn normalize_hex(hex_data: &str) -> String {
+    let clean = hex_data.trim().to_lowercase();
+    format!("0x{}", clean.strip_prefix("0x").unwrap_or(&clean))
+}
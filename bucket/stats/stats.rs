@@ -0,0 +1,195 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // pass the records through as they are counted, otherwise only the summary is emitted
+    #[serde(default = "emit_records_default")]
+    pub emit_records: bool,
+    // the field holding the contract of a record, the emitting contract of a log by default
+    #[serde(default = "contract_field_default")]
+    pub contract_field: String,
+}
+
+fn emit_records_default() -> bool {
+    true
+}
+
+fn contract_field_default() -> String {
+    "address".to_string()
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            emit_records: emit_records_default(),
+            contract_field: contract_field_default(),
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// set once the summary has been emitted, so that the end of stream is passed on after it
+static ENDED: RwLock<bool> = RwLock::new(false);
+
+// what the stream has held so far, a record is decoded when a decoding lens named its event or
+// function and failed when one left a `decodeError` on it
+#[derive(Default)]
+struct Stats {
+    records: u64,
+    decoded: u64,
+    failed: u64,
+    contracts: HashSet<String>,
+    min_block: Option<u64>,
+    max_block: Option<u64>,
+}
+
+static STATS: RwLock<Option<Stats>> = RwLock::new(Option::None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    // new parameters start a new stream, what was counted under the previous ones is forgotten
+    *STATS.write()? = Option::None;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    if *ENDED.read()? {
+        *ENDED.write()? = false;
+        return Ok(EndOfStream);
+    }
+
+    let ptr = unsafe { next() };
+    let input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        // the summary is emitted before the end of stream, even of a stream that held no records, and
+        // counting starts over for the stream that may follow
+        EndOfStream => {
+            let stats = STATS.write()?.take().unwrap_or_default();
+            *ENDED.write()? = true;
+            return Ok(Some(serde_json::to_vec(&stats.summary())?));
+        }
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    let mut current = STATS.write()?;
+    let stats = current.get_or_insert_with(Stats::default);
+    stats.records += 1;
+    if input.get("decodeError").is_some_and(|e| !e.is_null()) {
+        stats.failed += 1;
+    } else if ["eventName", "functionName"].iter().any(|key| input.get(*key).is_some_and(|v| !v.is_null())) {
+        stats.decoded += 1;
+    }
+    if let Option::Some(contract) = input.get(&params.contract_field).and_then(|c| c.as_str()) {
+        stats.contracts.insert(contract.trim().to_lowercase());
+    }
+    if let Option::Some(block) = input.get("blockNumber").and_then(quantity) {
+        stats.min_block = Option::Some(stats.min_block.map_or(block, |min| min.min(block)));
+        stats.max_block = Option::Some(stats.max_block.map_or(block, |max| max.max(block)));
+    }
+    // the stats are released before a record that is not emitted reads the next one
+    drop(current);
+
+    if !params.emit_records {
+        lens_sdk::free_transport_buffer(ptr)?;
+        return try_transform();
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+impl Stats {
+    fn summary(&self) -> Value {
+        serde_json::json!({
+            "recordCount": self.records,
+            "decodeSuccessCount": self.decoded,
+            "decodeFailureCount": self.failed,
+            "distinctContractCount": self.contracts.len(),
+            "minBlockNumber": self.min_block,
+            "maxBlockNumber": self.max_block,
+        })
+    }
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity
+fn quantity(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => u64::from_str_radix(digits, 16).ok(),
+            Option::None => s.trim().parse::<u64>().ok(),
+        },
+        _ => Option::None,
+    }
+}
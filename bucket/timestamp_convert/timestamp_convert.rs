@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+// the lens needs no configuration, parameters are optional and default when never set, each converted
+// `field` gets `fieldIso` and the enabled derived fields alongside it
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // the fields holding unix timestamps in seconds, as json numbers, decimal strings or hex quantities
+    #[serde(default = "fields_default")]
+    pub fields: Vec<String>,
+    // add `fieldDate`, the day in UTC, e.g. "2015-07-30"
+    #[serde(default)]
+    pub date: bool,
+    // add `fieldHour`, the start of the hour in UTC, e.g. "2015-07-30T15:00:00Z"
+    #[serde(default)]
+    pub hour: bool,
+    // add `fieldWeekday`, the day of the week in UTC, e.g. "Thursday"
+    #[serde(default)]
+    pub weekday: bool,
+}
+
+fn fields_default() -> Vec<String> {
+    vec!["timestamp".to_string()]
+}
+
+impl Default for Parameters {
+    fn default() -> Parameters {
+        Parameters {
+            fields: fields_default(),
+            date: false,
+            hour: false,
+            weekday: false,
+        }
+    }
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+// 1970-01-01 was a thursday
+const WEEKDAYS: [&str; 7] = ["Thursday", "Friday", "Saturday", "Sunday", "Monday", "Tuesday", "Wednesday"];
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = match PARAMETERS.read()?.clone() {
+        Some(params) => params,
+        _ => Parameters::default(),
+    };
+
+    // the derived fields are null when the timestamp is missing or isn't a quantity
+    for field in params.fields.iter() {
+        let timestamp = input.get(field).and_then(quantity);
+        let (days, seconds) = (timestamp.map(|t| t / 86400), timestamp.map(|t| t % 86400));
+        let date = days.map(civil_from_days).map(|(y, m, d)| format!("{:04}-{:02}-{:02}", y, m, d));
+
+        let iso = date.as_ref().zip(seconds).map(|(date, s)| {
+            format!("{}T{:02}:{:02}:{:02}Z", date, s / 3600, s % 3600 / 60, s % 60)
+        });
+        input.insert(format!("{}Iso", field), serde_json::json!(iso));
+        if params.date {
+            input.insert(format!("{}Date", field), serde_json::json!(date));
+        }
+        if params.hour {
+            let hour = date.as_ref().zip(seconds).map(|(date, s)| format!("{}T{:02}:00:00Z", date, s / 3600));
+            input.insert(format!("{}Hour", field), serde_json::json!(hour));
+        }
+        if params.weekday {
+            input.insert(format!("{}Weekday", field), serde_json::json!(days.map(|d| WEEKDAYS[(d % 7) as usize])));
+        }
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// the civil date of a day count since 1970-01-01, after Howard Hinnant's civil_from_days,
+// counted in 400-year eras of 146097 days starting on march 1st so leap days fall at the end
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+    (year, month, day)
+}
+
+// read a json number, a decimal string or a 0x-prefixed hex quantity, timestamps past the year 9999
+// don't fit the iso format and are rejected
+fn quantity(value: &Value) -> Option<u64> {
+    let n = match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => match s.trim().strip_prefix("0x") {
+            Option::Some(digits) => u64::from_str_radix(digits, 16).ok(),
+            Option::None => s.trim().parse::<u64>().ok(),
+        },
+        _ => Option::None,
+    };
+    n.filter(|n| *n < 253402300800)
+}
@@ -0,0 +1,315 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::error::Error;
+use std::{fmt, error};
+use serde::Deserialize;
+use lens_sdk::StreamOption;
+use lens_sdk::option::StreamOption::{Some, None, EndOfStream};
+use serde_json::Value;
+
+#[link(wasm_import_module = "lens")]
+extern "C" {
+    fn next() -> *mut u8;
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+enum ModuleError {
+    ParametersNotSetError,
+}
+
+impl error::Error for ModuleError { }
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self {
+            ModuleError::ParametersNotSetError => f.write_str("Parameters have not been set."),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parameters {
+    // e.g. [{"field": "value", "from": "wei", "to": "ether", "precision": 6}]
+    pub conversions: Vec<Conversion>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversion {
+    // the field holding the amount, a json number, a decimal string, which may be signed or fractional,
+    // or a 0x-prefixed hex quantity
+    pub field: String,
+    #[serde(default = "from_default")]
+    pub from: Unit,
+    #[serde(default = "to_default")]
+    pub to: Unit,
+    // the field the converted amount is written to, the field name suffixed with the unit by default,
+    // e.g. `valueEther`
+    #[serde(default)]
+    pub output: Option<String>,
+    // the most fraction digits kept, rounding half away from zero, all of them by default
+    #[serde(default)]
+    pub precision: Option<usize>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    Wei,
+    Kwei,
+    Mwei,
+    Gwei,
+    Szabo,
+    Finney,
+    #[serde(alias = "eth")]
+    Ether,
+}
+
+impl Unit {
+    // the power of ten of wei the unit is worth
+    fn exponent(&self) -> i64 {
+        match self {
+            Unit::Wei => 0,
+            Unit::Kwei => 3,
+            Unit::Mwei => 6,
+            Unit::Gwei => 9,
+            Unit::Szabo => 12,
+            Unit::Finney => 15,
+            Unit::Ether => 18,
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            Unit::Wei => "Wei",
+            Unit::Kwei => "Kwei",
+            Unit::Mwei => "Mwei",
+            Unit::Gwei => "Gwei",
+            Unit::Szabo => "Szabo",
+            Unit::Finney => "Finney",
+            Unit::Ether => "Ether",
+        }
+    }
+}
+
+fn from_default() -> Unit {
+    Unit::Wei
+}
+
+fn to_default() -> Unit {
+    Unit::Ether
+}
+
+static PARAMETERS: RwLock<StreamOption<Parameters>> = RwLock::new(None);
+
+#[no_mangle]
+pub extern fn alloc(size: usize) -> *mut u8 {
+    lens_sdk::alloc(size)
+}
+
+#[no_mangle]
+pub extern fn set_param(ptr: *mut u8) -> *mut u8 {
+    match try_set_param(ptr) {
+        Ok(_) => lens_sdk::nil_ptr(),
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_set_param(ptr: *mut u8) -> Result<(), Box<dyn Error>> {
+    let parameter = lens_sdk::try_from_mem::<Parameters>(ptr)?
+        .ok_or(ModuleError::ParametersNotSetError)?;
+
+    let mut dst = PARAMETERS.write()?;
+    *dst = Some(parameter);
+    Ok(())
+}
+
+#[no_mangle]
+pub extern fn transform() -> *mut u8 {
+    match try_transform() {
+        Ok(o) => match o {
+            Some(result_json) => lens_sdk::to_mem(lens_sdk::JSON_TYPE_ID, &result_json),
+            None => lens_sdk::nil_ptr(),
+            EndOfStream => lens_sdk::to_mem(lens_sdk::EOS_TYPE_ID, &[]),
+        },
+        Err(e) => lens_sdk::to_mem(lens_sdk::ERROR_TYPE_ID, &e.to_string().as_bytes())
+    }
+}
+
+fn try_transform() -> Result<StreamOption<Vec<u8>>, Box<dyn Error>> {
+    let ptr = unsafe { next() };
+    let mut input = match lens_sdk::try_from_mem::<HashMap<String, serde_json::Value>>(ptr)? {
+        Some(v) => v,
+        // Implementations of `transform` are free to handle nil however they like. In this
+        // implementation we chose to return nil given a nil input.
+        None => return Ok(None),
+        EndOfStream => return Ok(EndOfStream)
+    };
+
+    let params = PARAMETERS.read()?
+        .clone()
+        .ok_or(ModuleError::ParametersNotSetError)?
+        .clone();
+
+    // amounts that are missing or aren't numbers are converted to null
+    for conversion in params.conversions.iter() {
+        let converted = input
+            .get(&conversion.field)
+            .and_then(decimal)
+            .map(|(negative, digits, scale)| {
+                let scale = scale - (conversion.from.exponent() - conversion.to.exponent());
+                format_decimal(negative, digits, scale, conversion.precision)
+            });
+        let output = conversion
+            .output
+            .clone()
+            .unwrap_or(format!("{}{}", conversion.field, conversion.to.suffix()));
+        input.insert(output, serde_json::json!(converted));
+    }
+
+    let result_json = serde_json::to_vec(&input)?;
+    lens_sdk::free_transport_buffer(ptr)?;
+    Ok(Some(result_json))
+}
+
+// read an amount as its sign, its decimal digits and the count of them that are fraction digits,
+// the amount being digits * 10^-scale
+fn decimal(value: &Value) -> Option<(bool, String, i64)> {
+    let text = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => return Option::None,
+    };
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Option::Some(unsigned) => (true, unsigned),
+        Option::None => (false, text.as_str()),
+    };
+
+    if let Option::Some(digits) = unsigned.strip_prefix("0x") {
+        // quantities are encoded without leading zeros so their digit count may be odd
+        let padded = format!("{:0>width$}", digits, width = digits.len() + digits.len() % 2);
+        if padded.len() > 64 {
+            return Option::None;
+        }
+        let n = U256::from_be_slice(&hex::decode(padded).ok()?);
+        return Option::Some((negative, n.to_string(), 0));
+    }
+
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let digits = format!("{}{}", whole, fraction);
+    match !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        true => Option::Some((negative, digits, fraction.len() as i64)),
+        false => Option::None,
+    }
+}
+
+// format digits * 10^-scale as a decimal string, without leading zeros in the whole part nor
+// trailing zeros in the fraction, rounding off the fraction digits beyond `precision`
+fn format_decimal(negative: bool, mut digits: String, mut scale: i64, precision: Option<usize>) -> String {
+    // a negative scale shifts the point right of the digits
+    if scale < 0 {
+        digits.push_str(&"0".repeat(-scale as usize));
+        scale = 0;
+    }
+    let mut scale = scale as usize;
+    if digits.len() <= scale {
+        digits = format!("{:0>width$}", digits, width = scale + 1);
+    }
+
+    if let Option::Some(precision) = precision.filter(|p| *p < scale) {
+        let cut = digits.len() - (scale - precision);
+        let round_up = digits.as_bytes()[cut] >= b'5';
+        digits.truncate(cut);
+        if round_up {
+            digits = increment(&digits);
+        }
+        scale = precision;
+    }
+
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    let whole = match whole.trim_start_matches('0') {
+        "" => "0",
+        whole => whole,
+    };
+    let fraction = fraction.trim_end_matches('0');
+    let sign = match negative && (whole != "0" || !fraction.is_empty()) {
+        true => "-",
+        false => "",
+    };
+    match fraction.is_empty() {
+        true => format!("{}{}", sign, whole),
+        false => format!("{}{}.{}", sign, whole, fraction),
+    }
+}
+
+// add one to a string of decimal digits, which may grow it by a digit
+fn increment(digits: &str) -> String {
+    let mut bytes = digits.as_bytes().to_vec();
+    for b in bytes.iter_mut().rev() {
+        if *b == b'9' {
+            *b = b'0';
+        } else {
+            *b += 1;
+            return String::from_utf8(bytes).unwrap_or_default();
+        }
+    }
+    format!("1{}", String::from_utf8(bytes).unwrap_or_default())
+}
+
+// minimal 256-bit unsigned integer, limbs are stored least significant first
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct U256([u64; 4]);
+
+impl U256 {
+    // build from up to 32 big-endian bytes, longer inputs keep their low 32 bytes
+    fn from_be_slice(bytes: &[u8]) -> U256 {
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut limbs = [0u64; 4];
+        for (i, b) in bytes.iter().rev().enumerate() {
+            limbs[i / 8] |= (*b as u64) << ((i % 8) * 8);
+        }
+        U256(limbs)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.iter().all(|l| *l == 0)
+    }
+
+    // divide by a small divisor, returning the quotient and the remainder
+    fn div_rem(self, divisor: u64) -> (U256, u64) {
+        let mut limbs = [0u64; 4];
+        let mut remainder = 0u128;
+        for i in (0..4).rev() {
+            let acc = (remainder << 64) | self.0[i] as u128;
+            limbs[i] = (acc / divisor as u128) as u64;
+            remainder = acc % divisor as u128;
+        }
+        (U256(limbs), remainder as u64)
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+
+        // peel off 19 decimal digits at a time, the largest power of ten that fits a u64
+        let mut chunks = Vec::new();
+        let mut n = *self;
+        while !n.is_zero() {
+            let (q, r) = n.div_rem(10_000_000_000_000_000_000);
+            chunks.push(r);
+            n = q;
+        }
+        let mut out = chunks.pop().unwrap_or_default().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{:019}", chunk));
+        }
+        f.write_str(&out)
+    }
+}